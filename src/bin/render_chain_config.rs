@@ -1,6 +1,18 @@
-use rpc_proxy::chain_config::ACTIVE_CONFIG;
+use rpc_proxy::chain_config::load_active_config;
 
+/// Render the chains config for the monitoring, optionally from the provider
+/// catalogue file: `render_chain_config [--markdown] [catalogue.toml]`.
+/// With `--markdown` the `SUPPORTED_CHAINS.md` table is printed as well.
 fn main() {
-    let json = serde_json::to_string_pretty(&*ACTIVE_CONFIG).unwrap();
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let markdown = args.iter().any(|arg| arg == "--markdown");
+    let catalogue_path = args.iter().find(|arg| !arg.starts_with("--"));
+
+    let config = load_active_config(catalogue_path.map(String::as_str))
+        .unwrap_or_else(|e| panic!("Failed to load the chains config: {e}"));
+    let json = serde_json::to_string_pretty(&config).unwrap();
     std::fs::write("terraform/monitoring/chain_config.json", json).unwrap();
+    if markdown {
+        print!("{}", config.render_supported_chains());
+    }
 }
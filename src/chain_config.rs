@@ -1,6 +1,14 @@
-use crate::providers::Priority;
-use serde::Serialize;
-use std::sync::LazyLock;
+use crate::providers::{
+    capabilities::ProviderCapabilities, http_client::UpstreamClientConfig, Priority, ProviderKind,
+    Weight,
+};
+use hyper::http::{HeaderName, HeaderValue};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::{
+    collections::{BTreeMap, HashSet},
+    str::FromStr,
+    sync::LazyLock,
+};
 
 // For now, remember to run `just render-config` after updating the config
 // TODO in the future, we will pass this via TF variable and generate the chain_config.json file in the CI pipeline
@@ -8,7 +16,8 @@ use std::sync::LazyLock;
 // Splitting out only the YAML into separate crate would allow quickly generating the JSON file
 
 // Keep in-sync with SUPPORTED_CHAINS.md
-// In the future we could auto-generate the Markdown file
+// The Markdown table can be rendered with `just render-config --markdown`
+// The catalogue file set by `RPC_PROXY_PROVIDER_CATALOGUE_PATH` replaces this config
 pub static ACTIVE_CONFIG: LazyLock<Config> = LazyLock::new(|| Config {
     chains: vec![
         ChainConfig {
@@ -322,27 +331,530 @@ pub static ACTIVE_CONFIG: LazyLock<Config> = LazyLock::new(|| Config {
             providers: vec![],
         },
     ],
+    http_clients: BTreeMap::new(),
+    provider_capabilities: BTreeMap::new(),
 });
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub chains: Vec<ChainConfig>,
+    /// Upstream HTTP client settings per provider name, e.g. `Quicknode`
+    /// or the generic provider `kind`
+    #[serde(default, skip_serializing)]
+    pub http_clients: BTreeMap<String, UpstreamClientConfig>,
+    /// Capabilities of the built-in providers per provider name and chain id
+    #[serde(default, skip_serializing)]
+    pub provider_capabilities: BTreeMap<String, BTreeMap<String, ProviderCapabilities>>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChainConfig {
     pub caip2: String,
     pub name: String,
+    #[serde(default)]
     pub providers: Vec<ProviderConfig>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProviderConfig {
     pub url: String,
-    #[serde(skip)]
+    #[serde(
+        skip_serializing,
+        default = "default_priority",
+        deserialize_with = "deserialize_priority"
+    )]
     pub priority: Priority,
+    /// Provider name used for the provider kind instead of the URL-based id
+    #[serde(default, skip_serializing)]
+    pub kind: Option<String>,
+    /// WebSocket endpoint of the provider
+    #[serde(default, skip_serializing)]
+    pub ws_url: Option<String>,
+    /// Headers sent with every provider request, e.g. the API key header
+    #[serde(default, skip_serializing)]
+    pub headers: BTreeMap<String, String>,
+    /// Authentication scheme of the provider requests
+    #[serde(default, skip_serializing)]
+    pub auth: Option<ProviderAuth>,
+    /// Provider specific rate-limited responses detection
+    #[serde(default, skip_serializing)]
+    pub rate_limit: Option<RateLimitDetection>,
+    /// Archive, trace, debug and txpool capabilities of the provider
+    #[serde(default, skip_serializing)]
+    pub capabilities: Option<ProviderCapabilities>,
 }
 
-// TODO
-// - env var: RPC_PROXY_RPC_CONFIG_VAR_my_api_key=""
-//   - use in-side of `url` via `<my_api_key>`
+/// Provider authentication scheme. The `url`, `ws_url`, headers values and
+/// the authentication fields can reference the secrets from the
+/// `RPC_PROXY_RPC_CONFIG_VAR_<name>` env vars as `<name>`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ProviderAuth {
+    /// `Authorization: Bearer` header
+    Bearer { token: String },
+    /// `Authorization: Basic` header
+    Basic { username: String, password: String },
+    /// API key query parameter added to the `url` and `ws_url`
+    QueryParam { name: String, value: String },
+}
+
+/// Rate-limited responses are detected by the `429` status code and the
+/// additional status codes or response body patterns
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimitDetection {
+    #[serde(default)]
+    pub status_codes: Vec<u16>,
+    /// Substrings of the response body, e.g. the JSON-RPC error message
+    #[serde(default)]
+    pub body_patterns: Vec<String>,
+}
+
+impl RateLimitDetection {
+    pub fn is_rate_limited(&self, status: u16, body: &[u8]) -> bool {
+        status == 429
+            || self.status_codes.contains(&status)
+            || (!self.body_patterns.is_empty()
+                && std::str::from_utf8(body).is_ok_and(|body| {
+                    self.body_patterns
+                        .iter()
+                        .any(|pattern| body.contains(pattern.as_str()))
+                }))
+    }
+}
+
+fn default_priority() -> Priority {
+    Priority::Normal
+}
+
+/// Priority is either the priority name (`Max`, `High`, `Normal`, `Low`,
+/// `Minimal`, `Disabled`) or the custom priority value
+fn deserialize_priority<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Priority, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum PriorityRepr {
+        Value(u64),
+        Name(String),
+    }
+
+    match PriorityRepr::deserialize(deserializer)? {
+        PriorityRepr::Value(value) => Ok(Priority::Custom(value)),
+        PriorityRepr::Name(name) => Priority::from_str(&name)
+            .map_err(|_| serde::de::Error::custom(format!("unknown priority `{name}`"))),
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CatalogueError {
+    #[error("Failed to read the provider catalogue {path}: {source}")]
+    Read {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("Failed to parse the provider catalogue: {0}")]
+    Parse(#[from] toml::de::Error),
+    #[error("Invalid chain {chain} in the provider catalogue: {reason}")]
+    InvalidChain { chain: String, reason: String },
+    #[error("Invalid provider #{index} of the chain {chain} in the provider catalogue: {reason}")]
+    InvalidProvider {
+        chain: String,
+        index: usize,
+        reason: String,
+    },
+    #[error("Invalid capabilities of the provider {provider} in the provider catalogue: {reason}")]
+    InvalidCapabilities { provider: String, reason: String },
+}
+
+impl Config {
+    /// Load and validate the TOML provider catalogue file
+    pub fn load(path: &str) -> Result<Self, CatalogueError> {
+        let content = std::fs::read_to_string(path).map_err(|source| CatalogueError::Read {
+            path: path.to_string(),
+            source,
+        })?;
+        Self::from_toml(&content)
+    }
+
+    /// Parse and validate the TOML provider catalogue
+    pub fn from_toml(content: &str) -> Result<Self, CatalogueError> {
+        let config = toml::from_str::<Self>(content)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), CatalogueError> {
+        let mut chains = HashSet::new();
+        for chain in &self.chains {
+            let invalid_chain = |reason: &str| CatalogueError::InvalidChain {
+                chain: chain.caip2.clone(),
+                reason: reason.to_string(),
+            };
+            let is_valid_caip2 =
+                chain
+                    .caip2
+                    .split_once(':')
+                    .is_some_and(|(namespace, reference)| {
+                        !namespace.is_empty()
+                            && !reference.is_empty()
+                            && !chain.caip2.contains(char::is_whitespace)
+                    });
+            if !is_valid_caip2 {
+                return Err(invalid_chain(
+                    "expected the CAIP-2 `namespace:reference` chain id",
+                ));
+            }
+            if chain.name.trim().is_empty() {
+                return Err(invalid_chain("empty chain name"));
+            }
+            if !chains.insert(chain.caip2.as_str()) {
+                return Err(invalid_chain("duplicate chain"));
+            }
+
+            let mut providers = HashSet::new();
+            for (index, provider) in chain.providers.iter().enumerate() {
+                provider
+                    .validate()
+                    .and_then(|_| {
+                        providers
+                            .insert(provider.kind.as_ref().unwrap_or(&provider.url))
+                            .then_some(())
+                            .ok_or_else(|| "duplicate provider kind or URL".to_string())
+                    })
+                    .map_err(|reason| CatalogueError::InvalidProvider {
+                        chain: chain.caip2.clone(),
+                        index,
+                        reason,
+                    })?;
+            }
+        }
+
+        for (provider, chains) in &self.provider_capabilities {
+            let invalid_capabilities = |reason: String| CatalogueError::InvalidCapabilities {
+                provider: provider.clone(),
+                reason,
+            };
+            if matches!(
+                ProviderKind::from_str(provider),
+                None | Some(ProviderKind::Generic(_))
+            ) {
+                return Err(invalid_capabilities(
+                    "unknown built-in provider, generic providers declare the capabilities \
+                     in the chain providers"
+                        .to_string(),
+                ));
+            }
+            if let Some(chain) = chains.keys().find(|chain| !chain.contains(':')) {
+                return Err(invalid_capabilities(format!(
+                    "expected the CAIP-2 chain id instead of `{chain}`"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Markdown table of the chains in the `SUPPORTED_CHAINS.md` format
+    pub fn render_supported_chains(&self) -> String {
+        let mut table = format!(
+            "| {:<56} | {:<20} |\n|{}|{}|\n",
+            "Network",
+            "Chain ID",
+            "-".repeat(58),
+            "-".repeat(22)
+        );
+        for chain in &self.chains {
+            table.push_str(&format!("| {:<56} | {:<20} |\n", chain.name, chain.caip2));
+        }
+        table
+    }
+}
+
+impl ProviderConfig {
+    /// Provider config with the `<name>` secrets references replaced by the
+    /// `RPC_PROXY_RPC_CONFIG_VAR_<name>` env vars values
+    pub fn with_secrets(&self) -> Result<Self, String> {
+        self.resolve_templates(&|name| std::env::var(format!("{CONFIG_VAR_ENV_PREFIX}{name}")).ok())
+    }
+
+    fn resolve_templates(&self, lookup: &dyn Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let resolve = |field: &str, template: &str| {
+            resolve_template(template, lookup).map_err(|e| format!("{field}: {e}"))
+        };
+        Ok(Self {
+            url: resolve("url", &self.url)?,
+            ws_url: self
+                .ws_url
+                .as_ref()
+                .map(|ws_url| resolve("ws_url", ws_url))
+                .transpose()?,
+            headers: self
+                .headers
+                .iter()
+                .map(|(name, value)| Ok((name.clone(), resolve(name, value)?)))
+                .collect::<Result<_, String>>()?,
+            auth: self
+                .auth
+                .as_ref()
+                .map(|auth| {
+                    Ok::<_, String>(match auth {
+                        ProviderAuth::Bearer { token } => ProviderAuth::Bearer {
+                            token: resolve("auth.token", token)?,
+                        },
+                        ProviderAuth::Basic { username, password } => ProviderAuth::Basic {
+                            username: resolve("auth.username", username)?,
+                            password: resolve("auth.password", password)?,
+                        },
+                        ProviderAuth::QueryParam { name, value } => ProviderAuth::QueryParam {
+                            name: name.clone(),
+                            value: resolve("auth.value", value)?,
+                        },
+                    })
+                })
+                .transpose()?,
+            ..self.clone()
+        })
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        self.with_secrets()?.validate_resolved()?;
+        if let Some(rate_limit) = &self.rate_limit {
+            if let Some(code) = rate_limit
+                .status_codes
+                .iter()
+                .find(|code| !(100..=599).contains(*code))
+            {
+                return Err(format!("rate_limit: invalid status code {code}"));
+            }
+            if rate_limit.body_patterns.iter().any(String::is_empty) {
+                return Err("rate_limit: empty body pattern".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_resolved(&self) -> Result<(), String> {
+        validate_url(&self.url, &["http", "https"]).map_err(|e| format!("url: {e}"))?;
+        if let Some(ws_url) = &self.ws_url {
+            validate_url(ws_url, &["ws", "wss"]).map_err(|e| format!("ws_url: {e}"))?;
+        }
+        Weight::new(self.priority).map_err(|e| format!("priority: {e}"))?;
+        if let Some(kind) = &self.kind {
+            if kind.is_empty()
+                || !kind
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return Err(format!(
+                    "kind: `{kind}` must contain only the alphanumeric, `-` and `_` characters"
+                ));
+            }
+        }
+        for (name, value) in &self.headers {
+            HeaderName::from_str(name).map_err(|e| format!("header `{name}`: {e}"))?;
+            HeaderValue::from_str(value).map_err(|e| format!("header `{name}` value: {e}"))?;
+        }
+        match &self.auth {
+            Some(ProviderAuth::Bearer { token }) if token.is_empty() => {
+                return Err("auth: empty bearer token".to_string());
+            }
+            Some(ProviderAuth::Bearer { token }) => {
+                HeaderValue::from_str(token).map_err(|e| format!("auth.token: {e}"))?;
+            }
+            Some(ProviderAuth::Basic { username, .. }) if username.is_empty() => {
+                return Err("auth: empty basic auth username".to_string());
+            }
+            Some(ProviderAuth::QueryParam { name, value })
+                if name.is_empty() || value.is_empty() =>
+            {
+                return Err("auth: empty query parameter name or value".to_string());
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Env vars prefix of the secrets referenced in the provider config
+const CONFIG_VAR_ENV_PREFIX: &str = "RPC_PROXY_RPC_CONFIG_VAR_";
+
+/// Replace the `<name>` references in the template with the variables values.
+/// Only the alphanumeric and `_` names are references, other `<` are kept.
+fn resolve_template(
+    template: &str,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<String, String> {
+    let mut resolved = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('<') {
+        resolved.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let reference = after.find('>').map(|end| &after[..end]).filter(|name| {
+            !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        });
+        match reference {
+            Some(name) => {
+                let value = lookup(name).ok_or_else(|| {
+                    format!("undefined secret `{name}`, set {CONFIG_VAR_ENV_PREFIX}{name}")
+                })?;
+                resolved.push_str(&value);
+                rest = &after[name.len() + 1..];
+            }
+            None => {
+                resolved.push('<');
+                rest = after;
+            }
+        }
+    }
+    resolved.push_str(rest);
+    Ok(resolved)
+}
+
+fn validate_url(url: &str, schemes: &[&str]) -> Result<(), String> {
+    let parsed = url::Url::parse(url).map_err(|e| format!("`{url}` is not a valid URL: {e}"))?;
+    if !schemes.contains(&parsed.scheme()) {
+        return Err(format!(
+            "`{url}` scheme must be one of {}",
+            schemes.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+/// Chains config from the provider catalogue file if set,
+/// or the built-in active config otherwise
+pub fn load_active_config(catalogue_path: Option<&str>) -> Result<Config, CatalogueError> {
+    match catalogue_path {
+        Some(path) => Config::load(path),
+        None => Ok(ACTIVE_CONFIG.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_active_config_is_valid() {
+        ACTIVE_CONFIG.validate().unwrap();
+    }
+
+    #[test]
+    fn test_catalogue_from_toml() {
+        let config = Config::from_toml(include_str!("../providers.example.toml")).unwrap();
+        let provider = &config.chains[0].providers[0];
+        assert_eq!(provider.priority, Priority::High);
+        assert_eq!(provider.kind.as_deref(), Some("example"));
+        assert!(provider.ws_url.is_some());
+        assert_eq!(config.chains[0].providers[1].priority, Priority::Custom(10));
+        assert_eq!(
+            config.http_clients["example"].pool_max_idle_per_host,
+            Some(128)
+        );
+        assert_eq!(
+            provider
+                .capabilities
+                .as_ref()
+                .and_then(|capabilities| capabilities.max_logs_block_range),
+            Some(5000)
+        );
+        assert!(config.provider_capabilities["Quicknode"]["eip155:1"].trace);
+        assert_eq!(
+            config.provider_capabilities["Quicknode"]["eip155:1"].max_batch_size,
+            Some(100)
+        );
+        assert!(config.render_supported_chains().contains(&format!(
+            "| {:<56} | {:<20} |",
+            "Ethereum Mainnet", "eip155:1"
+        )));
+    }
+
+    #[test]
+    fn test_catalogue_validation_errors() {
+        let error = Config::from_toml(
+            r#"
+            [[chains]]
+            caip2 = "eip155:1"
+            name = "Ethereum Mainnet"
+
+            [[chains.providers]]
+            url = "wss://rpc.example.com"
+            "#,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            CatalogueError::InvalidProvider { index: 0, .. }
+        ));
+
+        let error = Config::from_toml(
+            r#"
+            [[chains]]
+            caip2 = "eip155"
+            name = "Ethereum Mainnet"
+            "#,
+        )
+        .unwrap_err();
+        assert!(matches!(error, CatalogueError::InvalidChain { .. }));
+
+        let error = Config::from_toml(
+            r#"
+            [[chains]]
+            caip2 = "eip155:1"
+            name = "Ethereum Mainnet"
+
+            [[chains.providers]]
+            url = "https://rpc.example.com"
+            priority = "Highest"
+            "#,
+        )
+        .unwrap_err();
+        assert!(matches!(error, CatalogueError::Parse(_)));
+    }
+
+    #[test]
+    fn test_resolve_template() {
+        let lookup = |name: &str| (name == "api_key").then(|| "secret".to_string());
+        assert_eq!(
+            resolve_template("https://rpc.example.com/<api_key>", &lookup).unwrap(),
+            "https://rpc.example.com/secret"
+        );
+        assert_eq!(
+            resolve_template("a < b <not a ref> <api_key>", &lookup).unwrap(),
+            "a < b <not a ref> secret"
+        );
+        assert!(resolve_template("https://rpc.example.com/<missing>", &lookup).is_err());
+
+        let provider: ProviderConfig = toml::from_str(
+            r#"
+            url = "https://rpc.example.com/v1/<api_key>"
+            auth = { type = "basic", username = "user", password = "<api_key>" }
+            headers = { "x-api-key" = "<api_key>" }
+            "#,
+        )
+        .unwrap();
+        let resolved = provider.resolve_templates(&lookup).unwrap();
+        assert_eq!(resolved.url, "https://rpc.example.com/v1/secret");
+        assert_eq!(resolved.headers["x-api-key"], "secret");
+        assert_eq!(
+            resolved.auth,
+            Some(ProviderAuth::Basic {
+                username: "user".to_string(),
+                password: "secret".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_rate_limit_detection() {
+        let detection = RateLimitDetection {
+            status_codes: vec![403],
+            body_patterns: vec!["capacity exceeded".to_string()],
+        };
+        assert!(detection.is_rate_limited(429, b""));
+        assert!(detection.is_rate_limited(403, b""));
+        assert!(detection.is_rate_limited(
+            200,
+            br#"{"error":{"code":-32005,"message":"capacity exceeded"}}"#
+        ));
+        assert!(!detection.is_rate_limited(200, br#"{"result":"0x1"}"#));
+        assert!(!RateLimitDetection::default().is_rate_limited(403, b""));
+    }
+}
@@ -1,6 +1,7 @@
 use {
     crate::database::error::DatabaseError,
     chrono::{DateTime, Utc},
+    serde::Serialize,
     sqlx::{FromRow, PgExecutor, Postgres},
 };
 
@@ -178,3 +179,49 @@ pub async fn expire_old_pending(
         .await?;
     Ok(res.rows_affected())
 }
+
+/// Pending transactions of the exchange waiting for the reconciliation
+#[derive(Debug, FromRow, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingBacklog {
+    pub exchange_id: String,
+    pub pending: i64,
+    /// Transactions which can be claimed by the next reconciliation batch
+    pub due: i64,
+    /// Transactions locked by the reconciliation in progress
+    pub locked: i64,
+    pub oldest_created_at: Option<DateTime<Utc>>,
+    pub oldest_checked_at: Option<DateTime<Utc>>,
+}
+
+pub async fn pending_backlog(
+    executor: impl PgExecutor<'_>,
+) -> Result<Vec<PendingBacklog>, DatabaseError> {
+    let query = r#"
+        SELECT
+            exchange_id,
+            COUNT(*) AS pending,
+            COUNT(*) FILTER (
+                WHERE (locked_at IS NULL OR locked_at < NOW() - make_interval(mins => $1))
+                  AND (last_checked_at IS NULL OR last_checked_at < NOW() - make_interval(mins => $2))
+                  AND created_at < NOW() - make_interval(hours => $3)
+            ) AS due,
+            COUNT(*) FILTER (
+                WHERE locked_at >= NOW() - make_interval(mins => $1)
+            ) AS locked,
+            MIN(created_at) AS oldest_created_at,
+            MIN(last_checked_at) AS oldest_checked_at
+        FROM exchange_reconciliation_ledger
+        WHERE status = 'pending'
+        GROUP BY exchange_id
+        ORDER BY exchange_id
+    "#;
+
+    let rows = sqlx::query_as::<Postgres, PendingBacklog>(query)
+        .bind(LOCK_EXPIRATION_MINUTES)
+        .bind(CHECK_BACKOFF_MINUTES)
+        .bind(MIN_CLAIM_AGE_HOURS)
+        .fetch_all(executor)
+        .await?;
+    Ok(rows)
+}
@@ -7,6 +7,9 @@ use {
 pub struct GenericConfig {
     pub caip2: String,
     pub name: String,
+    /// Catalogue provider kind, the settings of the named providers are
+    /// shared across the chains
+    pub kind: Option<String>,
     pub provider: chain_config::ProviderConfig,
 }
 
@@ -22,10 +25,20 @@ impl ProviderConfig for GenericConfig {
     }
 
     fn supported_ws_chains(self) -> HashMap<String, (String, Weight)> {
-        HashMap::new()
+        let Some(ws_url) = self.provider.ws_url else {
+            return HashMap::new();
+        };
+        HashMap::from([(
+            self.caip2,
+            (ws_url, Weight::new(self.provider.priority).unwrap()),
+        )])
     }
 
     fn provider_kind(&self) -> crate::providers::ProviderKind {
+        // Named providers are distinguished per chain by the chain id suffix
+        if let Some(kind) = &self.kind {
+            return crate::providers::ProviderKind::Generic(format!("{kind}-{}", self.caip2));
+        }
         let deterministic_id = base64::engine::general_purpose::STANDARD
             .encode(format!("{}-{}", self.caip2, self.provider.url));
         crate::providers::ProviderKind::Generic(deterministic_id)
@@ -123,10 +123,11 @@ mod test {
             env::{Config, ServerConfig},
             handlers::balance::Config as BalanceConfig,
             handlers::json_rpc::exchanges::Config as ExchangesConfig,
+            handlers::proxy::retry::BroadcastMode,
             names::Config as NamesConfig,
             profiler::ProfilerConfig,
             project,
-            providers::ProvidersConfig,
+            providers::{health::WeightsSource, ProvidersConfig},
             storage::irn::Config as IrnConfig,
             utils::rate_limit::RateLimitingConfig,
         },
@@ -145,6 +146,7 @@ mod test {
             ("RPC_PROXY_BLOCKED_COUNTRIES", "KP,IR,CU,SY"),
             ("RPC_PROXY_GEOIP_DB_BUCKET", "GEOIP_DB_BUCKET"),
             ("RPC_PROXY_GEOIP_DB_KEY", "GEOIP_DB_KEY"),
+            ("RPC_PROXY_ADMIN_TOKEN", "ADMIN_TOKEN"),
             // Integration tests config.
             ("RPC_PROXY_TESTING_PROJECT_ID", "TESTING_PROJECT_ID"),
             // Registry config.
@@ -182,6 +184,14 @@ mod test {
             ("RPC_PROXY_ANALYTICS_S3_ENDPOINT", "s3://127.0.0.1"),
             ("RPC_PROXY_ANALYTICS_EXPORT_BUCKET", "EXPORT_BUCKET"),
             // Providers config
+            ("RPC_PROXY_PROVIDER_WEIGHTS_SOURCE", "local"),
+            ("RPC_PROXY_PROVIDER_HEAD_TRACKER_ENABLED", "true"),
+            ("RPC_PROXY_PROVIDER_TX_TRACKER_ENABLED", "true"),
+            ("RPC_PROXY_PROVIDER_WRITE_BROADCAST_MODE", "all"),
+            (
+                "RPC_PROXY_PROVIDER_COALESCING_METHODS",
+                "eth_blockNumber,eth_gasPrice",
+            ),
             (
                 "RPC_PROXY_PROVIDER_CACHE_REDIS_ADDR",
                 "redis://127.0.0.1/providers_cache",
@@ -232,6 +242,20 @@ mod test {
                 "CALLSTATIC_API_KEY",
             ),
             ("RPC_PROXY_PROVIDER_BLAST_API_KEY", "BLAST_API_KEY"),
+            ("RPC_PROXY_PROVIDER_PROVIDER_BUDGETS", "Quicknode:50:"),
+            ("RPC_PROXY_PROVIDER_LOGS_SPLITTING_ENABLED", "true"),
+            ("RPC_PROXY_PROVIDER_LOGS_SPLIT_MAX_CONCURRENCY", "4"),
+            (
+                "RPC_PROXY_PROVIDER_PRIVATE_TX_RELAYS",
+                "eip155:1=https://relay.example.com/fast",
+            ),
+            (
+                "RPC_PROXY_PROVIDER_METHOD_DENYLIST",
+                "debug_*@tier:free,eth_sendRawTransaction@project:readonly",
+            ),
+            ("RPC_PROXY_PROVIDER_METHOD_ACCESS_FALLBACK_TIER", "free"),
+            ("RPC_PROXY_PROVIDER_UPSTREAM_CONNECT_TIMEOUT_MS", "2000"),
+            ("RPC_PROXY_PROVIDER_UPSTREAM_POOL_MAX_IDLE_PER_HOST", "64"),
             // Postgres config.
             (
                 "RPC_PROXY_POSTGRES_URI",
@@ -307,6 +331,7 @@ mod test {
                     testing_project_id: Some("TESTING_PROJECT_ID".to_owned()),
                     validate_project_id: true,
                     skip_quota_chains: vec![],
+                    admin_token: Some("ADMIN_TOKEN".to_owned()),
                 },
                 registry: project::Config {
                     api_url: Some("API_URL".to_owned()),
@@ -343,7 +368,64 @@ mod test {
                 providers: ProvidersConfig {
                     prometheus_query_url: Some("PROMETHEUS_QUERY_URL".to_owned()),
                     prometheus_workspace_header: Some("PROMETHEUS_WORKSPACE_HEADER".to_owned()),
+                    weights_source: Some(WeightsSource::Local),
+                    health_window_secs: None,
+                    circuit_breaker_failure_threshold: None,
+                    circuit_breaker_cooldown_ms: None,
+                    rate_limit_backoff_ms: None,
+                    provider_budgets: Some(vec!["Quicknode:50:".to_owned()]),
+                    hedging_enabled: None,
+                    hedging_latency_percentile: None,
+                    hedging_max_delay_ms: None,
+                    batch_splitting_enabled: None,
+                    batch_max_size: None,
+                    batch_max_items: None,
+                    batch_split_max_concurrency: None,
+                    logs_splitting_enabled: Some(true),
+                    logs_split_max_depth: None,
+                    logs_split_max_concurrency: Some(4),
+                    method_allowlist: None,
+                    method_denylist: Some(vec![
+                        "debug_*@tier:free".to_owned(),
+                        "eth_sendRawTransaction@project:readonly".to_owned(),
+                    ]),
+                    method_access_fallback_tier: Some("free".to_owned()),
+                    private_tx_relays: Some(vec![
+                        "eip155:1=https://relay.example.com/fast".to_owned()
+                    ]),
+                    private_tx_projects: None,
+                    sticky_session_window_secs: None,
+                    coalescing_methods: Some(vec![
+                        "eth_blockNumber".to_owned(),
+                        "eth_gasPrice".to_owned(),
+                    ]),
+                    head_tracker_enabled: Some(true),
+                    head_tracker_interval_secs: None,
+                    tx_tracker_enabled: Some(true),
+                    tx_tracker_interval_secs: None,
+                    tx_tracker_drop_timeout_secs: None,
+                    write_broadcast_mode: Some(BroadcastMode::All),
+                    read_retry_budget: None,
+                    read_deadline_ms: None,
+                    ws_max_reconnects: None,
+                    ws_subscription_hub_enabled: None,
+                    ws_allowed_methods: None,
+                    ws_quota_check_interval_secs: None,
+                    ws_emulation_enabled: None,
+                    ws_emulation_poll_interval_ms: None,
+                    ws_emulation_max_subscriptions: None,
+                    ws_emulation_max_in_flight_calls: None,
+                    catalogue_path: None,
+                    catalogue_reload_interval_secs: None,
+                    upstream_connect_timeout_ms: Some(2000),
+                    upstream_timeout_ms: None,
+                    upstream_pool_max_idle_per_host: Some(64),
+                    upstream_pool_idle_timeout_secs: None,
+                    upstream_tcp_keepalive_secs: None,
+                    upstream_http2_prior_knowledge: None,
+                    upstream_compression: None,
                     cache_redis_addr: Some("redis://127.0.0.1/providers_cache".to_owned()),
+                    json_rpc_cache_redis_enabled: None,
                     pokt_project_id: "POKT_PROJECT_ID".to_string(),
                     quicknode_api_tokens: "QUICKNODE_API_TOKENS".to_string(),
                     zerion_api_key: "ZERION_API_KEY".to_owned(),
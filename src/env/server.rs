@@ -20,6 +20,9 @@ pub struct ServerConfig {
     pub validate_project_id: bool,
     /// Contains CAIP-2 chain identifiers that should bypass quota validation.
     pub skip_quota_chains: Vec<String>,
+    /// Bearer token of the admin API on the private port,
+    /// the admin API is disabled if not set
+    pub admin_token: Option<String>,
 }
 
 impl Default for ServerConfig {
@@ -37,6 +40,7 @@ impl Default for ServerConfig {
             testing_project_id: None,
             validate_project_id: true,
             skip_quota_chains: Vec::new(),
+            admin_token: None,
         }
     }
 }
@@ -58,6 +58,18 @@ pub enum RpcError {
     #[error("Requested chain provider is temporarily unavailable: {0}")]
     ChainTemporarilyUnavailable(String),
 
+    #[error("Provider {0} circuit is open or already probed")]
+    ProviderCircuitOpen(String),
+
+    #[error("Private transaction relay submission failed: {0}")]
+    PrivateTxRelayFailed(String),
+
+    #[error("No {chain_id} chain provider supports the required capabilities: {capabilities}")]
+    NoCapableProviders {
+        chain_id: String,
+        capabilities: String,
+    },
+
     #[error("Invalid chainId format for the requested namespace: {0}")]
     InvalidChainIdFormat(String),
 
@@ -210,6 +222,9 @@ pub enum RpcError {
     #[error(transparent)]
     Reqwest(#[from] reqwest::Error),
 
+    #[error("Provider response exceeds the maximum size of {0} bytes")]
+    ProviderResponseTooLarge(usize),
+
     #[error("Weighted providers index error: {0}")]
     WeightedProvidersIndex(String),
 
@@ -264,6 +279,9 @@ pub enum RpcError {
     #[error("Orchestration ID is not found: {0}")]
     OrchestrationIdNotFound(String),
 
+    #[error("Transaction is not found: {0}")]
+    TransactionNotFound(String),
+
     #[error("Bridging final amount is less then expected")]
     BridgingFinalAmountLess,
 
@@ -338,6 +356,17 @@ impl IntoResponse for RpcError {
                 )),
             )
                 .into_response(),
+            Self::PrivateTxRelayFailed(reason) => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(new_error_response(
+                    "privateTx".to_string(),
+                    format!(
+                        "Private transaction relay submission outcome is unknown, the \
+                         transaction may be already submitted: {reason}"
+                    ),
+                )),
+            )
+                .into_response(),
             Self::BalanceTemporarilyUnavailable(namespace) => (
                 StatusCode::SERVICE_UNAVAILABLE,
                 Json(new_error_response(
@@ -730,6 +759,14 @@ impl IntoResponse for RpcError {
                 )),
             )
                 .into_response(),
+            Self::TransactionNotFound(hash) => (
+                StatusCode::NOT_FOUND,
+                Json(new_error_response(
+                    "hash".to_string(),
+                    format!("Transaction is not found: {hash}"),
+                )),
+            )
+                .into_response(),
             Self::RouteSolana(e) => e.into_response(),
             // Any other errors considering as 500
             _ => (
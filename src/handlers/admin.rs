@@ -0,0 +1,234 @@
+use {
+    crate::{
+        chain_config::CatalogueError,
+        database::{error::DatabaseError, exchange_reconciliation},
+        providers::ProviderKind,
+        state::AppState,
+        utils::json_rpc_cache,
+    },
+    axum::{
+        extract::{Path, Request, State},
+        http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+        middleware::Next,
+        response::{IntoResponse, Response},
+        routing::{delete, get, post},
+        Json, Router,
+    },
+    serde_json::json,
+    std::sync::Arc,
+    tracing::{info, log::warn},
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum AdminError {
+    #[error("Provider {provider} is not configured for the chain {chain_id}")]
+    UnknownProvider { provider: String, chain_id: String },
+
+    #[error("Providers reload failed: {0}")]
+    Reload(#[from] CatalogueError),
+
+    #[error("Database error: {0}")]
+    Database(#[from] DatabaseError),
+
+    #[error("Cache flush failed: {0}")]
+    CacheFlush(anyhow::Error),
+}
+
+impl IntoResponse for AdminError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            Self::UnknownProvider { .. } => StatusCode::NOT_FOUND,
+            Self::Reload(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::Database(_) | Self::CacheFlush(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, Json(json!({ "error": self.to_string() }))).into_response()
+    }
+}
+
+/// Admin API routes for the private server, `None` if the admin token is
+/// not configured
+pub fn router(state: Arc<AppState>) -> Option<Router<Arc<AppState>>> {
+    state.config.server.admin_token.as_ref()?;
+    Some(
+        Router::new()
+            .route("/admin/providers", get(list_providers))
+            .route("/admin/providers/reload", post(reload_providers))
+            .route(
+                "/admin/providers/{chain_id}/{provider}/disable",
+                post(disable_provider),
+            )
+            .route(
+                "/admin/providers/{chain_id}/{provider}/enable",
+                post(enable_provider),
+            )
+            .route(
+                "/admin/providers/{chain_id}/{provider}/pin",
+                post(pin_provider),
+            )
+            .route("/admin/providers/{chain_id}/pin", delete(unpin_provider))
+            .route("/admin/cache/flush", post(flush_cache))
+            .route(
+                "/admin/exchanges/reconciliation",
+                get(exchange_reconciliation_backlog),
+            )
+            .layer(axum::middleware::from_fn_with_state(state, auth_middleware)),
+    )
+}
+
+async fn auth_middleware(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !is_authorized(
+        state.config.server.admin_token.as_deref(),
+        request.headers(),
+    ) {
+        warn!("Unauthorized admin API request to {}", request.uri().path());
+        state.metrics.add_admin_unauthorized();
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    next.run(request).await
+}
+
+fn is_authorized(admin_token: Option<&str>, headers: &HeaderMap) -> bool {
+    let Some(admin_token) = admin_token.filter(|token| !token.is_empty()) else {
+        return false;
+    };
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| constant_time_eq(token.as_bytes(), admin_token.as_bytes()))
+}
+
+/// Compare the tokens without the early return on the first mismatch
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+async fn list_providers(State(state): State<Arc<AppState>>) -> Response {
+    Json(state.providers.rpc_providers_status()).into_response()
+}
+
+async fn reload_providers(State(state): State<Arc<AppState>>) -> Result<Response, AdminError> {
+    state.reload_providers().await?;
+    record_action(&state, "reload", "providers reloaded");
+    Ok(Json(state.providers.rpc_supported_chains()).into_response())
+}
+
+async fn disable_provider(
+    State(state): State<Arc<AppState>>,
+    Path((chain_id, provider)): Path<(String, String)>,
+) -> Result<Response, AdminError> {
+    let provider_kind = chain_provider(&state, &chain_id, &provider)?;
+    let changed = state.providers.overrides.disable(&provider_kind, &chain_id);
+    record_action(
+        &state,
+        "disable",
+        &format!("{provider_kind} is disabled for {chain_id}"),
+    );
+    Ok(Json(json!({ "changed": changed })).into_response())
+}
+
+async fn enable_provider(
+    State(state): State<Arc<AppState>>,
+    Path((chain_id, provider)): Path<(String, String)>,
+) -> Result<Response, AdminError> {
+    let provider_kind = chain_provider(&state, &chain_id, &provider)?;
+    let changed = state.providers.overrides.enable(&provider_kind, &chain_id);
+    record_action(
+        &state,
+        "enable",
+        &format!("{provider_kind} is enabled for {chain_id}"),
+    );
+    Ok(Json(json!({ "changed": changed })).into_response())
+}
+
+async fn pin_provider(
+    State(state): State<Arc<AppState>>,
+    Path((chain_id, provider)): Path<(String, String)>,
+) -> Result<Response, AdminError> {
+    let provider_kind = chain_provider(&state, &chain_id, &provider)?;
+    state.providers.overrides.pin(&provider_kind, &chain_id);
+    record_action(
+        &state,
+        "pin",
+        &format!("{provider_kind} is pinned for {chain_id}"),
+    );
+    Ok(Json(json!({ "pinned": provider_kind.to_string() })).into_response())
+}
+
+async fn unpin_provider(
+    State(state): State<Arc<AppState>>,
+    Path(chain_id): Path<String>,
+) -> Response {
+    let unpinned = state.providers.overrides.unpin(&chain_id);
+    record_action(&state, "unpin", &format!("{chain_id} provider is unpinned"));
+    Json(json!({ "unpinned": unpinned.map(|provider| provider.to_string()) })).into_response()
+}
+
+async fn flush_cache(State(state): State<Arc<AppState>>) -> Result<Response, AdminError> {
+    let memory_entries = state.moka_cache.entry_count();
+    state.moka_cache.invalidate_all();
+    let redis_keys = match &state.providers.json_rpc_cache_redis_pool {
+        Some(redis_pool) => Some(
+            json_rpc_cache::flush_redis_cache(redis_pool)
+                .await
+                .map_err(AdminError::CacheFlush)?,
+        ),
+        None => None,
+    };
+    record_action(&state, "flush_cache", "caches are flushed");
+    Ok(Json(json!({
+        "memoryEntries": memory_entries,
+        "redisKeys": redis_keys,
+    }))
+    .into_response())
+}
+
+async fn exchange_reconciliation_backlog(
+    State(state): State<Arc<AppState>>,
+) -> Result<Response, AdminError> {
+    let backlog = exchange_reconciliation::pending_backlog(&state.postgres).await?;
+    Ok(Json(backlog).into_response())
+}
+
+/// Provider kind of the provider configured for the chain
+fn chain_provider(
+    state: &AppState,
+    chain_id: &str,
+    provider: &str,
+) -> Result<ProviderKind, AdminError> {
+    ProviderKind::from_str(provider)
+        .filter(|provider_kind| state.providers.is_chain_provider(provider_kind, chain_id))
+        .ok_or_else(|| AdminError::UnknownProvider {
+            provider: provider.to_string(),
+            chain_id: chain_id.to_string(),
+        })
+}
+
+fn record_action(state: &AppState, action: &str, details: &str) {
+    info!("Admin API action {action}: {details}");
+    state.metrics.add_admin_action(action.to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, axum::http::HeaderValue};
+
+    #[test]
+    fn test_is_authorized() {
+        let mut headers = HeaderMap::new();
+        assert!(!is_authorized(Some("secret"), &headers));
+
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer secret"));
+        assert!(is_authorized(Some("secret"), &headers));
+        assert!(!is_authorized(Some("secret2"), &headers));
+        assert!(!is_authorized(None, &headers));
+        assert!(!is_authorized(Some(""), &headers));
+
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("secret"));
+        assert!(!is_authorized(Some("secret"), &headers));
+    }
+}
@@ -412,6 +412,7 @@ async fn lookup_identity_rpc(
             chain_id: ETHEREUM_MAINNET.to_owned(),
             provider_id: None,
             session_id: None,
+            private_tx: None,
             source: Some(crate::analytics::MessageSource::Identity),
             sdk_info,
         },
@@ -113,6 +113,7 @@ async fn handler_internal(
                 project_id,
                 provider_id: None,
                 session_id: None,
+                private_tx: None,
                 source: Some(MessageSource::WalletGetCallsStatus),
                 sdk_info: query.sdk_info.clone(),
             },
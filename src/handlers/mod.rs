@@ -10,6 +10,7 @@ use {
     tracing::error,
 };
 
+pub mod admin;
 pub mod balance;
 pub mod bundler;
 pub mod chain_agnostic;
@@ -27,6 +28,7 @@ pub mod proxy;
 pub mod self_provider;
 pub mod sessions;
 pub mod supported_chains;
+pub mod transactions;
 pub mod ws_proxy;
 
 // TODO: Remove this once Dune Rootstock support is fixed
@@ -49,6 +51,8 @@ pub struct RpcQueryParams {
     /// Optional provider ID for the exact provider request
     pub provider_id: Option<String>,
     pub session_id: Option<String>,
+    /// Submit the raw transaction through the chain private relays
+    pub private_tx: Option<bool>,
 
     // TODO remove this param, as it can be set by actual rpc users but it shouldn't be
     /// Optional "source" field to indicate an internal request
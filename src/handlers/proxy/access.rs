@@ -0,0 +1,365 @@
+use {
+    super::DEFAULT_CONTENT_TYPE,
+    crate::{
+        error::RpcError,
+        handlers::RpcQueryParams,
+        json_rpc::{
+            ErrorResponse, JsonRpcError, JsonRpcRequest, JsonRpcResponse, METHOD_NOT_FOUND_CODE,
+        },
+        project::Registry,
+        providers::ProvidersConfig,
+        state::AppState,
+    },
+    axum::{
+        body::Bytes,
+        response::{IntoResponse, Response},
+    },
+    hyper::http::StatusCode,
+    tracing::log::{debug, warn},
+};
+
+/// Request attribute the method access rule applies to
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum RuleScope {
+    All,
+    Project(String),
+    Chain(String),
+    /// Project plan tier from the project data
+    Tier(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct MethodRule {
+    /// Exact method name or the method prefix ending with `*`
+    method: String,
+    scope: RuleScope,
+}
+
+impl MethodRule {
+    /// Parse the `<method>[@<scope>]` rule where the scope is `project:<id>`,
+    /// `chain:<caip2>` or `tier:<tier>`, the rule without the scope applies
+    /// to all requests
+    fn parse(rule: &str) -> Option<Self> {
+        let (method, scope) = match rule.split_once('@') {
+            Some((method, scope)) => {
+                let scope = match scope.split_once(':')? {
+                    ("project", id) if !id.is_empty() => RuleScope::Project(id.to_string()),
+                    ("chain", chain_id) if !chain_id.is_empty() => {
+                        RuleScope::Chain(chain_id.to_string())
+                    }
+                    ("tier", tier) if !tier.is_empty() => RuleScope::Tier(tier.to_string()),
+                    _ => return None,
+                };
+                (method, scope)
+            }
+            None => (rule, RuleScope::All),
+        };
+        if method.is_empty() {
+            return None;
+        }
+        Some(Self {
+            method: method.to_string(),
+            scope,
+        })
+    }
+
+    fn matches_method(&self, method: &str) -> bool {
+        match self.method.strip_suffix('*') {
+            Some(prefix) => method.starts_with(prefix),
+            None => self.method == method,
+        }
+    }
+
+    fn applies_to(&self, request: &AccessScope<'_>) -> bool {
+        match &self.scope {
+            RuleScope::All => true,
+            RuleScope::Project(id) => id == request.project_id,
+            RuleScope::Chain(chain_id) => chain_id == request.chain_id,
+            RuleScope::Tier(tier) => request.tier == Some(tier.as_str()),
+        }
+    }
+}
+
+/// Attributes of the request matched against the rules scopes
+#[derive(Debug, Clone, Copy)]
+pub struct AccessScope<'a> {
+    pub project_id: &'a str,
+    pub chain_id: &'a str,
+    pub tier: Option<&'a str>,
+}
+
+/// Rules list that rejected the method
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessList {
+    Allowlist,
+    Denylist,
+}
+
+impl AccessList {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Allowlist => "allowlist",
+            Self::Denylist => "denylist",
+        }
+    }
+}
+
+/// JSON-RPC methods allowlist and denylist rules per project, chain and
+/// project tier. The method is rejected if any applicable denylist rule
+/// matches it or if it's not matched by the allowlist rules of each
+/// applicable scope having the allowlist.
+#[derive(Debug, Default)]
+pub struct MethodAccess {
+    allowlist: Vec<MethodRule>,
+    denylist: Vec<MethodRule>,
+    /// Tier of the projects which data lookup failed
+    fallback_tier: Option<String>,
+}
+
+impl MethodAccess {
+    pub fn new(config: &ProvidersConfig) -> Self {
+        Self {
+            allowlist: parse_rules(config.method_allowlist.as_deref()),
+            denylist: parse_rules(config.method_denylist.as_deref()),
+            fallback_tier: config.method_access_fallback_tier.clone(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.allowlist.is_empty() || !self.denylist.is_empty()
+    }
+
+    /// Whether the project data should be fetched for the tier rules
+    fn has_tier_rules(&self) -> bool {
+        self.allowlist
+            .iter()
+            .chain(&self.denylist)
+            .any(|rule| matches!(rule.scope, RuleScope::Tier(_)))
+    }
+
+    /// Project tier for the tier rules, `None` if there are no tier rules.
+    /// The fallback tier is used if the project data lookup fails, the
+    /// lookup error is returned if it's not configured so the tier rules are
+    /// not bypassed.
+    pub async fn tier(
+        &self,
+        registry: &Registry,
+        project_id: &str,
+    ) -> Result<Option<String>, RpcError> {
+        if !self.has_tier_rules() {
+            return Ok(None);
+        }
+        match registry.project_data(project_id).await {
+            Ok(project) => Ok(Some(project.limits.tier)),
+            Err(e) => match &self.fallback_tier {
+                Some(tier) => {
+                    warn!("Using the fallback tier {tier} for project {project_id}: {e}");
+                    Ok(Some(tier.clone()))
+                }
+                None => Err(e),
+            },
+        }
+    }
+
+    /// Check the method access, returns the list rejecting the method
+    pub fn check(&self, method: &str, request: &AccessScope<'_>) -> Result<(), AccessList> {
+        if self
+            .denylist
+            .iter()
+            .any(|rule| rule.applies_to(request) && rule.matches_method(method))
+        {
+            return Err(AccessList::Denylist);
+        }
+
+        let allowlist = self
+            .allowlist
+            .iter()
+            .filter(|rule| rule.applies_to(request))
+            .collect::<Vec<_>>();
+        let is_allowed = allowlist.iter().all(|scope_rule| {
+            allowlist
+                .iter()
+                .filter(|rule| rule.scope == scope_rule.scope)
+                .any(|rule| rule.matches_method(method))
+        });
+        if is_allowed {
+            Ok(())
+        } else {
+            Err(AccessList::Allowlist)
+        }
+    }
+}
+
+fn parse_rules(rules: Option<&[String]>) -> Vec<MethodRule> {
+    rules
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|entry| {
+            let rule = MethodRule::parse(entry);
+            if rule.is_none() {
+                warn!("Skipping invalid method access rule: {entry}");
+            }
+            rule
+        })
+        .collect()
+}
+
+/// Check the methods of the single or batch request against the method
+/// access rules. Returns the JSON-RPC error response if any method is
+/// rejected, the whole batch is rejected in this case.
+pub async fn check_method_access(
+    state: &AppState,
+    query_params: &RpcQueryParams,
+    body: &Bytes,
+) -> Result<Option<Response>, RpcError> {
+    let method_access = &state.method_access;
+    if !method_access.is_enabled() {
+        return Ok(None);
+    }
+    // Malformed requests are left for the providers to reject
+    let (requests, is_batch) = match serde_json::from_slice::<JsonRpcRequest>(body) {
+        Ok(request) => (vec![request], false),
+        Err(_) => match serde_json::from_slice::<Vec<JsonRpcRequest>>(body) {
+            Ok(requests) => (requests, true),
+            Err(_) => return Ok(None),
+        },
+    };
+
+    let project_id = query_params.project_id.as_str();
+    let tier = method_access.tier(&state.registry, project_id).await?;
+    let scope = AccessScope {
+        project_id,
+        chain_id: &query_params.chain_id,
+        tier: tier.as_deref(),
+    };
+
+    let denied = requests
+        .iter()
+        .filter_map(|request| {
+            let list = method_access.check(&request.method, &scope).err()?;
+            Some((request.method.clone(), list))
+        })
+        .collect::<Vec<_>>();
+    if denied.is_empty() {
+        return Ok(None);
+    }
+    for (method, list) in &denied {
+        debug!(
+            "Method {method} is rejected by the {} for project: {project_id}",
+            list.as_str()
+        );
+        state.metrics.add_rpc_method_denied(
+            project_id.to_string(),
+            query_params.chain_id.clone(),
+            list.as_str(),
+        );
+    }
+
+    let error_response = |request: &JsonRpcRequest| {
+        let message = if denied.iter().any(|(method, _)| *method == request.method) {
+            format!("The method {} is not allowed", request.method)
+        } else {
+            "The batch request contains not allowed methods".to_string()
+        };
+        JsonRpcResponse::Error(JsonRpcError::new(
+            request.id.clone(),
+            ErrorResponse {
+                code: METHOD_NOT_FOUND_CODE,
+                message: message.into(),
+                data: None,
+            },
+        ))
+    };
+    let response = if is_batch {
+        serde_json::to_string(&requests.iter().map(error_response).collect::<Vec<_>>())?
+    } else {
+        serde_json::to_string(&error_response(&requests[0]))?
+    };
+    Ok(Some(
+        (StatusCode::FORBIDDEN, [DEFAULT_CONTENT_TYPE], response).into_response(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn method_access(allowlist: &[&str], denylist: &[&str]) -> MethodAccess {
+        let rules = |rules: &[&str]| {
+            rules
+                .iter()
+                .filter_map(|rule| MethodRule::parse(rule))
+                .collect()
+        };
+        MethodAccess {
+            allowlist: rules(allowlist),
+            denylist: rules(denylist),
+            fallback_tier: None,
+        }
+    }
+
+    #[test]
+    fn test_method_rule_parse() {
+        assert_eq!(
+            MethodRule::parse("trace_*@chain:eip155:1"),
+            Some(MethodRule {
+                method: "trace_*".to_string(),
+                scope: RuleScope::Chain("eip155:1".to_string()),
+            })
+        );
+        assert_eq!(
+            MethodRule::parse("eth_call").map(|rule| rule.scope),
+            Some(RuleScope::All)
+        );
+        assert_eq!(MethodRule::parse("eth_call@team:abc"), None);
+        assert_eq!(MethodRule::parse("eth_call@project:"), None);
+        assert_eq!(MethodRule::parse("@tier:free"), None);
+    }
+
+    #[test]
+    fn test_method_access_check() {
+        let access = method_access(
+            &["eth_call@project:readonly", "eth_get*@project:readonly"],
+            &[
+                "debug_*@tier:free",
+                "eth_sendRawTransaction@project:readonly",
+            ],
+        );
+        let free = AccessScope {
+            project_id: "project",
+            chain_id: "eip155:1",
+            tier: Some("free"),
+        };
+        assert_eq!(
+            access.check("debug_traceBlockByNumber", &free),
+            Err(AccessList::Denylist)
+        );
+        assert_eq!(access.check("eth_sendRawTransaction", &free), Ok(()));
+        assert_eq!(
+            access.check(
+                "debug_traceBlockByNumber",
+                &AccessScope {
+                    tier: Some("pro"),
+                    ..free
+                }
+            ),
+            Ok(())
+        );
+
+        let readonly = AccessScope {
+            project_id: "readonly",
+            chain_id: "eip155:1",
+            tier: None,
+        };
+        assert_eq!(access.check("eth_getBalance", &readonly), Ok(()));
+        assert_eq!(
+            access.check("eth_sendRawTransaction", &readonly),
+            Err(AccessList::Denylist)
+        );
+        assert_eq!(
+            access.check("trace_block", &readonly),
+            Err(AccessList::Allowlist)
+        );
+        assert!(!method_access(&[], &[]).is_enabled());
+    }
+}
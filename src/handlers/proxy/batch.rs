@@ -0,0 +1,348 @@
+use {
+    super::{rpc_call_providers, DEFAULT_CONTENT_TYPE, PROVIDER_RESPONSE_MAX_BYTES},
+    crate::{
+        error::RpcError,
+        handlers::RpcQueryParams,
+        json_rpc::{
+            ErrorResponse, JsonRpcError, JsonRpcRequest, JsonRpcResponse, INTERNAL_ERROR_CODE,
+        },
+        state::AppState,
+        utils::json_rpc_cache::{cache_response, is_cached_response},
+    },
+    axum::{
+        body::{to_bytes, Bytes},
+        response::{IntoResponse, Response},
+    },
+    futures_util::{stream, StreamExt},
+    hyper::{http, HeaderMap},
+    serde_json::Value,
+    std::{
+        collections::{HashMap, HashSet},
+        net::SocketAddr,
+        sync::Arc,
+    },
+    tracing::log::error,
+};
+
+const DEFAULT_BATCH_MAX_SIZE: usize = 100;
+const DEFAULT_BATCH_MAX_ITEMS: usize = 1_000;
+const DEFAULT_BATCH_SPLIT_MAX_CONCURRENCY: usize = 4;
+
+/// Split the batch request into the items that are served from the JSON-RPC
+/// cache and the rest that is sent upstream in chunks of the maximum batch
+/// size concurrently up to the concurrency limit. The chunk size is limited
+/// by the largest batch size declared by the chain providers, the chunks are
+/// routed only to the providers accepting it. The responses are reassembled
+/// in the original order. Batches exceeding the maximum items are rejected.
+pub async fn split_batch_call(
+    state: Arc<AppState>,
+    addr: SocketAddr,
+    query_params: RpcQueryParams,
+    headers: HeaderMap,
+    requests: Vec<JsonRpcRequest>,
+) -> Result<Response, RpcError> {
+    let chain_id = query_params.chain_id.clone();
+    if requests.is_empty() {
+        return Err(RpcError::InvalidParameter(
+            "Empty JSON-RPC batch request".to_string(),
+        ));
+    }
+    validate_batch_size(
+        requests.len(),
+        state
+            .config
+            .providers
+            .batch_max_items
+            .unwrap_or(DEFAULT_BATCH_MAX_ITEMS),
+    )?;
+    validate_unique_ids(&requests)?;
+
+    let redis_pool = state.providers.json_rpc_cache_redis_pool.as_ref();
+    let mut responses = Vec::with_capacity(requests.len());
+    let mut upstream_indexes = Vec::new();
+    for (i, request) in requests.iter().enumerate() {
+        // Requests to the exact provider are not served from the cache
+        let cached = match query_params.provider_id {
+            Some(_) => None,
+            None => {
+                is_cached_response(
+                    &chain_id,
+                    request,
+                    &state.metrics,
+                    &state.moka_cache,
+                    redis_pool,
+                )
+                .await
+            }
+        };
+        match cached {
+            Some(response) => responses.push(serde_json::to_value(response)?),
+            None => {
+                responses.push(Value::Null);
+                upstream_indexes.push(i);
+            }
+        }
+    }
+
+    if !upstream_indexes.is_empty() {
+        let batch_max_size = state
+            .config
+            .providers
+            .batch_max_size
+            .unwrap_or(DEFAULT_BATCH_MAX_SIZE)
+            .min(
+                state
+                    .providers
+                    .rpc_max_batch_size(&chain_id)
+                    .unwrap_or(usize::MAX),
+            )
+            .max(1);
+        let chunks = upstream_indexes.chunks(batch_max_size).collect::<Vec<_>>();
+        state
+            .metrics
+            .add_rpc_batch_upstream_chunks(chunks.len() as u64, chain_id.clone());
+
+        // Each chunk is sent to the separately selected provider, the
+        // responses are kept in the chunks order
+        let max_concurrency = state
+            .config
+            .providers
+            .batch_split_max_concurrency
+            .unwrap_or(DEFAULT_BATCH_SPLIT_MAX_CONCURRENCY)
+            .max(1);
+        let chunks_responses = stream::iter(chunks.iter().map(|chunk| {
+            let chunk_requests = chunk.iter().map(|&i| &requests[i]).collect::<Vec<_>>();
+            call_chunk(&state, addr, &query_params, &headers, chunk_requests)
+        }))
+        .buffered(max_concurrency)
+        .collect::<Vec<_>>()
+        .await;
+
+        for (chunk, chunk_responses) in chunks.into_iter().zip(chunks_responses) {
+            // The chunk error is forwarded to each chunk item
+            let mut chunk_responses = match chunk_responses {
+                Ok(chunk_responses) => chunk_responses,
+                Err(error) => {
+                    for &i in chunk {
+                        responses[i] = error_response(requests[i].id.clone(), error.clone())?;
+                    }
+                    continue;
+                }
+            };
+            for &i in chunk {
+                let request = &requests[i];
+                responses[i] = match chunk_responses.remove(&request.id.to_string()) {
+                    Some(response) => {
+                        // Responses of the exact provider are not cached
+                        let result = response
+                            .get("result")
+                            .filter(|_| query_params.provider_id.is_none());
+                        if let Some(result) = result {
+                            cache_response(
+                                &chain_id,
+                                request,
+                                result,
+                                state.providers.chain_head(&chain_id),
+                                &state.moka_cache,
+                                redis_pool,
+                            )
+                            .await;
+                        }
+                        response
+                    }
+                    None => missing_response_error(request.id.clone())?,
+                };
+            }
+        }
+    }
+
+    Ok((
+        http::StatusCode::OK,
+        [DEFAULT_CONTENT_TYPE],
+        serde_json::to_string(&responses)?,
+    )
+        .into_response())
+}
+
+/// Send the batch chunk upstream and return the responses by the request id.
+/// Returns the error for the chunk items if the chunk call failed, the
+/// provider error response is forwarded as is.
+async fn call_chunk(
+    state: &Arc<AppState>,
+    addr: SocketAddr,
+    query_params: &RpcQueryParams,
+    headers: &HeaderMap,
+    requests: Vec<&JsonRpcRequest>,
+) -> Result<HashMap<String, Value>, ChunkError> {
+    let body = serde_json::to_vec(&requests).map_err(|e| {
+        error!("Failed to serialize JSON-RPC batch chunk: {e}");
+        internal_error(format!("Failed to serialize the batch request: {e}"))
+    })?;
+    let response = rpc_call_providers(
+        state.clone(),
+        addr,
+        query_params.clone(),
+        headers.clone(),
+        Bytes::from(body),
+        None,
+    )
+    .await
+    .map_err(|e| {
+        error!("Failed to call providers for the JSON-RPC batch chunk: {e}");
+        internal_error(e.to_string())
+    })?;
+
+    let status = response.status();
+    let body = to_bytes(response.into_body(), PROVIDER_RESPONSE_MAX_BYTES)
+        .await
+        .map_err(|e| {
+            error!("Failed to read the JSON-RPC batch chunk response: {e}");
+            internal_error(format!("Failed to read the provider response: {e}"))
+        })?;
+    match serde_json::from_slice::<Value>(&body) {
+        Ok(Value::Array(responses)) if status.is_success() => Ok(index_responses_by_id(responses)),
+        response => {
+            error!("Provider responded with status {status} without the batch chunk responses");
+            Err(response
+                .ok()
+                .and_then(|response| provider_error(&response))
+                .unwrap_or_else(|| {
+                    internal_error(format!(
+                        "Provider responded with status {status} to the batch request"
+                    ))
+                }))
+        }
+    }
+}
+
+/// Error response of the batch chunk items
+type ChunkError = ErrorResponse<Option<Value>>;
+
+/// JSON-RPC error of the provider response to the whole batch
+fn provider_error(response: &Value) -> Option<ChunkError> {
+    let error = response.get("error")?;
+    Some(ErrorResponse {
+        code: error.get("code")?.as_i64()?.try_into().ok()?,
+        message: error.get("message")?.as_str()?.into(),
+        data: error.get("data").cloned(),
+    })
+}
+
+fn internal_error(message: String) -> ChunkError {
+    ErrorResponse {
+        code: INTERNAL_ERROR_CODE,
+        message: message.into(),
+        data: None,
+    }
+}
+
+fn error_response(id: Value, error: ChunkError) -> Result<Value, RpcError> {
+    Ok(serde_json::to_value(JsonRpcError::new(id, error))?)
+}
+
+fn validate_batch_size(size: usize, max_items: usize) -> Result<(), RpcError> {
+    if size > max_items {
+        return Err(RpcError::InvalidParameter(format!(
+            "JSON-RPC batch request of {size} items exceeds the maximum of {max_items} items"
+        )));
+    }
+    Ok(())
+}
+
+/// Batch request ids must be unique to match the responses
+fn validate_unique_ids(requests: &[JsonRpcRequest]) -> Result<(), RpcError> {
+    let mut ids = HashSet::new();
+    for request in requests {
+        if !ids.insert(request.id.to_string()) {
+            return Err(RpcError::InvalidParameter(format!(
+                "Duplicate JSON-RPC request id {} in the batch",
+                request.id
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn index_responses_by_id(responses: Vec<Value>) -> HashMap<String, Value> {
+    responses
+        .into_iter()
+        .filter_map(|response| Some((response.get("id")?.to_string(), response)))
+        .collect()
+}
+
+fn missing_response_error(id: Value) -> Result<Value, RpcError> {
+    Ok(serde_json::to_value(JsonRpcResponse::Error(
+        JsonRpcError::new(
+            id,
+            ErrorResponse {
+                code: INTERNAL_ERROR_CODE,
+                message: "Provider didn't respond to the batch request item".into(),
+                data: None,
+            },
+        ),
+    ))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, serde_json::json};
+
+    fn request(id: Value) -> JsonRpcRequest {
+        JsonRpcRequest::new_with_params(id, "eth_blockNumber".into(), json!([]))
+    }
+
+    #[test]
+    fn test_validate_unique_ids() {
+        assert!(validate_unique_ids(&[request(json!(1)), request(json!(2))]).is_ok());
+        // Numeric and string ids are different
+        assert!(validate_unique_ids(&[request(json!(1)), request(json!("1"))]).is_ok());
+        assert!(matches!(
+            validate_unique_ids(&[request(json!(1)), request(json!(1))]),
+            Err(RpcError::InvalidParameter(_))
+        ));
+    }
+
+    #[test]
+    fn test_validate_batch_size() {
+        assert!(validate_batch_size(100, DEFAULT_BATCH_MAX_ITEMS).is_ok());
+        assert!(validate_batch_size(DEFAULT_BATCH_MAX_ITEMS, DEFAULT_BATCH_MAX_ITEMS).is_ok());
+        assert!(matches!(
+            validate_batch_size(DEFAULT_BATCH_MAX_ITEMS + 1, DEFAULT_BATCH_MAX_ITEMS),
+            Err(RpcError::InvalidParameter(_))
+        ));
+    }
+
+    #[test]
+    fn test_provider_error() {
+        let error = provider_error(&json!({
+            "jsonrpc": "2.0",
+            "id": null,
+            "error": { "code": -32005, "message": "batch size is too large", "data": 50 },
+        }))
+        .unwrap();
+        assert_eq!(error.code, -32005);
+        assert_eq!(error.message.as_ref(), "batch size is too large");
+        assert_eq!(error.data, Some(json!(50)));
+        assert_eq!(
+            error_response(json!(7), error).unwrap(),
+            json!({
+                "jsonrpc": "2.0",
+                "id": 7,
+                "error": { "code": -32005, "message": "batch size is too large", "data": 50 },
+            })
+        );
+        assert!(provider_error(&json!({ "jsonrpc": "2.0", "id": 1, "result": "0x1" })).is_none());
+    }
+
+    #[test]
+    fn test_index_responses_by_id() {
+        let responses = index_responses_by_id(vec![
+            json!({ "jsonrpc": "2.0", "id": 2, "result": "0x2" }),
+            json!({ "jsonrpc": "2.0", "id": "1", "result": "0x1" }),
+            json!({ "jsonrpc": "2.0", "result": "0x3" }),
+        ]);
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[&json!(2).to_string()]["result"], "0x2");
+        assert_eq!(responses[&json!("1").to_string()]["result"], "0x1");
+    }
+}
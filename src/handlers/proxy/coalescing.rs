@@ -0,0 +1,263 @@
+use {
+    super::{rpc_call_providers, DEFAULT_CONTENT_TYPE, PROVIDER_RESPONSE_MAX_BYTES},
+    crate::{
+        error::RpcError, handlers::RpcQueryParams, json_rpc::JsonRpcRequest,
+        providers::ProvidersConfig, state::AppState,
+    },
+    axum::{
+        body::{to_bytes, Bytes},
+        response::{IntoResponse, Response},
+    },
+    hyper::{http::StatusCode, HeaderMap},
+    serde_json::Value,
+    std::{
+        collections::{HashMap, HashSet},
+        net::SocketAddr,
+        sync::{Arc, Mutex},
+    },
+    tokio::sync::watch,
+    tracing::log::{debug, error},
+};
+
+/// Response of the coalesced upstream call shared with the waiting requests
+#[derive(Debug, Clone)]
+pub struct CoalescedResponse {
+    pub status: StatusCode,
+    pub body: Bytes,
+}
+
+type InFlightCalls = Mutex<HashMap<String, watch::Receiver<Option<CoalescedResponse>>>>;
+
+/// Coalescing of the identical in-flight requests into a single upstream call.
+/// The first request for the key becomes the leader that calls the providers
+/// and the following identical requests wait for the leader's response.
+#[derive(Debug)]
+pub struct Coalescing {
+    methods: HashSet<String>,
+    in_flight: Arc<InFlightCalls>,
+}
+
+/// Role of the request in the coalesced call
+pub enum CoalescingRole {
+    Leader(CoalescingLeader),
+    Follower(watch::Receiver<Option<CoalescedResponse>>),
+}
+
+impl Coalescing {
+    pub fn new(config: &ProvidersConfig) -> Self {
+        Self {
+            methods: config
+                .coalescing_methods
+                .clone()
+                .unwrap_or_default()
+                .into_iter()
+                .collect(),
+            in_flight: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Whether the identical requests of the method should be coalesced
+    pub fn is_enabled_for(&self, method: &str) -> bool {
+        self.methods.contains(method)
+    }
+
+    /// Join the in-flight call for the same request or become the leader
+    /// of the new one. Returns `None` if the in-flight calls map is poisoned.
+    pub fn join(&self, chain_id: &str, request: &JsonRpcRequest) -> Option<CoalescingRole> {
+        let key = coalescing_key(chain_id, request);
+        let mut in_flight = self.in_flight.lock().ok()?;
+        if let Some(receiver) = in_flight.get(&key) {
+            return Some(CoalescingRole::Follower(receiver.clone()));
+        }
+
+        let (sender, receiver) = watch::channel(None);
+        in_flight.insert(key.clone(), receiver);
+        Some(CoalescingRole::Leader(CoalescingLeader {
+            key,
+            sender,
+            in_flight: self.in_flight.clone(),
+        }))
+    }
+}
+
+/// Leader of the coalesced call. The in-flight call is removed when the
+/// leader is dropped, so the followers stop waiting if the leader failed
+/// or was cancelled without the response.
+pub struct CoalescingLeader {
+    key: String,
+    sender: watch::Sender<Option<CoalescedResponse>>,
+    in_flight: Arc<InFlightCalls>,
+}
+
+impl CoalescingLeader {
+    /// Share the upstream response with the followers
+    pub fn complete(self, response: CoalescedResponse) {
+        self.sender.send_replace(Some(response));
+    }
+}
+
+impl Drop for CoalescingLeader {
+    fn drop(&mut self) {
+        if let Ok(mut in_flight) = self.in_flight.lock() {
+            in_flight.remove(&self.key);
+        }
+    }
+}
+
+/// Coalesce the request with the identical in-flight request if there is one,
+/// otherwise call the providers and share the response with the identical
+/// requests arrived in the meantime
+pub async fn coalesced_rpc_call(
+    state: Arc<AppState>,
+    addr: SocketAddr,
+    query_params: RpcQueryParams,
+    headers: HeaderMap,
+    body: Bytes,
+    request: JsonRpcRequest,
+) -> Result<Response, RpcError> {
+    let chain_id = query_params.chain_id.clone();
+    let leader = match state.coalescing.join(&chain_id, &request) {
+        Some(CoalescingRole::Leader(leader)) => leader,
+        Some(CoalescingRole::Follower(receiver)) => {
+            if let Some(response) = wait_for_leader(receiver).await {
+                if let Some(body) = rewrite_response_id(&response.body, &request.id) {
+                    state
+                        .metrics
+                        .add_coalesced_rpc_call(chain_id, request.method.to_string());
+                    return Ok((response.status, [DEFAULT_CONTENT_TYPE], body).into_response());
+                }
+            }
+            debug!("Coalesced call for {chain_id} failed, calling the providers");
+            return rpc_call_providers(state, addr, query_params, headers, body, Some(request))
+                .await;
+        }
+        None => {
+            return rpc_call_providers(state, addr, query_params, headers, body, Some(request))
+                .await
+        }
+    };
+
+    // Followers stop waiting and call the providers on their own
+    // if the leader's call failed
+    let response =
+        rpc_call_providers(state, addr, query_params, headers, body, Some(request)).await?;
+    let status = response.status();
+    let body = to_bytes(response.into_body(), PROVIDER_RESPONSE_MAX_BYTES)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read the coalesced response body: {e}"))?;
+    leader.complete(CoalescedResponse {
+        status,
+        body: body.clone(),
+    });
+    Ok((status, [DEFAULT_CONTENT_TYPE], body).into_response())
+}
+
+/// Wait for the leader's response, returns `None` if the leader failed
+pub async fn wait_for_leader(
+    mut receiver: watch::Receiver<Option<CoalescedResponse>>,
+) -> Option<CoalescedResponse> {
+    receiver
+        .wait_for(Option::is_some)
+        .await
+        .ok()
+        .and_then(|response| response.clone())
+}
+
+/// Requests are identical if the chain, method and params are the same
+fn coalescing_key(chain_id: &str, request: &JsonRpcRequest) -> String {
+    format!("{chain_id}:{}:{}", request.method, request.params)
+}
+
+/// Rewrite the leader's response id to the follower's request id
+pub fn rewrite_response_id(body: &[u8], id: &Value) -> Option<Bytes> {
+    let mut response = serde_json::from_slice::<Value>(body)
+        .map_err(|e| error!("Failed to parse the coalesced JSON-RPC response: {e}"))
+        .ok()?;
+    *response.as_object_mut()?.get_mut("id")? = id.clone();
+    serde_json::to_vec(&response).ok().map(Bytes::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, serde_json::json};
+
+    fn coalescing() -> Coalescing {
+        Coalescing {
+            methods: HashSet::from(["eth_blockNumber".to_string()]),
+            in_flight: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn request(id: u64, params: Value) -> JsonRpcRequest {
+        JsonRpcRequest::new_with_params(json!(id), "eth_blockNumber".into(), params)
+    }
+
+    #[test]
+    fn test_is_enabled_for() {
+        let coalescing = coalescing();
+        assert!(coalescing.is_enabled_for("eth_blockNumber"));
+        assert!(!coalescing.is_enabled_for("eth_sendRawTransaction"));
+    }
+
+    #[tokio::test]
+    async fn test_followers_receive_leader_response() {
+        let coalescing = coalescing();
+        let Some(CoalescingRole::Leader(leader)) =
+            coalescing.join("eip155:1", &request(1, json!([])))
+        else {
+            panic!("First request should be the leader");
+        };
+        // Request id is ignored
+        let Some(CoalescingRole::Follower(follower)) =
+            coalescing.join("eip155:1", &request(2, json!([])))
+        else {
+            panic!("Identical request should be the follower");
+        };
+        // Different chain or params are not coalesced
+        assert!(matches!(
+            coalescing.join("eip155:10", &request(3, json!([]))),
+            Some(CoalescingRole::Leader(_))
+        ));
+        assert!(matches!(
+            coalescing.join("eip155:1", &request(4, json!(["latest"]))),
+            Some(CoalescingRole::Leader(_))
+        ));
+
+        leader.complete(CoalescedResponse {
+            status: StatusCode::OK,
+            body: Bytes::from_static(br#"{"jsonrpc":"2.0","id":1,"result":"0x1"}"#),
+        });
+        let response = wait_for_leader(follower).await.unwrap();
+        assert_eq!(response.status, StatusCode::OK);
+
+        // The call is removed from the in-flight calls after completion
+        assert!(matches!(
+            coalescing.join("eip155:1", &request(5, json!([]))),
+            Some(CoalescingRole::Leader(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_followers_stop_waiting_for_dropped_leader() {
+        let coalescing = coalescing();
+        let leader = coalescing.join("eip155:1", &request(1, json!([])));
+        let Some(CoalescingRole::Follower(follower)) =
+            coalescing.join("eip155:1", &request(2, json!([])))
+        else {
+            panic!("Identical request should be the follower");
+        };
+        drop(leader);
+        assert!(wait_for_leader(follower).await.is_none());
+    }
+
+    #[test]
+    fn test_rewrite_response_id() {
+        let body = br#"{"jsonrpc":"2.0","id":1,"result":"0x1"}"#;
+        let rewritten = rewrite_response_id(body, &json!("abc")).unwrap();
+        assert_eq!(
+            serde_json::from_slice::<Value>(&rewritten).unwrap(),
+            json!({ "jsonrpc": "2.0", "id": "abc", "result": "0x1" })
+        );
+        assert!(rewrite_response_id(b"invalid", &json!(1)).is_none());
+    }
+}
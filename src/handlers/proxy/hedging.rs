@@ -0,0 +1,193 @@
+use {
+    crate::providers::ProvidersConfig,
+    std::{
+        collections::{HashMap, VecDeque},
+        sync::Mutex,
+        time::Duration,
+    },
+};
+
+/// JSON-RPC methods that are safe to send to more than one provider at once
+/// as they don't change the chain state
+const HEDGEABLE_METHODS: &[&str] = &[
+    "eth_blockNumber",
+    "eth_call",
+    "eth_chainId",
+    "eth_estimateGas",
+    "eth_feeHistory",
+    "eth_gasPrice",
+    "eth_getBalance",
+    "eth_getBlockByHash",
+    "eth_getBlockByNumber",
+    "eth_getBlockReceipts",
+    "eth_getCode",
+    "eth_getLogs",
+    "eth_getStorageAt",
+    "eth_getTransactionByHash",
+    "eth_getTransactionCount",
+    "eth_getTransactionReceipt",
+    "eth_maxPriorityFeePerGas",
+    "getAccountInfo",
+    "getBalance",
+    "getLatestBlockhash",
+    "getSignatureStatuses",
+    "getSlot",
+    "getTokenAccountsByOwner",
+];
+
+/// Amount of the latest successful calls latencies kept per chain
+const LATENCY_WINDOW_SIZE: usize = 200;
+/// Minimal amount of samples to calculate the percentile from, before that
+/// the default hedging delay is used
+const MIN_LATENCY_SAMPLES: usize = 20;
+const DEFAULT_HEDGING_DELAY: Duration = Duration::from_millis(500);
+const MIN_HEDGING_DELAY: Duration = Duration::from_millis(50);
+const DEFAULT_HEDGING_MAX_DELAY_MS: u64 = 2_000;
+const DEFAULT_HEDGING_LATENCY_PERCENTILE: u8 = 95;
+
+pub fn is_hedgeable_method(method: &str) -> bool {
+    HEDGEABLE_METHODS.contains(&method)
+}
+
+/// Speculative (hedged) provider requests policy.
+/// Tracks the recent successful provider calls latencies per chain to
+/// calculate the delay after which the same request is sent to the next
+/// provider.
+#[derive(Debug)]
+pub struct Hedging {
+    enabled: bool,
+    percentile: u8,
+    max_delay: Duration,
+    latencies: Mutex<HashMap<String, LatencyWindow>>,
+}
+
+impl Hedging {
+    pub fn new(config: &ProvidersConfig) -> Self {
+        Self {
+            enabled: config.hedging_enabled.unwrap_or(false),
+            percentile: config
+                .hedging_latency_percentile
+                .unwrap_or(DEFAULT_HEDGING_LATENCY_PERCENTILE)
+                .min(100),
+            max_delay: Duration::from_millis(
+                config
+                    .hedging_max_delay_ms
+                    .unwrap_or(DEFAULT_HEDGING_MAX_DELAY_MS),
+            ),
+            latencies: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether the request for the method should be hedged
+    pub fn is_enabled_for(&self, method: &str) -> bool {
+        self.enabled && is_hedgeable_method(method)
+    }
+
+    /// Record the latency of a successful provider call for the chain
+    pub fn record_latency(&self, chain_id: &str, latency: Duration) {
+        let Ok(mut latencies) = self.latencies.lock() else {
+            return;
+        };
+        latencies
+            .entry(chain_id.to_string())
+            .or_default()
+            .push(latency.as_millis() as u64);
+    }
+
+    /// Delay after which the hedged request should be sent for the chain
+    pub fn delay(&self, chain_id: &str) -> Duration {
+        let percentile = self
+            .latencies
+            .lock()
+            .ok()
+            .and_then(|latencies| latencies.get(chain_id)?.percentile(self.percentile));
+
+        match percentile {
+            // The configured max delay takes precedence over the minimal delay
+            Some(ms) => Duration::from_millis(ms)
+                .max(MIN_HEDGING_DELAY)
+                .min(self.max_delay),
+            None => DEFAULT_HEDGING_DELAY.min(self.max_delay),
+        }
+    }
+}
+
+/// Sliding window of the latest latencies in milliseconds
+#[derive(Debug, Default)]
+struct LatencyWindow {
+    samples: VecDeque<u64>,
+}
+
+impl LatencyWindow {
+    fn push(&mut self, latency_ms: u64) {
+        if self.samples.len() == LATENCY_WINDOW_SIZE {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(latency_ms);
+    }
+
+    fn percentile(&self, percentile: u8) -> Option<u64> {
+        if self.samples.len() < MIN_LATENCY_SAMPLES {
+            return None;
+        }
+        let mut sorted = self.samples.iter().copied().collect::<Vec<_>>();
+        sorted.sort_unstable();
+        let index = (sorted.len() - 1) * percentile as usize / 100;
+        sorted.get(index).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_window_percentile() {
+        let mut window = LatencyWindow::default();
+        for ms in 1..=10 {
+            window.push(ms);
+        }
+        // Not enough samples yet
+        assert_eq!(window.percentile(95), None);
+
+        for ms in 11..=100 {
+            window.push(ms);
+        }
+        assert_eq!(window.percentile(50), Some(50));
+        assert_eq!(window.percentile(95), Some(95));
+        assert_eq!(window.percentile(100), Some(100));
+    }
+
+    #[test]
+    fn test_latency_window_is_bounded() {
+        let mut window = LatencyWindow::default();
+        for ms in 0..(LATENCY_WINDOW_SIZE as u64 * 2) {
+            window.push(ms);
+        }
+        assert_eq!(window.samples.len(), LATENCY_WINDOW_SIZE);
+        assert_eq!(window.samples.front(), Some(&(LATENCY_WINDOW_SIZE as u64)));
+    }
+
+    #[test]
+    fn test_hedging_delay_below_min_delay() {
+        let hedging = Hedging {
+            enabled: true,
+            percentile: 95,
+            max_delay: Duration::from_millis(10),
+            latencies: Mutex::new(HashMap::new()),
+        };
+        assert_eq!(hedging.delay("eip155:1"), Duration::from_millis(10));
+        for _ in 0..MIN_LATENCY_SAMPLES {
+            hedging.record_latency("eip155:1", Duration::from_millis(200));
+        }
+        assert_eq!(hedging.delay("eip155:1"), Duration::from_millis(10));
+    }
+
+    #[test]
+    fn test_is_hedgeable_method() {
+        assert!(is_hedgeable_method("eth_call"));
+        assert!(is_hedgeable_method("eth_getBlockByNumber"));
+        assert!(!is_hedgeable_method("eth_sendRawTransaction"));
+        assert!(!is_hedgeable_method("eth_subscribe"));
+    }
+}
@@ -0,0 +1,482 @@
+use {
+    super::{rpc_call_providers, DEFAULT_CONTENT_TYPE, PROVIDER_RESPONSE_MAX_BYTES},
+    crate::{
+        error::RpcError,
+        handlers::RpcQueryParams,
+        json_rpc::{
+            ErrorResponse, JsonRpcError, JsonRpcRequest, JsonRpcResponse, JsonRpcResult,
+            LIMIT_EXCEEDED_CODE,
+        },
+        providers::{
+            capabilities::{logs_filter_blocks, LogsBlock},
+            is_logs_range_error_rpc_message,
+        },
+        state::AppState,
+    },
+    axum::{
+        body::{to_bytes, Body, Bytes},
+        response::{IntoResponse, Response},
+    },
+    futures_util::future::{join, BoxFuture},
+    hyper::{http::StatusCode, HeaderMap},
+    serde_json::{json, Value},
+    std::{
+        net::SocketAddr,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    },
+    tokio::sync::Semaphore,
+    tracing::log::debug,
+};
+
+pub const GET_LOGS_METHOD: &str = "eth_getLogs";
+const DEFAULT_LOGS_SPLIT_MAX_DEPTH: u32 = 6;
+const DEFAULT_LOGS_SPLIT_MAX_CONCURRENCY: usize = 4;
+/// Request id of the internal block tag resolution call
+const BLOCK_TAG_REQUEST_ID: &str = "rpc-proxy-logs-block-tag";
+
+/// Response of the `eth_getLogs` blocks range call
+#[derive(Debug)]
+enum RangeResponse {
+    Logs(Vec<Value>),
+    /// Response that is returned to the client as is, e.g. the error
+    /// response not related to the range limits
+    Other(StatusCode, Bytes),
+    /// Merged logs exceed the response size limit, the original range error
+    /// is returned to the client
+    TooLarge,
+}
+
+/// Call the providers for the `eth_getLogs` request. If the provider rejects
+/// the blocks range or the results count, the range is split into halves
+/// that are queried concurrently and halved again while rejected. Ranges
+/// exceeding the declared maximum range of all chain providers are split
+/// without querying them first. The block tags are resolved by the providers
+/// to split the range. The logs of the sub-ranges are merged in the blocks
+/// order up to the response size limit.
+pub async fn split_logs_call(
+    state: Arc<AppState>,
+    addr: SocketAddr,
+    query_params: RpcQueryParams,
+    headers: HeaderMap,
+    body: Bytes,
+    request: JsonRpcRequest,
+) -> Result<Response, RpcError> {
+    let chain_id = query_params.chain_id.clone();
+
+    let blocks = request.params.get(0).and_then(logs_filter_blocks);
+    let Some((from, to)) = blocks.filter(|blocks| match blocks {
+        (LogsBlock::Number(from_block), LogsBlock::Number(to_block)) => from_block < to_block,
+        _ => true,
+    }) else {
+        return rpc_call_providers(state, addr, query_params, headers, body, Some(request)).await;
+    };
+    let max_range = state.providers.rpc_max_logs_block_range(&chain_id);
+
+    let providers_config = &state.config.providers;
+    let range_call = RangeCall {
+        state: &state,
+        addr,
+        query_params: &query_params,
+        headers: &headers,
+        request: &request,
+        max_range,
+        max_depth: providers_config
+            .logs_split_max_depth
+            .unwrap_or(DEFAULT_LOGS_SPLIT_MAX_DEPTH),
+        permits: Semaphore::new(
+            providers_config
+                .logs_split_max_concurrency
+                .unwrap_or(DEFAULT_LOGS_SPLIT_MAX_CONCURRENCY)
+                .max(1),
+        ),
+        merged_size: AtomicUsize::new(0),
+    };
+
+    // The block tags are resolved before the full range call only to check
+    // the range against the declared maximum range
+    let bounds = match max_range {
+        Some(_) => range_call.resolve_bounds(&from, &to).await,
+        None => None,
+    };
+    let pre_split = bounds
+        .filter(|(from_block, to_block)| exceeds_max_range(*from_block, *to_block, max_range));
+
+    // Range error response of the full range call returned to the client if
+    // the merged logs are too large
+    let (from_block, to_block, range_error_response) = match pre_split {
+        Some((from_block, to_block)) => (from_block, to_block, None),
+        None => {
+            let response = rpc_call_providers(
+                state.clone(),
+                addr,
+                query_params.clone(),
+                headers.clone(),
+                body,
+                Some(request.clone()),
+            )
+            .await?;
+            let (parts, body) = response.into_parts();
+            let body = to_bytes(body, PROVIDER_RESPONSE_MAX_BYTES)
+                .await
+                .map_err(|e| {
+                    anyhow::anyhow!("Failed to read the eth_getLogs response body: {e}")
+                })?;
+            let response = Response::from_parts(parts, Body::from(body.clone()));
+            if !is_logs_range_error(&body) {
+                return Ok(response);
+            }
+            let bounds = match bounds {
+                Some(bounds) => Some(bounds),
+                None => range_call.resolve_bounds(&from, &to).await,
+            };
+            match bounds.filter(|(from_block, to_block)| from_block < to_block) {
+                Some((from_block, to_block)) => (from_block, to_block, Some(response)),
+                // The range can't be split, so the range error is returned as is
+                None => return Ok(response),
+            }
+        }
+    };
+    debug!("Splitting eth_getLogs blocks range {from_block}..={to_block} for chain_id: {chain_id}");
+
+    let (response, sub_ranges) = range_call.split(from_block, to_block, 1).await?;
+    state.metrics.add_logs_range_split(chain_id, sub_ranges);
+
+    match response {
+        RangeResponse::Logs(logs) => Ok((
+            StatusCode::OK,
+            [DEFAULT_CONTENT_TYPE],
+            serde_json::to_string(&JsonRpcResponse::Result(JsonRpcResult::new(
+                request.id,
+                Value::Array(logs),
+            )))?,
+        )
+            .into_response()),
+        RangeResponse::Other(status, body) => {
+            Ok((status, [DEFAULT_CONTENT_TYPE], body).into_response())
+        }
+        RangeResponse::TooLarge => {
+            debug!(
+                "Merged eth_getLogs response is too large for chain_id: {}",
+                query_params.chain_id
+            );
+            match range_error_response {
+                Some(response) => Ok(response),
+                None => too_large_response(request.id),
+            }
+        }
+    }
+}
+
+/// Request data shared between the sub-range calls of the same request
+struct RangeCall<'a> {
+    state: &'a Arc<AppState>,
+    addr: SocketAddr,
+    query_params: &'a RpcQueryParams,
+    headers: &'a HeaderMap,
+    request: &'a JsonRpcRequest,
+    /// Largest blocks range declared by the chain providers
+    max_range: Option<u64>,
+    max_depth: u32,
+    /// Concurrent sub-range provider calls limit
+    permits: Semaphore,
+    /// Size of the sub-range logs responses received so far
+    merged_size: AtomicUsize,
+}
+
+impl RangeCall<'_> {
+    /// Query the range halves concurrently and merge the logs. Returns the
+    /// response with the amount of the queried sub-ranges.
+    fn split(
+        &self,
+        from_block: u64,
+        to_block: u64,
+        depth: u32,
+    ) -> BoxFuture<'_, Result<(RangeResponse, u64), RpcError>> {
+        Box::pin(async move {
+            let middle = from_block + (to_block - from_block) / 2;
+            let (first, second) = join(
+                self.call(from_block, middle, depth),
+                self.call(middle + 1, to_block, depth),
+            )
+            .await;
+            let ((first, first_count), (second, second_count)) = (first?, second?);
+            let response = match (first, second) {
+                (RangeResponse::TooLarge, _) | (_, RangeResponse::TooLarge) => {
+                    RangeResponse::TooLarge
+                }
+                (RangeResponse::Logs(mut logs), RangeResponse::Logs(second_logs)) => {
+                    logs.extend(second_logs);
+                    RangeResponse::Logs(logs)
+                }
+                (other @ RangeResponse::Other(..), _) | (_, other @ RangeResponse::Other(..)) => {
+                    other
+                }
+            };
+            Ok((response, first_count + second_count))
+        })
+    }
+
+    /// Query the blocks range and split it again if it's rejected. The
+    /// ranges are not queried anymore once the merged logs are too large.
+    async fn call(
+        &self,
+        from_block: u64,
+        to_block: u64,
+        depth: u32,
+    ) -> Result<(RangeResponse, u64), RpcError> {
+        let can_split = from_block < to_block && depth < self.max_depth;
+        if can_split && exceeds_max_range(from_block, to_block, self.max_range) {
+            return self.split(from_block, to_block, depth + 1).await;
+        }
+        let (status, body) = {
+            // The permit is released before querying the split sub-ranges
+            let _permit = self.permits.acquire().await.map_err(|e| {
+                anyhow::anyhow!("Failed to acquire the eth_getLogs call permit: {e}")
+            })?;
+            if self.is_too_large(0) {
+                return Ok((RangeResponse::TooLarge, 0));
+            }
+            let request = range_request(self.request, from_block, to_block);
+            let body = serde_json::to_vec(&request)?;
+            let response = rpc_call_providers(
+                self.state.clone(),
+                self.addr,
+                self.query_params.clone(),
+                self.headers.clone(),
+                Bytes::from(body),
+                Some(request),
+            )
+            .await?;
+            let status = response.status();
+            let body = to_bytes(response.into_body(), PROVIDER_RESPONSE_MAX_BYTES)
+                .await
+                .map_err(|e| {
+                    anyhow::anyhow!("Failed to read the eth_getLogs response body: {e}")
+                })?;
+            (status, body)
+        };
+
+        if can_split && is_logs_range_error(&body) {
+            return self.split(from_block, to_block, depth + 1).await;
+        }
+        let response = range_response(status, body.clone());
+        if matches!(response, RangeResponse::Logs(_)) && self.is_too_large(body.len()) {
+            return Ok((RangeResponse::TooLarge, 1));
+        }
+        Ok((response, 1))
+    }
+
+    /// Resolve the range blocks to the numbers, `None` if any block tag can't
+    /// be resolved. The tags are resolved by the providers call, as the
+    /// tracked chain head may be stale and doesn't apply to the `safe` and
+    /// `finalized` tags.
+    async fn resolve_bounds(&self, from: &LogsBlock, to: &LogsBlock) -> Option<(u64, u64)> {
+        Some((
+            self.resolve_block(from).await?,
+            self.resolve_block(to).await?,
+        ))
+    }
+
+    async fn resolve_block(&self, block: &LogsBlock) -> Option<u64> {
+        let tag = match block {
+            LogsBlock::Number(block) => return Some(*block),
+            LogsBlock::Tag(tag) => tag,
+        };
+        let request = block_tag_request(tag);
+        let body = serde_json::to_vec(&request).ok()?;
+        let number = match rpc_call_providers(
+            self.state.clone(),
+            self.addr,
+            self.query_params.clone(),
+            self.headers.clone(),
+            Bytes::from(body),
+            Some(request),
+        )
+        .await
+        {
+            Ok(response) => to_bytes(response.into_body(), PROVIDER_RESPONSE_MAX_BYTES)
+                .await
+                .ok()
+                .and_then(|body| block_tag_number(&body)),
+            Err(_) => None,
+        };
+        if number.is_none() {
+            debug!(
+                "Failed to resolve the eth_getLogs block tag {tag} for chain_id: {}",
+                self.query_params.chain_id
+            );
+        }
+        number
+    }
+
+    /// Add the logs response size to the merged size and check it against
+    /// the response size limit
+    fn is_too_large(&self, size: usize) -> bool {
+        self.merged_size.fetch_add(size, Ordering::Relaxed) + size > PROVIDER_RESPONSE_MAX_BYTES
+    }
+}
+
+/// Whether the blocks range exceeds the largest range declared by the chain
+/// providers, so no provider is capable of querying it
+fn exceeds_max_range(from_block: u64, to_block: u64, max_range: Option<u64>) -> bool {
+    max_range.is_some_and(|max_range| to_block - from_block > max_range)
+}
+
+/// JSON-RPC error response for the merged logs exceeding the response size
+/// limit
+fn too_large_response(id: Value) -> Result<Response, RpcError> {
+    let response = JsonRpcResponse::Error(JsonRpcError::new(
+        id,
+        ErrorResponse {
+            code: LIMIT_EXCEEDED_CODE,
+            message: "eth_getLogs response exceeds the size limit, query a smaller blocks range"
+                .into(),
+            data: None,
+        },
+    ));
+    Ok((
+        StatusCode::OK,
+        [DEFAULT_CONTENT_TYPE],
+        serde_json::to_string(&response)?,
+    )
+        .into_response())
+}
+
+/// The `eth_getBlockByNumber` request resolving the block tag
+fn block_tag_request(tag: &str) -> JsonRpcRequest {
+    JsonRpcRequest::new_with_params(
+        BLOCK_TAG_REQUEST_ID.into(),
+        "eth_getBlockByNumber".into(),
+        json!([tag, false]),
+    )
+}
+
+/// Block number of the `eth_getBlockByNumber` response, `None` for the
+/// error or the unknown block
+fn block_tag_number(body: &[u8]) -> Option<u64> {
+    serde_json::from_slice::<Value>(body)
+        .ok()?
+        .get("result")?
+        .get("number")?
+        .as_str()?
+        .strip_prefix("0x")
+        .and_then(|hex| u64::from_str_radix(hex, 16).ok())
+}
+
+/// The `eth_getLogs` request for the blocks range of the original filter
+fn range_request(request: &JsonRpcRequest, from_block: u64, to_block: u64) -> JsonRpcRequest {
+    let mut request = request.clone();
+    if let Some(filter) = request
+        .params
+        .get_mut(0)
+        .and_then(|filter| filter.as_object_mut())
+    {
+        filter.insert("fromBlock".into(), format!("{from_block:#x}").into());
+        filter.insert("toBlock".into(), format!("{to_block:#x}").into());
+    }
+    request
+}
+
+fn range_response(status: StatusCode, body: Bytes) -> RangeResponse {
+    if !status.is_success() {
+        return RangeResponse::Other(status, body);
+    }
+    match serde_json::from_slice::<Value>(&body)
+        .ok()
+        .as_mut()
+        .and_then(|response| response.get_mut("result"))
+        .map(Value::take)
+    {
+        Some(Value::Array(logs)) => RangeResponse::Logs(logs),
+        _ => RangeResponse::Other(status, body),
+    }
+}
+
+/// Whether the JSON-RPC response is the provider range or results count
+/// limits error
+fn is_logs_range_error(body: &[u8]) -> bool {
+    serde_json::from_slice::<Value>(body).is_ok_and(|response| {
+        response
+            .get("error")
+            .and_then(|error| error.get("message"))
+            .and_then(Value::as_str)
+            .is_some_and(is_logs_range_error_rpc_message)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, serde_json::json};
+
+    #[test]
+    fn test_range_request() {
+        let request = JsonRpcRequest::new_with_params(
+            json!(1),
+            GET_LOGS_METHOD.into(),
+            json!([{ "fromBlock": "earliest", "toBlock": "latest", "address": "0x0" }]),
+        );
+        let request = range_request(&request, 16, 255);
+        assert_eq!(
+            request.params,
+            json!([{ "fromBlock": "0x10", "toBlock": "0xff", "address": "0x0" }])
+        );
+        assert_eq!(request.id, json!(1));
+    }
+
+    #[test]
+    fn test_block_tag_resolution() {
+        for tag in ["finalized", "latest"] {
+            let filter = json!({ "fromBlock": "0x10", "toBlock": tag });
+            let (_, to) = logs_filter_blocks(&filter).unwrap();
+            assert_eq!(to, LogsBlock::Tag(tag.into()));
+
+            let request = block_tag_request(tag);
+            assert_eq!(request.method.as_ref(), "eth_getBlockByNumber");
+            assert_eq!(request.params, json!([tag, false]));
+        }
+        assert_eq!(
+            block_tag_number(br#"{"jsonrpc":"2.0","id":1,"result":{"number":"0x1312d00"}}"#),
+            Some(20_000_000)
+        );
+        assert_eq!(
+            block_tag_number(br#"{"jsonrpc":"2.0","id":1,"result":null}"#),
+            None
+        );
+        assert_eq!(
+            block_tag_number(
+                br#"{"jsonrpc":"2.0","id":1,"error":{"code":-32000,"message":"unknown block"}}"#
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_exceeds_max_range() {
+        assert!(!exceeds_max_range(0, 10_000, None));
+        assert!(!exceeds_max_range(100, 1_100, Some(1_000)));
+        assert!(exceeds_max_range(100, 1_101, Some(1_000)));
+    }
+
+    #[test]
+    fn test_range_response() {
+        let error = br#"{"jsonrpc":"2.0","id":1,"error":{"code":-32005,"message":"query returned more than 10000 results"}}"#;
+        assert!(is_logs_range_error(error));
+        assert!(matches!(
+            range_response(StatusCode::OK, Bytes::from_static(error)),
+            RangeResponse::Other(..)
+        ));
+        assert!(!is_logs_range_error(
+            br#"{"jsonrpc":"2.0","id":1,"error":{"code":-32000,"message":"execution reverted"}}"#
+        ));
+
+        let logs = br#"{"jsonrpc":"2.0","id":1,"result":[{"blockNumber":"0x1"}]}"#;
+        assert!(!is_logs_range_error(logs));
+        match range_response(StatusCode::OK, Bytes::from_static(logs)) {
+            RangeResponse::Logs(logs) => assert_eq!(logs, vec![json!({ "blockNumber": "0x1" })]),
+            other => panic!("unexpected response {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,963 @@
+use {
+    super::RpcQueryParams,
+    crate::{
+        analytics::MessageInfo,
+        error::RpcError,
+        json_rpc::{
+            ErrorResponse, JsonRpcError, JsonRpcRequest, JsonRpcResponse, METHOD_NOT_SUPPORTED_CODE,
+        },
+        providers::{
+            capabilities::RequiredCapabilities, health::ProviderCallResult,
+            is_internal_error_rpc_code, is_known_rpc_error_message,
+            is_logs_range_error_rpc_message, is_node_error_rpc_message,
+            is_rate_limited_error_rpc_message, rate_limits, tx_tracker, ProviderKind, RpcProvider,
+        },
+        state::AppState,
+        utils::{
+            batch_json_rpc_request::{MaybeBatchRequest, Request},
+            crypto,
+            json_rpc_cache::{
+                cache_response, is_cached_response, response_block_number, PolicyCachedMethods,
+            },
+            network,
+        },
+    },
+    axum::{
+        body::{to_bytes, Body, Bytes},
+        extract::{ConnectInfo, Query, State},
+        response::{IntoResponse, Response},
+    },
+    hyper::{http, HeaderMap},
+    retry::MethodPolicy,
+    std::{
+        borrow::Borrow,
+        collections::HashSet,
+        net::SocketAddr,
+        sync::Arc,
+        time::{Duration, SystemTime},
+    },
+    tap::TapFallible,
+    tokio::time::timeout,
+    tracing::{
+        log::{debug, error, warn},
+        Span,
+    },
+    wc::metrics::{future_metrics, FutureExt},
+};
+
+pub mod access;
+pub mod batch;
+pub mod coalescing;
+pub mod hedging;
+pub mod logs;
+pub mod private_tx;
+pub mod retry;
+pub mod sticky;
+
+const PROVIDER_PROXY_MAX_CALLS: usize = 5;
+const PROVIDER_PROXY_CALL_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_CONTENT_TYPE: (&str, &str) = ("content-type", "application/json");
+pub const PROVIDER_RESPONSE_MAX_BYTES: usize = 10 * 1024 * 1024; // 10 Mb
+
+pub async fn handler(
+    state: State<Arc<AppState>>,
+    addr: ConnectInfo<SocketAddr>,
+    query_params: Query<RpcQueryParams>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, RpcError> {
+    handler_internal(state, addr, query_params, headers, body)
+        .with_metrics(future_metrics!("handler_task", "name" => "proxy"))
+        .await
+}
+
+#[tracing::instrument(skip_all, level = "debug")]
+async fn handler_internal(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Query(query_params): Query<RpcQueryParams>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, RpcError> {
+    // Don't validate the quota and validate project access only
+    // if the chainId is in the skip_quota_chains list
+    if state
+        .config
+        .server
+        .skip_quota_chains
+        .contains(&query_params.chain_id)
+    {
+        state
+            .validate_project_access(&query_params.project_id.clone())
+            .await?;
+    } else {
+        state
+            .validate_project_access_and_quota(&query_params.project_id.clone())
+            .await?;
+    };
+
+    // Methods are checked against the project and chain access rules
+    if let Some(response) = access::check_method_access(&state, &query_params, &body).await? {
+        return Ok(response);
+    }
+
+    rpc_call(state, addr, query_params, headers, body).await
+}
+
+#[tracing::instrument(skip(state), level = "debug")]
+pub async fn rpc_call(
+    state: Arc<AppState>,
+    addr: SocketAddr,
+    query_params: RpcQueryParams,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, RpcError> {
+    let chain_id = query_params.chain_id.clone();
+
+    // Deserializing the request body to a JSON-RPC request schema and
+    // check if a cached response can be returned. Requests to the exact
+    // provider are not served from the cache.
+    // TODO: Optimize this to remove the second deserialization during the provider analytics
+    let rpc_request = match serde_json::from_slice::<JsonRpcRequest>(&body) {
+        Ok(request) => {
+            if query_params.provider_id.is_some() {
+                Some(request)
+            } else if let Some(response) = is_cached_response(
+                &chain_id,
+                &request,
+                &state.metrics,
+                &state.moka_cache,
+                state.providers.json_rpc_cache_redis_pool.as_ref(),
+            )
+            .await
+            {
+                return Ok((
+                    http::StatusCode::OK,
+                    [DEFAULT_CONTENT_TYPE],
+                    serde_json::to_string(&response)?,
+                )
+                    .into_response());
+            } else {
+                Some(request)
+            }
+        }
+        Err(e) => {
+            if let Ok(requests) = serde_json::from_slice::<Vec<JsonRpcRequest>>(&body) {
+                // Batch items are not submitted through the private relays, the
+                // opted-in raw transactions must not reach the public providers
+                if query_params.provider_id.is_none()
+                    && private_tx::has_raw_transaction(&requests)
+                    && state.private_tx.is_requested(&query_params)
+                {
+                    return private_tx::private_batch_response(&requests);
+                }
+                if state
+                    .config
+                    .providers
+                    .batch_splitting_enabled
+                    .unwrap_or(false)
+                {
+                    return batch::split_batch_call(state, addr, query_params, headers, requests)
+                        .await;
+                }
+            }
+            error!("Failed to deserialize JSON-RPC request: {e}");
+            None
+        }
+    };
+
+    if let Some(request) = rpc_request {
+        // Opted-in raw transactions are submitted through the private relays
+        if query_params.provider_id.is_none()
+            && request.method.as_ref() == private_tx::SEND_RAW_TRANSACTION_METHOD
+            && state.private_tx.is_requested(&query_params)
+        {
+            return private_tx::private_send_call(
+                state,
+                addr,
+                query_params,
+                headers,
+                body,
+                request,
+            )
+            .await;
+        }
+
+        // Logs requests rejected for the range limits are split into the
+        // sub-ranges instead of being coalesced
+        if query_params.provider_id.is_none()
+            && request.method.as_ref() == logs::GET_LOGS_METHOD
+            && state
+                .config
+                .providers
+                .logs_splitting_enabled
+                .unwrap_or(false)
+        {
+            return logs::split_logs_call(state, addr, query_params, headers, body, request).await;
+        }
+
+        // Requests to the exact or sticky provider are not coalesced
+        if query_params.provider_id.is_none()
+            && query_params.session_id.is_none()
+            && state.coalescing.is_enabled_for(&request.method)
+        {
+            return coalescing::coalesced_rpc_call(
+                state,
+                addr,
+                query_params,
+                headers,
+                body,
+                request,
+            )
+            .await;
+        }
+        return rpc_call_providers(state, addr, query_params, headers, body, Some(request)).await;
+    }
+
+    rpc_call_providers(state, addr, query_params, headers, body, None).await
+}
+
+/// Call the providers for the chain with retries. `rpc_request` is the
+/// deserialized single JSON-RPC request if the body is not a batch.
+#[tracing::instrument(skip(state), level = "debug")]
+async fn rpc_call_providers(
+    state: Arc<AppState>,
+    addr: SocketAddr,
+    query_params: RpcQueryParams,
+    headers: HeaderMap,
+    body: Bytes,
+    rpc_request: Option<JsonRpcRequest>,
+) -> Result<Response, RpcError> {
+    let chain_id = query_params.chain_id.clone();
+
+    // Start timing the total chain request (including retries)
+    let chain_request_start = SystemTime::now();
+
+    // Exact provider proxy request for testing suite
+    // This request is allowed only for the RPC_PROXY_TESTING_PROJECT_ID
+    let providers = match query_params.provider_id.clone() {
+        Some(provider_id) => {
+            let provider = vec![state
+                .providers
+                .get_rpc_provider_by_provider_id(&provider_id)
+                .ok_or_else(|| RpcError::UnsupportedProvider(provider_id.clone()))?];
+
+            if let Some(ref testing_project_id) = state.config.server.testing_project_id {
+                if !crypto::constant_time_eq(testing_project_id, &query_params.project_id) {
+                    return Err(RpcError::InvalidParameter(format!(
+                        "The project ID {} is not allowed to use the exact provider request",
+                        query_params.project_id
+                    )));
+                }
+            } else {
+                return Err(RpcError::InvalidParameter(
+                    "RPC_PROXY_TESTING_PROJECT_ID should be configured for this type of request"
+                        .into(),
+                ));
+            }
+
+            provider
+        }
+        None => {
+            // Only the providers having the capabilities required by the
+            // methods and the block parameters are called
+            let head = state.providers.chain_head(&chain_id);
+            let mut required = match &rpc_request {
+                Some(request) => RequiredCapabilities::for_request(request, head),
+                None => serde_json::from_slice::<Vec<JsonRpcRequest>>(&body)
+                    .map(|requests| RequiredCapabilities::for_batch(&requests, head))
+                    .unwrap_or_default(),
+            };
+            let select_providers = |required: &RequiredCapabilities| {
+                state
+                    .providers
+                    .get_rpc_provider_for_chain_id_with_capabilities(
+                        &chain_id,
+                        PROVIDER_PROXY_MAX_CALLS,
+                        required,
+                    )
+            };
+            let mut providers = select_providers(&required);
+            // The batch is not split without the batch splitting, so the
+            // providers accepting the batch size are only preferred
+            let batch_splitting = state
+                .config
+                .providers
+                .batch_splitting_enabled
+                .unwrap_or(false);
+            if !batch_splitting
+                && required.batch_size.is_some()
+                && matches!(providers, Err(RpcError::NoCapableProviders { .. }))
+            {
+                required.batch_size = None;
+                providers = select_providers(&required);
+            }
+            match providers {
+                Err(e @ RpcError::NoCapableProviders { .. }) => {
+                    state
+                        .metrics
+                        .add_no_capable_providers(chain_id.clone(), required.names().join(","));
+                    return no_capable_providers_response(&e, rpc_request.as_ref(), &body);
+                }
+                providers => providers?,
+            }
+        }
+    };
+
+    let call_context = ProviderCallContext {
+        state: &state,
+        addr,
+        query_params: &query_params,
+        headers: &headers,
+        body: &body,
+        rpc_request: rpc_request.as_ref(),
+        chain_request_start,
+    };
+
+    // Transactions are broadcast according to the write methods policy
+    // instead of retrying them to the next providers
+    if let MethodPolicy::Broadcast(mode) = state
+        .retry_policy
+        .method_policy(rpc_request.as_ref(), &body)
+    {
+        return retry::broadcast_call(&call_context, &providers, mode).await;
+    }
+
+    // Reads are retried to the next providers within the retry budget
+    // and the overall deadline
+    let retry_budget = providers.len().min(state.retry_policy.read_retry_budget());
+    let deadline = state.retry_policy.read_deadline();
+    match timeout(deadline, read_call(&call_context, &providers, retry_budget)).await {
+        Ok(response) => response,
+        Err(_) => {
+            state
+                .metrics
+                .add_rpc_call_deadline_exceeded(chain_id.clone());
+            debug!("Providers didn't respond within {deadline:?} for chain_id: {chain_id}");
+            Err(RpcError::ChainTemporarilyUnavailable(chain_id))
+        }
+    }
+}
+
+/// JSON-RPC error response for each request when no chain provider has the
+/// capabilities required by the request
+fn no_capable_providers_response(
+    error: &RpcError,
+    rpc_request: Option<&JsonRpcRequest>,
+    body: &Bytes,
+) -> Result<Response, RpcError> {
+    let error_response = |id: serde_json::Value| {
+        JsonRpcResponse::Error(JsonRpcError::new(
+            id,
+            ErrorResponse {
+                code: METHOD_NOT_SUPPORTED_CODE,
+                message: error.to_string().into(),
+                data: None,
+            },
+        ))
+    };
+    let response = match rpc_request {
+        Some(request) => serde_json::to_string(&error_response(request.id.clone()))?,
+        None => {
+            let requests = serde_json::from_slice::<Vec<JsonRpcRequest>>(body)?;
+            serde_json::to_string(
+                &requests
+                    .into_iter()
+                    .map(|request| error_response(request.id))
+                    .collect::<Vec<_>>(),
+            )?
+        }
+    };
+    Ok((
+        http::StatusCode::BAD_REQUEST,
+        [DEFAULT_CONTENT_TYPE],
+        response,
+    )
+        .into_response())
+}
+
+/// Call the providers for the read request sequentially until the first
+/// valid response, up to the `retry_budget` providers are called
+async fn read_call(
+    call_context: &ProviderCallContext<'_>,
+    providers: &[Arc<dyn RpcProvider>],
+    retry_budget: usize,
+) -> Result<Response, RpcError> {
+    let state = call_context.state;
+    let query_params = call_context.query_params;
+    let chain_id = query_params.chain_id.clone();
+
+    // Keep the session on the same provider to have the consistent block height
+    // between the consecutive calls
+    if let (Some(session_id), None) = (&query_params.session_id, &query_params.provider_id) {
+        return sticky::sticky_session_call(call_context, session_id, providers, retry_budget)
+            .await;
+    }
+    let providers = &providers[..retry_budget];
+
+    // Send the speculative (hedged) request to the second provider if the first one
+    // didn't respond within the chain latency budget
+    let mut sequential_start = 0;
+    let is_hedged = call_context
+        .rpc_request
+        .is_some_and(|request| state.hedging.is_enabled_for(&request.method))
+        && providers.len() > 1;
+    if is_hedged {
+        if let Some(response) =
+            hedged_provider_call(call_context, &providers[0], &providers[1]).await
+        {
+            return Ok(response);
+        }
+        sequential_start = 2;
+    }
+
+    for (i, provider) in providers.iter().enumerate().skip(sequential_start) {
+        if let ProviderCallOutcome::Response(response) =
+            call_context.call_provider(i, provider).await
+        {
+            return Ok(response);
+        }
+    }
+
+    state.metrics.add_no_providers_for_chain(chain_id.clone());
+    debug!("All providers failed for chain_id: {chain_id}");
+    Err(RpcError::ChainTemporarilyUnavailable(chain_id))
+}
+
+/// Outcome of the single provider call within the providers retrying loop
+enum ProviderCallOutcome {
+    /// Response that should be returned to the client
+    Response(Response),
+    /// The provider failed and the next provider should be tried
+    Retry,
+}
+
+impl ProviderCallOutcome {
+    fn into_option(self) -> Option<Response> {
+        match self {
+            Self::Response(response) => Some(response),
+            Self::Retry => None,
+        }
+    }
+}
+
+/// Request data shared between the provider calls of the same RPC request
+struct ProviderCallContext<'a> {
+    state: &'a Arc<AppState>,
+    addr: SocketAddr,
+    query_params: &'a RpcQueryParams,
+    headers: &'a HeaderMap,
+    body: &'a Bytes,
+    /// Deserialized single JSON-RPC request for the response caching
+    rpc_request: Option<&'a JsonRpcRequest>,
+    chain_request_start: SystemTime,
+}
+
+impl ProviderCallContext<'_> {
+    /// Record the provider block height if the method returns the chain head
+    /// and cache the successful provider response result if the method is cacheable,
+    /// the exact provider responses are not cached
+    async fn process_result(
+        &self,
+        provider_kind: &ProviderKind,
+        request: &JsonRpcRequest,
+        result: &str,
+    ) {
+        if request.method.parse::<PolicyCachedMethods>().is_err() {
+            return;
+        }
+        let result = match serde_json::from_str::<serde_json::Value>(result) {
+            Ok(result) => result,
+            Err(e) => {
+                error!("Failed to parse JSON-RPC response result for caching: {e}");
+                return;
+            }
+        };
+
+        let chain_id = &self.query_params.chain_id;
+        if let Some(block) = response_block_number(&request.method, &request.params, &result) {
+            self.state
+                .providers
+                .record_provider_head(provider_kind, chain_id, block);
+        }
+        // Responses of the exact provider are not cached for the other clients
+        if self.query_params.provider_id.is_some() {
+            return;
+        }
+        cache_response(
+            chain_id,
+            request,
+            &result,
+            self.state.providers.chain_head(chain_id),
+            &self.state.moka_cache,
+            self.state.providers.json_rpc_cache_redis_pool.as_ref(),
+        )
+        .await
+    }
+
+    /// Call the provider and check whether the response can be returned to the client
+    /// or the next provider should be tried. `i` is the provider attempt index.
+    async fn call_provider(
+        &self,
+        i: usize,
+        provider: &Arc<dyn RpcProvider>,
+    ) -> ProviderCallOutcome {
+        let state = self.state;
+        let chain_id = self.query_params.chain_id.clone();
+
+        let provider_call = rpc_provider_call(
+            state.clone(),
+            self.addr,
+            self.query_params.clone(),
+            self.headers.clone(),
+            self.body.clone(),
+            provider.clone(),
+        )
+        .await;
+
+        let response_result = match provider_call {
+            Ok(response) => response,
+            Err(RpcError::ProviderCircuitOpen(provider_kind)) => {
+                debug!("Provider {provider_kind} circuit probe is in progress, trying the next provider");
+                state
+                    .metrics
+                    .add_rpc_call_retries(i as u64, chain_id.clone());
+                return ProviderCallOutcome::Retry;
+            }
+            Err(e) => {
+                error!(
+                    "Call to provider '{}' returned a connection error {e:?}, trying the next provider",
+                    provider.provider_kind()
+                );
+                state
+                    .metrics
+                    .add_provider_connection_error(chain_id.clone(), provider.borrow());
+                state
+                    .metrics
+                    .add_rpc_call_retries(i as u64, chain_id.clone());
+                return ProviderCallOutcome::Retry;
+            }
+        };
+
+        // Proceed to the result if the status is success or
+        // any client error except the rate limited error
+        let provider_kind = provider.provider_kind();
+        let status = response_result.status();
+        if status.is_success() || status == http::StatusCode::BAD_REQUEST {
+            let body_bytes =
+                match to_bytes(response_result.into_body(), PROVIDER_RESPONSE_MAX_BYTES).await {
+                    Ok(bytes) => bytes,
+                    Err(e) => {
+                        error!(
+                        "Failed to read JSON-RPC response body from provider {provider_kind}: {e}"
+                    );
+                        state
+                            .metrics
+                            .add_rpc_call_retries(i as u64, chain_id.clone());
+                        return ProviderCallOutcome::Retry;
+                    }
+                };
+
+            // Check the JSON-RPC response schema and possible internal error codes
+            match serde_json::from_slice::<jsonrpc::Response>(&body_bytes) {
+                Ok(json_response) => {
+                    if let (Some(request), Some(result), true) =
+                        (self.rpc_request, &json_response.result, status.is_success())
+                    {
+                        self.process_result(&provider_kind, request, result.get())
+                            .await;
+                    }
+
+                    if let Some(error) = &json_response.error {
+                        let error_code = error.code;
+                        let error_message = error.message.clone();
+
+                        // Internal error codes range -32000..-32099 https://www.jsonrpc.org/specification#error_object
+                        if is_internal_error_rpc_code(error_code) {
+                            // Retry to another provider if the error is a rate limited or node error,
+                            // the rate-limited provider is backed off for the chain
+                            if is_rate_limited_error_rpc_message(&error_message) {
+                                state.providers.record_rate_limited(
+                                    &state.metrics,
+                                    &provider_kind,
+                                    &chain_id,
+                                    None,
+                                );
+                                state
+                                    .metrics
+                                    .add_rpc_call_retries(i as u64, chain_id.clone());
+                                return ProviderCallOutcome::Retry;
+                            }
+                            if is_node_error_rpc_message(&error_message) {
+                                state
+                                    .metrics
+                                    .add_rpc_call_retries(i as u64, chain_id.clone());
+                                return ProviderCallOutcome::Retry;
+                            }
+
+                            // Log an error, increment the metrics for unknown error codes and continue
+                            // without retrying since it can be a contract execution error.
+                            // We should catch unknown errors by alarm for the metrics
+                            // and investigate it first without retrying.
+                            if !is_known_rpc_error_message(&error_message)
+                                && !is_logs_range_error_rpc_message(&error_message)
+                            {
+                                error!("Provider {provider_kind} returned an error code: {error_code} and the message: {error_message}");
+                                state.metrics.add_internal_error_code_for_provider(
+                                    provider_kind,
+                                    chain_id.clone(),
+                                    error.code,
+                                );
+                            }
+                        }
+                    }
+                }
+                Err(e) => {
+                    error!("Failed to parse JSON-RPC response from provider {provider_kind}: {e}. Message: {}", String::from_utf8_lossy(&body_bytes));
+                }
+            }
+
+            state
+                .metrics
+                .add_found_provider_for_chain(chain_id.clone(), &provider.provider_kind());
+
+            // Record successful chain latency for the provider that succeeded
+            // and return the response
+            state.metrics.add_chain_latency(
+                &provider.provider_kind(),
+                self.chain_request_start,
+                chain_id.clone(),
+            );
+            return ProviderCallOutcome::Response(
+                (status, [DEFAULT_CONTENT_TYPE], body_bytes).into_response(),
+            );
+        }
+
+        debug!(
+            "Provider '{}' returned unsuccessful status {}, trying the next provider",
+            provider.provider_kind(),
+            status
+        );
+        state
+            .metrics
+            .add_rpc_call_retries(i as u64, chain_id.clone());
+        ProviderCallOutcome::Retry
+    }
+}
+
+/// Call the primary provider and if it doesn't respond within the chain
+/// hedging delay send the same request to the secondary provider.
+/// The first valid response is returned and the other call is cancelled.
+/// Returns `None` if both providers failed.
+async fn hedged_provider_call(
+    call_context: &ProviderCallContext<'_>,
+    primary: &Arc<dyn RpcProvider>,
+    secondary: &Arc<dyn RpcProvider>,
+) -> Option<Response> {
+    let state = call_context.state;
+    let chain_id = call_context.query_params.chain_id.clone();
+    let delay = state.hedging.delay(&chain_id);
+
+    let primary_call = call_context.call_provider(0, primary);
+    tokio::pin!(primary_call);
+
+    tokio::select! {
+        outcome = &mut primary_call => {
+            return match outcome {
+                ProviderCallOutcome::Response(response) => Some(response),
+                // The primary provider failed before the hedging delay,
+                // so just proceed to the secondary provider
+                ProviderCallOutcome::Retry => {
+                    call_context.call_provider(1, secondary).await.into_option()
+                }
+            };
+        }
+        _ = tokio::time::sleep(delay) => {}
+    }
+
+    debug!(
+        "Provider '{}' didn't respond within {delay:?}, sending hedged request to '{}'",
+        primary.provider_kind(),
+        secondary.provider_kind()
+    );
+    state
+        .metrics
+        .add_hedged_rpc_call(chain_id.clone(), &secondary.provider_kind());
+
+    let secondary_call = call_context.call_provider(1, secondary);
+    tokio::pin!(secondary_call);
+
+    // Dropping the losing call future cancels the request to the provider
+    let (response, is_hedge_winner) = tokio::select! {
+        outcome = &mut primary_call => match outcome {
+            ProviderCallOutcome::Response(response) => (Some(response), false),
+            ProviderCallOutcome::Retry => (secondary_call.await.into_option(), true),
+        },
+        outcome = &mut secondary_call => match outcome {
+            ProviderCallOutcome::Response(response) => (Some(response), true),
+            ProviderCallOutcome::Retry => (primary_call.await.into_option(), false),
+        },
+    };
+
+    if response.is_some() {
+        let winner = if is_hedge_winner { secondary } else { primary };
+        state.metrics.add_hedged_rpc_call_winner(
+            chain_id,
+            &winner.provider_kind(),
+            is_hedge_winner,
+        );
+    }
+    response
+}
+
+// TODO eventually refactor this to be called by the wallet handler (generic JSON-RPC)
+// However, dependency on us having an exaustive list of supported RPC methods is a blocker to merging these handlers.
+#[tracing::instrument(skip(state), level = "debug")]
+pub async fn rpc_provider_call(
+    state: Arc<AppState>,
+    addr: SocketAddr,
+    query_params: RpcQueryParams,
+    headers: HeaderMap,
+    body: Bytes,
+    provider: Arc<dyn RpcProvider>,
+) -> Result<Response, RpcError> {
+    Span::current().record("provider", provider.provider_kind().to_string());
+    let chain_id = query_params.chain_id.clone();
+
+    // The half-open circuit probing call slot is taken only by the provider
+    // being called, the exact provider requests bypass the circuit breaker
+    if query_params.provider_id.is_none()
+        && !state
+            .providers
+            .try_acquire_rpc_call(&provider.provider_kind(), &chain_id)
+    {
+        return Err(RpcError::ProviderCircuitOpen(
+            provider.provider_kind().to_string(),
+        ));
+    }
+    let origin = headers
+        .get("origin")
+        .map(|v| Arc::from(v.to_str().unwrap_or("invalid_header").to_string()));
+
+    state
+        .metrics
+        .add_rpc_call(chain_id.clone(), &provider.provider_kind());
+
+    let (country, continent, region) = state
+        .analytics
+        .lookup_geo_data(network::get_forwarded_ip(&headers).unwrap_or_else(|| addr.ip()))
+        .map(|geo| (geo.country, geo.continent, geo.region))
+        .unwrap_or((None, None, None));
+
+    // Approximate compute units of the call for the provider budget
+    let mut compute_units = 1;
+    // Request ids and raw transactions of the submissions to track
+    let mut raw_transactions = Vec::new();
+    match serde_json::from_slice::<MaybeBatchRequest>(&body) {
+        Ok(body) => {
+            if state.tx_tracker.is_enabled() {
+                raw_transactions = match &body {
+                    MaybeBatchRequest::Single(req) => raw_transaction(req).into_iter().collect(),
+                    MaybeBatchRequest::Batch(reqs) => {
+                        reqs.iter().filter_map(raw_transaction).collect()
+                    }
+                };
+            }
+            let rpcs = match &body {
+                MaybeBatchRequest::Single(req) => {
+                    vec![(req.id.to_string(), req.method.to_string())]
+                }
+                MaybeBatchRequest::Batch(reqs) => {
+                    {
+                        // Validate unique RPC IDs
+                        let mut ids = HashSet::new();
+                        for req in reqs {
+                            if !ids.insert(&req.id) {
+                                // TODO turn this into a 4xx error after validating with data that this behavior isn't widely depended on.
+                                // Duplicates are already rejected with the 4xx when the batch splitting is enabled.
+                                error!(
+                                    "Duplicate RPC ID: {:?} for body {}",
+                                    req.id,
+                                    serde_json::to_string(&body).unwrap_or_default()
+                                );
+                            }
+                        }
+                    }
+
+                    reqs.iter()
+                        .map(|req| (req.id.to_string(), req.method.to_string()))
+                        .collect()
+                }
+            };
+
+            compute_units = rpcs
+                .iter()
+                .map(|(_, rpc_method)| rate_limits::method_compute_units(rpc_method))
+                .sum();
+            for (rpc_id, rpc_method) in rpcs {
+                state.analytics.message(MessageInfo::new(
+                    &query_params,
+                    &headers,
+                    query_params.session_id.clone(),
+                    rpc_id,
+                    rpc_method,
+                    region.clone(),
+                    country.clone(),
+                    continent.clone(),
+                    &provider.provider_kind(),
+                    origin.clone(),
+                    query_params.sdk_info.sv.clone(),
+                    query_params.sdk_info.st.clone(),
+                ));
+            }
+        }
+        Err(e) => {
+            // TODO turn this into a 4xx error after validating with data that this behavior isn't widely depended on
+            error!(
+                "TRIAGE: Invalid JSON-RPC request: {} for body: {}",
+                e,
+                String::from_utf8_lossy(&body)
+            );
+        }
+    }
+
+    let project_id = query_params.project_id.clone();
+    // Start timing external provider added time
+    let external_call_start = SystemTime::now();
+
+    state.providers.consume_provider_budget(
+        &state.metrics,
+        &provider.provider_kind(),
+        compute_units,
+    );
+    let proxy_fut = provider.proxy(&chain_id, body);
+    let timeout_fut = timeout(PROVIDER_PROXY_CALL_TIMEOUT, proxy_fut);
+    let mut response = timeout_fut
+        .await
+        .tap_err(|e| {
+            warn!(
+                "Timeout calling provider: {} with {}",
+                provider.provider_kind(),
+                e
+            );
+            state.providers.record_rpc_call_result(
+                &state.metrics,
+                &provider.provider_kind(),
+                &chain_id,
+                ProviderCallResult::Timeout,
+            );
+        })
+        .map_err(RpcError::ProxyTimeoutError)?
+        .tap_err(|e| {
+            warn!(
+                "Failed call to provider: {} with {}",
+                provider.provider_kind(),
+                e
+            );
+            state.providers.record_rpc_call_result(
+                &state.metrics,
+                &provider.provider_kind(),
+                &chain_id,
+                ProviderCallResult::ConnectionError,
+            );
+        })?;
+
+    state.metrics.add_status_code_for_provider(
+        &provider.provider_kind(),
+        response.status().as_u16(),
+        Some(chain_id.clone()),
+        None,
+    );
+
+    let is_rate_limited = provider.is_rate_limited(&mut response).await;
+    if is_rate_limited {
+        state
+            .metrics
+            .add_rate_limited_call(provider.borrow(), project_id);
+        *response.status_mut() = http::StatusCode::SERVICE_UNAVAILABLE;
+    }
+
+    // Honour the backoff requested by the provider rate limits headers
+    let requested_backoff = rate_limits::requested_backoff(response.headers(), is_rate_limited);
+    if is_rate_limited || requested_backoff.is_some() {
+        state.providers.record_rate_limited(
+            &state.metrics,
+            &provider.provider_kind(),
+            &chain_id,
+            requested_backoff,
+        );
+    }
+    rate_limits::strip_headers(response.headers_mut());
+
+    state.metrics.add_external_http_latency(
+        &provider.provider_kind(),
+        external_call_start,
+        Some(chain_id.clone()),
+        None,
+    );
+
+    match response.status() {
+        http::StatusCode::OK | http::StatusCode::BAD_REQUEST => {
+            let latency = external_call_start.elapsed().unwrap_or_default();
+            state.hedging.record_latency(&chain_id, latency);
+            state.providers.record_rpc_call_result(
+                &state.metrics,
+                &provider.provider_kind(),
+                &chain_id,
+                ProviderCallResult::Success(latency),
+            );
+            state
+                .metrics
+                .add_finished_provider_call(chain_id, provider.borrow());
+        }
+        _ => {
+            error!(
+                "Call to provider '{}' failed with status '{}' and body '{:?}'",
+                provider.provider_kind(),
+                response.status(),
+                response.body()
+            );
+            state.providers.record_rpc_call_result(
+                &state.metrics,
+                &provider.provider_kind(),
+                &chain_id,
+                if is_rate_limited {
+                    ProviderCallResult::RateLimited
+                } else {
+                    ProviderCallResult::Failure
+                },
+            );
+            state
+                .metrics
+                .add_failed_provider_call(chain_id, provider.borrow());
+            *response.status_mut() = http::StatusCode::SERVICE_UNAVAILABLE;
+        }
+    };
+
+    if response.status() == http::StatusCode::OK && !raw_transactions.is_empty() {
+        let (parts, body) = response.into_parts();
+        let body = to_bytes(body, PROVIDER_RESPONSE_MAX_BYTES)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to read the provider response body: {e}"))?;
+        for raw_tx in tx_tracker::accepted_transactions(&body, &raw_transactions) {
+            state.tx_tracker.track(
+                raw_tx,
+                &query_params.chain_id,
+                &query_params.project_id,
+                &provider.provider_kind().to_string(),
+            );
+        }
+        return Ok(Response::from_parts(parts, Body::from(body)));
+    }
+    Ok(response)
+}
+
+/// Serialized request id and the raw transaction of the
+/// `eth_sendRawTransaction` request
+fn raw_transaction(request: &Request) -> Option<(String, String)> {
+    if request.method != private_tx::SEND_RAW_TRANSACTION_METHOD {
+        return None;
+    }
+    let raw_tx = request.params.as_ref()?.get(0)?.as_str()?;
+    Some((serde_json::to_string(&request.id).ok()?, raw_tx.to_string()))
+}
@@ -0,0 +1,405 @@
+use {
+    super::{rpc_call_providers, DEFAULT_CONTENT_TYPE},
+    crate::{
+        error::RpcError,
+        handlers::RpcQueryParams,
+        json_rpc::{
+            ErrorResponse, JsonRpcError, JsonRpcRequest, JsonRpcResponse, TRANSACTION_REJECTED_CODE,
+        },
+        providers::{http_client, ProviderKind, ProvidersConfig},
+        state::AppState,
+    },
+    axum::{
+        body::Bytes,
+        response::{IntoResponse, Response},
+    },
+    hyper::{
+        http::{HeaderValue, StatusCode},
+        HeaderMap,
+    },
+    reqwest::Url,
+    serde_json::Value,
+    std::{
+        collections::{HashMap, HashSet},
+        net::SocketAddr,
+        sync::Arc,
+        time::Duration,
+    },
+    tracing::log::{debug, warn},
+};
+
+pub const SEND_RAW_TRANSACTION_METHOD: &str = "eth_sendRawTransaction";
+/// Response header reporting whether the transaction was submitted through
+/// the private relay or the public providers
+pub const SUBMISSION_PATH_HEADER: &str = "x-tx-submission-path";
+const RELAY_CALL_TIMEOUT: Duration = Duration::from_secs(5);
+/// Upstream client name of the relays for the `http_clients` overrides
+const RELAY_CLIENT_KIND: &str = "private_tx_relay";
+
+/// Transaction submission path reported to the client
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubmissionPath {
+    Private,
+    Public,
+}
+
+impl SubmissionPath {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Private => "private",
+            Self::Public => "public",
+        }
+    }
+}
+
+/// Private transaction relays (builders) per chain for the MEV-protected
+/// transactions submission
+#[derive(Debug, Default)]
+pub struct PrivateTxRelays {
+    relays: HashMap<String, Vec<Url>>,
+    /// Projects submitting the transactions privately without the query param
+    projects: HashSet<String>,
+}
+
+impl PrivateTxRelays {
+    pub fn new(config: &ProvidersConfig) -> Self {
+        let mut relays = HashMap::<String, Vec<Url>>::new();
+        for entry in config.private_tx_relays.iter().flatten() {
+            match parse_relay(entry) {
+                Some((chain_id, url)) => relays.entry(chain_id).or_default().push(url),
+                None => warn!("Skipping invalid private transaction relay entry: {entry}"),
+            }
+        }
+        Self {
+            relays,
+            projects: config
+                .private_tx_projects
+                .clone()
+                .unwrap_or_default()
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    /// Whether the private submission is requested by the `privateTx` query
+    /// param or enabled for the project
+    pub fn is_requested(&self, query_params: &RpcQueryParams) -> bool {
+        query_params
+            .private_tx
+            .unwrap_or_else(|| self.projects.contains(&query_params.project_id))
+    }
+
+    fn chain_relays(&self, chain_id: &str) -> &[Url] {
+        self.relays
+            .get(chain_id)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+}
+
+/// Whether any request of the batch submits a raw transaction
+pub fn has_raw_transaction(requests: &[JsonRpcRequest]) -> bool {
+    requests
+        .iter()
+        .any(|request| request.method.as_ref() == SEND_RAW_TRANSACTION_METHOD)
+}
+
+/// JSON-RPC error responses to the requests which can't be submitted
+/// privately, the batch is rejected as a whole so none of its transactions
+/// reach the public mempool
+fn unavailable_responses(requests: &[JsonRpcRequest], message: &str) -> Vec<JsonRpcResponse> {
+    requests
+        .iter()
+        .map(|request| {
+            JsonRpcResponse::Error(JsonRpcError::new(
+                request.id.clone(),
+                ErrorResponse {
+                    code: TRANSACTION_REJECTED_CODE,
+                    message: message.to_string().into(),
+                    data: None,
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Reject the batch containing the raw transactions when the private
+/// submission is requested, the batch items are not submitted through the
+/// relays
+pub fn private_batch_response(requests: &[JsonRpcRequest]) -> Result<Response, RpcError> {
+    let responses = unavailable_responses(
+        requests,
+        "Private transaction submission is not available for the batch requests",
+    );
+    Ok((
+        StatusCode::BAD_REQUEST,
+        [DEFAULT_CONTENT_TYPE],
+        serde_json::to_string(&responses)?,
+    )
+        .into_response())
+}
+
+/// Parse the `<caip2>=<relay_url>` entry
+fn parse_relay(entry: &str) -> Option<(String, Url)> {
+    let (chain_id, url) = entry.split_once('=')?;
+    if !chain_id.contains(':') {
+        return None;
+    }
+    Some((chain_id.to_string(), Url::parse(url).ok()?))
+}
+
+/// Relay response to the transaction submission
+#[derive(Debug)]
+enum RelayOutcome {
+    Accepted(Bytes),
+    /// Explicit JSON-RPC error of the relay
+    Rejected(String),
+    /// Transport failure or ambiguous response, the relay may have accepted
+    /// the transaction
+    Failed(String),
+}
+
+/// Submit the raw transaction to the chain private relays in order and fall
+/// back to the public providers only if all relays explicitly rejected it.
+/// The transaction is rejected if the chain has no relays. The relay
+/// transport failure is returned to the client since the
+/// transaction may be already accepted by the relay and must not reach the
+/// public mempool. The used path is reported by the `SUBMISSION_PATH_HEADER`
+/// response header.
+pub async fn private_send_call(
+    state: Arc<AppState>,
+    addr: SocketAddr,
+    query_params: RpcQueryParams,
+    headers: HeaderMap,
+    body: Bytes,
+    request: JsonRpcRequest,
+) -> Result<Response, RpcError> {
+    let chain_id = query_params.chain_id.clone();
+    let relays = state.private_tx.chain_relays(&chain_id);
+    if relays.is_empty() {
+        debug!("No private transaction relays for chain_id: {chain_id}");
+        let response = unavailable_responses(
+            std::slice::from_ref(&request),
+            &format!("Private transaction submission is not available for the chain {chain_id}"),
+        );
+        return Ok((
+            StatusCode::BAD_REQUEST,
+            [DEFAULT_CONTENT_TYPE],
+            serde_json::to_string(&response[0])?,
+        )
+            .into_response());
+    }
+    let http_client =
+        http_client::upstream_client(&ProviderKind::Generic(RELAY_CLIENT_KIND.to_string()));
+    for relay in relays {
+        let relay_host = relay.host_str().unwrap_or_default().to_string();
+        match relay_call(&http_client, relay, &body).await {
+            RelayOutcome::Accepted(response) => {
+                debug!("Transaction is submitted through the private relay {relay_host}");
+                if let Some(raw_tx) = request.params.get(0).and_then(Value::as_str) {
+                    state.tx_tracker.track(
+                        raw_tx,
+                        &chain_id,
+                        &query_params.project_id,
+                        &format!("private:{relay_host}"),
+                    );
+                }
+                state
+                    .metrics
+                    .add_tx_submission(chain_id, SubmissionPath::Private.as_str());
+                return Ok((
+                    StatusCode::OK,
+                    [
+                        DEFAULT_CONTENT_TYPE,
+                        (SUBMISSION_PATH_HEADER, SubmissionPath::Private.as_str()),
+                    ],
+                    response,
+                )
+                    .into_response());
+            }
+            RelayOutcome::Rejected(reason) => {
+                warn!("Private relay {relay_host} rejected the transaction for chain_id: {chain_id}: {reason}");
+                state
+                    .metrics
+                    .add_private_tx_relay_rejected(chain_id.clone(), relay_host);
+            }
+            RelayOutcome::Failed(reason) => {
+                warn!("Private relay {relay_host} failed for chain_id: {chain_id}: {reason}");
+                return Err(RpcError::PrivateTxRelayFailed(format!(
+                    "{relay_host}: {reason}"
+                )));
+            }
+        }
+    }
+
+    let mut response = rpc_call_providers(
+        state.clone(),
+        addr,
+        query_params,
+        headers,
+        body,
+        Some(request),
+    )
+    .await?;
+    response.headers_mut().insert(
+        SUBMISSION_PATH_HEADER,
+        HeaderValue::from_static(SubmissionPath::Public.as_str()),
+    );
+    state
+        .metrics
+        .add_tx_submission(chain_id, SubmissionPath::Public.as_str());
+    Ok(response)
+}
+
+/// Send the JSON-RPC request to the relay, the transaction is accepted only
+/// by the successful JSON-RPC result and rejected only by the JSON-RPC error
+async fn relay_call(http_client: &reqwest::Client, relay: &Url, body: &Bytes) -> RelayOutcome {
+    let response = match http_client
+        .post(relay.clone())
+        .header(DEFAULT_CONTENT_TYPE.0, DEFAULT_CONTENT_TYPE.1)
+        .body(body.clone())
+        .timeout(RELAY_CALL_TIMEOUT)
+        .send()
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => return RelayOutcome::Failed(format!("relay call failed: {e}")),
+    };
+    let status = response.status();
+    let body = match http_client::read_body(response).await {
+        Ok(body) => body,
+        Err(e) => return RelayOutcome::Failed(format!("relay response read failed: {e}")),
+    };
+    let response = match serde_json::from_slice::<Value>(&body) {
+        Ok(response) => response,
+        Err(_) => return RelayOutcome::Failed(format!("relay responded with status {status}")),
+    };
+    if let Some(error) = response.get("error").filter(|error| error.is_object()) {
+        return RelayOutcome::Rejected(
+            error
+                .get("message")
+                .and_then(Value::as_str)
+                .unwrap_or("relay rejected the transaction")
+                .to_string(),
+        );
+    }
+    if status.is_success()
+        && response
+            .get("result")
+            .is_some_and(|result| !result.is_null())
+    {
+        return RelayOutcome::Accepted(body);
+    }
+    RelayOutcome::Failed(format!(
+        "relay responded with status {status} without the result"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        serde_json::json,
+        wiremock::{
+            matchers::{body_partial_json, method},
+            Mock, MockServer, ResponseTemplate,
+        },
+    };
+
+    const RAW_TX_REQUEST: &str =
+        r#"{"jsonrpc":"2.0","id":1,"method":"eth_sendRawTransaction","params":["0x02f8"]}"#;
+
+    /// Local mock relay responding with the response template
+    async fn mock_relay(response: ResponseTemplate) -> MockServer {
+        let relay = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(
+                json!({ "method": SEND_RAW_TRANSACTION_METHOD }),
+            ))
+            .respond_with(response)
+            .mount(&relay)
+            .await;
+        relay
+    }
+
+    async fn submit(relay: &MockServer) -> RelayOutcome {
+        relay_call(
+            &reqwest::Client::new(),
+            &Url::parse(&relay.uri()).unwrap(),
+            &Bytes::from_static(RAW_TX_REQUEST.as_bytes()),
+        )
+        .await
+    }
+
+    #[test]
+    fn test_batch_private_tx_rejected() {
+        let requests = serde_json::from_value::<Vec<JsonRpcRequest>>(json!([
+            { "jsonrpc": "2.0", "id": 1, "method": "eth_chainId", "params": [] },
+            { "jsonrpc": "2.0", "id": 2, "method": SEND_RAW_TRANSACTION_METHOD, "params": ["0x02f8"] },
+        ]))
+        .unwrap();
+        assert!(has_raw_transaction(&requests));
+        assert!(!has_raw_transaction(&requests[..1]));
+
+        let responses =
+            serde_json::to_value(unavailable_responses(&requests, "unavailable")).unwrap();
+        assert_eq!(
+            responses,
+            json!([
+                { "jsonrpc": "2.0", "id": 1, "error": { "code": TRANSACTION_REJECTED_CODE, "message": "unavailable", "data": null } },
+                { "jsonrpc": "2.0", "id": 2, "error": { "code": TRANSACTION_REJECTED_CODE, "message": "unavailable", "data": null } },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_relay() {
+        let (chain_id, url) = parse_relay("eip155:1=https://relay.example.com/fast").unwrap();
+        assert_eq!(chain_id, "eip155:1");
+        assert_eq!(url.host_str(), Some("relay.example.com"));
+        assert!(parse_relay("mainnet=https://relay.example.com").is_none());
+        assert!(parse_relay("eip155:1=relay").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_relay_accepts_transaction() {
+        let relay = mock_relay(ResponseTemplate::new(200).set_body_json(json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "result": "0x8e1c",
+        })))
+        .await;
+        assert!(matches!(submit(&relay).await, RelayOutcome::Accepted(_)));
+    }
+
+    #[tokio::test]
+    async fn test_relay_rejects_transaction() {
+        let relay = mock_relay(ResponseTemplate::new(200).set_body_json(json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "error": { "code": -32000, "message": "bundle simulation failed" },
+        })))
+        .await;
+        match submit(&relay).await {
+            RelayOutcome::Rejected(reason) => assert_eq!(reason, "bundle simulation failed"),
+            outcome => panic!("unexpected relay outcome {outcome:?}"),
+        }
+
+        let relay = mock_relay(ResponseTemplate::new(400).set_body_json(json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "error": { "code": -32602, "message": "invalid transaction" },
+        })))
+        .await;
+        assert!(matches!(submit(&relay).await, RelayOutcome::Rejected(_)));
+    }
+
+    #[tokio::test]
+    async fn test_relay_failure_is_not_rejection() {
+        let relay = mock_relay(ResponseTemplate::new(503)).await;
+        assert!(matches!(submit(&relay).await, RelayOutcome::Failed(_)));
+
+        let relay = mock_relay(ResponseTemplate::new(200).set_body_string("accepted")).await;
+        assert!(matches!(submit(&relay).await, RelayOutcome::Failed(_)));
+    }
+}
@@ -0,0 +1,390 @@
+use {
+    super::{
+        ProviderCallContext, ProviderCallOutcome, DEFAULT_CONTENT_TYPE, PROVIDER_PROXY_MAX_CALLS,
+        PROVIDER_RESPONSE_MAX_BYTES,
+    },
+    crate::{
+        error::RpcError,
+        json_rpc::{JsonRpcRequest, JsonRpcResult},
+        providers::{ProvidersConfig, RpcProvider, TON_SEND_BOC_METHOD},
+    },
+    alloy::primitives::keccak256,
+    axum::{
+        body::{to_bytes, Bytes},
+        response::{IntoResponse, Response},
+    },
+    futures_util::future::join_all,
+    hyper::http::StatusCode,
+    serde::Deserialize,
+    serde_json::Value,
+    std::{sync::Arc, time::Duration},
+    tracing::log::{debug, error},
+};
+
+/// JSON-RPC methods broadcasting the signed transaction to the network
+const WRITE_METHODS: &[&str] = &[
+    "eth_sendRawTransaction",
+    // Solana
+    "sendTransaction",
+    TON_SEND_BOC_METHOD,
+    "tron_broadcastTransaction",
+];
+
+/// Error messages meaning the transaction was already received by the node,
+/// which is expected when the same transaction is broadcast more than once
+const DUPLICATE_TRANSACTION_ERROR_PATTERNS: &[&str] = &[
+    "already known",
+    "known transaction",
+    "already imported",
+    "AlreadyProcessed",
+    "DUP_TRANSACTION_ERROR",
+];
+
+/// Error message which is expected from the other providers when the
+/// transaction was broadcast to all of them and accepted by one
+const NONCE_TOO_LOW_ERROR_PATTERN: &str = "nonce too low";
+
+const DEFAULT_READ_DEADLINE: Duration = Duration::from_secs(20);
+
+pub fn is_write_method(method: &str) -> bool {
+    WRITE_METHODS.contains(&method)
+}
+
+/// Transactions broadcasting mode for the write methods
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BroadcastMode {
+    /// Transaction is sent to a single provider and to the next one only if
+    /// the previous provider failed to respond
+    #[default]
+    Once,
+    /// Transaction is sent to all the selected providers concurrently
+    All,
+}
+
+/// Providers retrying policy of the JSON-RPC method
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MethodPolicy {
+    /// Read methods are retried within the retry budget and the deadline
+    Read,
+    /// Write methods are broadcast according to the broadcasting mode
+    Broadcast(BroadcastMode),
+}
+
+/// Providers retrying policy for the RPC proxy calls
+#[derive(Debug)]
+pub struct RetryPolicy {
+    broadcast_mode: BroadcastMode,
+    read_retry_budget: usize,
+    read_deadline: Duration,
+}
+
+impl RetryPolicy {
+    pub fn new(config: &ProvidersConfig) -> Self {
+        Self {
+            broadcast_mode: config.write_broadcast_mode.unwrap_or_default(),
+            read_retry_budget: config
+                .read_retry_budget
+                .unwrap_or(PROVIDER_PROXY_MAX_CALLS)
+                .clamp(1, PROVIDER_PROXY_MAX_CALLS),
+            read_deadline: config
+                .read_deadline_ms
+                .map(Duration::from_millis)
+                .unwrap_or(DEFAULT_READ_DEADLINE),
+        }
+    }
+
+    /// Policy of the single request method or the batch request body.
+    /// Batch containing any write method is never broadcast to all providers.
+    pub fn method_policy(
+        &self,
+        rpc_request: Option<&JsonRpcRequest>,
+        body: &Bytes,
+    ) -> MethodPolicy {
+        match rpc_request {
+            Some(request) if is_write_method(&request.method) => {
+                MethodPolicy::Broadcast(self.broadcast_mode)
+            }
+            Some(_) => MethodPolicy::Read,
+            None if batch_has_write_methods(body) => MethodPolicy::Broadcast(BroadcastMode::Once),
+            None => MethodPolicy::Read,
+        }
+    }
+
+    /// Maximum amount of the providers tried for the read methods
+    pub fn read_retry_budget(&self) -> usize {
+        self.read_retry_budget
+    }
+
+    /// Overall deadline of the read method call including retries
+    pub fn read_deadline(&self) -> Duration {
+        self.read_deadline
+    }
+}
+
+fn batch_has_write_methods(body: &Bytes) -> bool {
+    serde_json::from_slice::<Vec<JsonRpcRequest>>(body)
+        .is_ok_and(|requests| requests.iter().any(|r| is_write_method(&r.method)))
+}
+
+/// Classification of the provider response to the transaction broadcast
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BroadcastResult {
+    Accepted,
+    /// Transaction was already received by the node
+    AlreadyKnown,
+    /// Transaction nonce was already used, which is expected from the other
+    /// providers if the transaction was accepted by one of them
+    NonceTooLow,
+    Rejected,
+}
+
+impl BroadcastResult {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Accepted => "accepted",
+            Self::AlreadyKnown => "already_known",
+            Self::NonceTooLow => "nonce_too_low",
+            Self::Rejected => "rejected",
+        }
+    }
+}
+
+fn classify_broadcast_response(body: &[u8]) -> BroadcastResult {
+    let Ok(response) = serde_json::from_slice::<jsonrpc::Response>(body) else {
+        return BroadcastResult::Rejected;
+    };
+    match (&response.result, &response.error) {
+        (Some(_), None) => BroadcastResult::Accepted,
+        (_, Some(error)) => {
+            if DUPLICATE_TRANSACTION_ERROR_PATTERNS
+                .iter()
+                .any(|pattern| error.message.contains(pattern))
+            {
+                BroadcastResult::AlreadyKnown
+            } else if error.message.contains(NONCE_TOO_LOW_ERROR_PATTERN) {
+                BroadcastResult::NonceTooLow
+            } else {
+                BroadcastResult::Rejected
+            }
+        }
+        (None, None) => BroadcastResult::Rejected,
+    }
+}
+
+/// Hash of the `eth_sendRawTransaction` signed transaction, which is the
+/// result of the successful broadcast
+fn raw_transaction_hash(request: &JsonRpcRequest) -> Option<String> {
+    if request.method.as_ref() != "eth_sendRawTransaction" {
+        return None;
+    }
+    let raw = request.params.get(0)?.as_str()?;
+    let raw = hex::decode(raw.strip_prefix("0x").unwrap_or(raw)).ok()?;
+    Some(format!("0x{}", hex::encode(keccak256(raw))))
+}
+
+/// Successful broadcast response with the transaction hash in place of the
+/// duplicate transaction error, since the transaction was accepted by the
+/// node on the previous broadcast
+fn deduplicated_response(request: &JsonRpcRequest) -> Option<Response> {
+    let hash = raw_transaction_hash(request)?;
+    let body =
+        serde_json::to_string(&JsonRpcResult::new(request.id.clone(), Value::String(hash))).ok()?;
+    Some((StatusCode::OK, [DEFAULT_CONTENT_TYPE], body).into_response())
+}
+
+async fn read_response(response: Response) -> Option<(StatusCode, Bytes)> {
+    let status = response.status();
+    to_bytes(response.into_body(), PROVIDER_RESPONSE_MAX_BYTES)
+        .await
+        .map_err(|e| error!("Failed to read the broadcast response body: {e}"))
+        .ok()
+        .map(|body| (status, body))
+}
+
+/// Broadcast the transaction according to the broadcasting mode
+pub(super) async fn broadcast_call(
+    call_context: &ProviderCallContext<'_>,
+    providers: &[Arc<dyn RpcProvider>],
+    mode: BroadcastMode,
+) -> Result<Response, RpcError> {
+    let chain_id = call_context.query_params.chain_id.clone();
+    let response = match mode {
+        BroadcastMode::Once => broadcast_once(call_context, providers).await,
+        BroadcastMode::All => broadcast_all(call_context, providers).await,
+    };
+    response.ok_or_else(|| {
+        call_context
+            .state
+            .metrics
+            .add_no_providers_for_chain(chain_id.clone());
+        debug!("All providers failed to broadcast for chain_id: {chain_id}");
+        RpcError::ChainTemporarilyUnavailable(chain_id)
+    })
+}
+
+/// Send the transaction to the providers one by one until the first provider
+/// responds. The duplicate transaction error after the failed attempt means
+/// the previous provider has relayed the transaction before failing.
+async fn broadcast_once(
+    call_context: &ProviderCallContext<'_>,
+    providers: &[Arc<dyn RpcProvider>],
+) -> Option<Response> {
+    for (i, provider) in providers.iter().enumerate() {
+        let ProviderCallOutcome::Response(response) = call_context.call_provider(i, provider).await
+        else {
+            continue;
+        };
+        let (status, body) = read_response(response).await?;
+        let result = classify_broadcast_response(&body);
+        record_broadcast_result(call_context, result);
+
+        if result == BroadcastResult::AlreadyKnown && i > 0 {
+            if let Some(response) = call_context.rpc_request.and_then(deduplicated_response) {
+                return Some(response);
+            }
+        }
+        return Some((status, [DEFAULT_CONTENT_TYPE], body).into_response());
+    }
+    None
+}
+
+/// Send the transaction to all the providers concurrently and respond with
+/// the accepted response. Already known and nonce too low errors from the
+/// providers that received the transaction from the network first are ignored.
+async fn broadcast_all(
+    call_context: &ProviderCallContext<'_>,
+    providers: &[Arc<dyn RpcProvider>],
+) -> Option<Response> {
+    let outcomes = join_all(
+        providers
+            .iter()
+            .enumerate()
+            .map(|(i, provider)| call_context.call_provider(i, provider)),
+    )
+    .await;
+
+    let mut responses = Vec::with_capacity(outcomes.len());
+    for response in outcomes
+        .into_iter()
+        .filter_map(ProviderCallOutcome::into_option)
+    {
+        if let Some((status, body)) = read_response(response).await {
+            let result = classify_broadcast_response(&body);
+            record_broadcast_result(call_context, result);
+            responses.push((result, status, body));
+        }
+    }
+
+    let accepted = responses
+        .iter()
+        .position(|(result, ..)| *result == BroadcastResult::Accepted);
+    let already_known = responses
+        .iter()
+        .position(|(result, ..)| *result == BroadcastResult::AlreadyKnown);
+    if accepted.is_none() && already_known.is_some() {
+        if let Some(response) = call_context.rpc_request.and_then(deduplicated_response) {
+            return Some(response);
+        }
+    }
+    let index = accepted.or(already_known).unwrap_or_default();
+    if index >= responses.len() {
+        return None;
+    }
+    let (_, status, body) = responses.swap_remove(index);
+    Some((status, [DEFAULT_CONTENT_TYPE], body).into_response())
+}
+
+fn record_broadcast_result(call_context: &ProviderCallContext<'_>, result: BroadcastResult) {
+    let method = call_context
+        .rpc_request
+        .map(|request| request.method.to_string())
+        .unwrap_or_else(|| "batch".to_string());
+    call_context.state.metrics.add_rpc_broadcast_result(
+        call_context.query_params.chain_id.clone(),
+        method,
+        result.as_str(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, serde_json::json};
+
+    #[test]
+    fn test_method_policy() {
+        let policy = RetryPolicy {
+            broadcast_mode: BroadcastMode::All,
+            read_retry_budget: 2,
+            read_deadline: DEFAULT_READ_DEADLINE,
+        };
+        let request = |method: &str| JsonRpcRequest::new(json!(1), method.into());
+        let empty = Bytes::new();
+
+        assert_eq!(
+            policy.method_policy(Some(&request("eth_sendRawTransaction")), &empty),
+            MethodPolicy::Broadcast(BroadcastMode::All)
+        );
+        assert_eq!(
+            policy.method_policy(Some(&request("ton_sendBoc")), &empty),
+            MethodPolicy::Broadcast(BroadcastMode::All)
+        );
+        assert_eq!(
+            policy.method_policy(Some(&request("eth_call")), &empty),
+            MethodPolicy::Read
+        );
+
+        let batch = Bytes::from(
+            serde_json::to_vec(&[request("eth_call"), request("eth_sendRawTransaction")]).unwrap(),
+        );
+        assert_eq!(
+            policy.method_policy(None, &batch),
+            MethodPolicy::Broadcast(BroadcastMode::Once)
+        );
+        let batch = Bytes::from(serde_json::to_vec(&[request("eth_call")]).unwrap());
+        assert_eq!(policy.method_policy(None, &batch), MethodPolicy::Read);
+    }
+
+    #[test]
+    fn test_classify_broadcast_response() {
+        let accepted = br#"{"jsonrpc":"2.0","id":1,"result":"0x1"}"#;
+        let already_known =
+            br#"{"jsonrpc":"2.0","id":1,"error":{"code":-32000,"message":"already known"}}"#;
+        let nonce_too_low =
+            br#"{"jsonrpc":"2.0","id":1,"error":{"code":-32000,"message":"nonce too low"}}"#;
+
+        assert_eq!(
+            classify_broadcast_response(accepted),
+            BroadcastResult::Accepted
+        );
+        assert_eq!(
+            classify_broadcast_response(already_known),
+            BroadcastResult::AlreadyKnown
+        );
+        assert_eq!(
+            classify_broadcast_response(nonce_too_low),
+            BroadcastResult::NonceTooLow
+        );
+        assert_eq!(
+            classify_broadcast_response(b"invalid"),
+            BroadcastResult::Rejected
+        );
+    }
+
+    #[test]
+    fn test_raw_transaction_hash() {
+        let request = JsonRpcRequest::new_with_params(
+            json!(1),
+            "eth_sendRawTransaction".into(),
+            json!(["0x01"]),
+        );
+        assert_eq!(
+            raw_transaction_hash(&request),
+            Some(format!("0x{}", hex::encode(keccak256([1u8]))))
+        );
+
+        let request =
+            JsonRpcRequest::new_with_params(json!(1), "sendTransaction".into(), json!(["0x01"]));
+        assert_eq!(raw_transaction_hash(&request), None);
+    }
+}
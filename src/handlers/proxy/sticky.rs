@@ -0,0 +1,242 @@
+use {
+    super::{ProviderCallContext, ProviderCallOutcome},
+    crate::{
+        error::RpcError,
+        providers::{head_tracker, ProviderKind, RpcProvider},
+    },
+    axum::response::Response,
+    deadpool_redis::{redis::AsyncCommands, Pool},
+    moka::future::Cache,
+    serde::{Deserialize, Serialize},
+    std::{
+        sync::Arc,
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    },
+    tracing::log::{debug, warn},
+};
+
+const DEFAULT_STICKY_SESSION_WINDOW: Duration = Duration::from_secs(60);
+
+/// Provider pinned to the session for the chain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct StickySession {
+    provider: String,
+    /// The highest provider block height the session has observed
+    block: Option<u64>,
+    /// Expiration unix timestamp in milliseconds
+    expires_at: u64,
+}
+
+impl StickySession {
+    fn provider_kind(&self) -> Option<ProviderKind> {
+        ProviderKind::from_str(&self.provider)
+    }
+}
+
+/// Call the provider pinned to the session for the chain. The pinned provider
+/// is reused only if it's among the permitted and capable `providers`. Other
+/// providers are used only if they are at least as far along in the block
+/// height as the session has observed, and the first succeeded provider is
+/// pinned to the session for the sticky session window. Up to the
+/// `retry_budget` providers including the pinned one are tried.
+pub(super) async fn sticky_session_call(
+    call_context: &ProviderCallContext<'_>,
+    session_id: &str,
+    providers: &[Arc<dyn RpcProvider>],
+    retry_budget: usize,
+) -> Result<Response, RpcError> {
+    let state = call_context.state;
+    let chain_id = call_context.query_params.chain_id.as_str();
+    let key = construct_session_key(chain_id, session_id);
+    let redis_pool = state.providers.cache_redis_pool.as_ref();
+
+    let session = get_session(&key, &state.moka_cache, redis_pool).await;
+    let pinned_provider = session
+        .as_ref()
+        .and_then(StickySession::provider_kind)
+        .and_then(|provider_kind| {
+            providers
+                .iter()
+                .find(|provider| provider.provider_kind() == provider_kind)
+                .cloned()
+        });
+    let required_block = session.as_ref().and_then(|session| session.block);
+
+    let pinned_provider_kind = pinned_provider.as_ref().map(|p| p.provider_kind());
+    let candidates = pinned_provider
+        .clone()
+        .into_iter()
+        .chain(
+            providers
+                .iter()
+                .filter(|provider| Some(provider.provider_kind()) != pinned_provider_kind)
+                .cloned(),
+        )
+        .take(retry_budget);
+
+    for (i, provider) in candidates.enumerate() {
+        let provider_kind = provider.provider_kind();
+        let is_pinned = Some(&provider_kind) == pinned_provider_kind.as_ref();
+        if !is_pinned {
+            if let Some(required_block) = required_block {
+                let block = provider_block_number(call_context, &provider).await;
+                if block.is_none_or(|block| block < required_block) {
+                    debug!(
+                        "Provider '{provider_kind}' at block {block:?} is behind the session \
+                         block {required_block}, skipping"
+                    );
+                    continue;
+                }
+            }
+        }
+
+        if let ProviderCallOutcome::Response(response) =
+            call_context.call_provider(i, &provider).await
+        {
+            let block = state
+                .providers
+                .get_provider_head(&provider_kind, chain_id)
+                .max(required_block);
+            let window = state
+                .config
+                .providers
+                .sticky_session_window_secs
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_STICKY_SESSION_WINDOW);
+            let session = StickySession {
+                provider: provider_kind.to_string(),
+                block,
+                expires_at: now_unix_ms() + window.as_millis() as u64,
+            };
+            set_session(&key, &session, window, &state.moka_cache, redis_pool).await;
+            return Ok(response);
+        }
+    }
+
+    state
+        .metrics
+        .add_no_providers_for_chain(chain_id.to_string());
+    debug!("All providers failed or behind the session block for chain_id: {chain_id}");
+    Err(RpcError::ChainTemporarilyUnavailable(chain_id.to_string()))
+}
+
+/// Latest known provider block height or the probed one if unknown
+async fn provider_block_number(
+    call_context: &ProviderCallContext<'_>,
+    provider: &Arc<dyn RpcProvider>,
+) -> Option<u64> {
+    let state = call_context.state;
+    let chain_id = call_context.query_params.chain_id.as_str();
+    let provider_kind = provider.provider_kind();
+    if let Some(block) = state.providers.get_provider_head(&provider_kind, chain_id) {
+        return Some(block);
+    }
+
+    let block = head_tracker::fetch_head(provider.as_ref(), chain_id).await?;
+    state
+        .providers
+        .record_provider_head(&provider_kind, chain_id, block);
+    Some(block)
+}
+
+fn construct_session_key(chain_id: &str, session_id: &str) -> String {
+    format!("rpc_session:{chain_id}:{session_id}")
+}
+
+fn now_unix_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+async fn get_session(
+    key: &str,
+    moka_cache: &Cache<String, String>,
+    redis_pool: Option<&Arc<Pool>>,
+) -> Option<StickySession> {
+    let session = match moka_cache.get(key).await {
+        Some(session) => session,
+        None => {
+            let mut connection = redis_pool?
+                .get()
+                .await
+                .map_err(|e| warn!("Failed to get the Redis connection for the session: {e}"))
+                .ok()?;
+            connection
+                .get::<_, Option<String>>(key)
+                .await
+                .map_err(|e| warn!("Failed to get the sticky session from Redis: {e}"))
+                .ok()
+                .flatten()?
+        }
+    };
+    serde_json::from_str::<StickySession>(&session)
+        .ok()
+        .filter(|session| session.expires_at > now_unix_ms())
+}
+
+async fn set_session(
+    key: &str,
+    session: &StickySession,
+    window: Duration,
+    moka_cache: &Cache<String, String>,
+    redis_pool: Option<&Arc<Pool>>,
+) {
+    let Ok(value) = serde_json::to_string(session) else {
+        return;
+    };
+    if let Some(redis_pool) = redis_pool {
+        match redis_pool.get().await {
+            Ok(mut connection) => {
+                if let Err(e) = connection
+                    .pset_ex::<_, _, ()>(key, &value, window.as_millis() as u64)
+                    .await
+                {
+                    warn!("Failed to set the sticky session to Redis: {e}");
+                }
+            }
+            Err(e) => warn!("Failed to get the Redis connection for the session: {e}"),
+        }
+    }
+    moka_cache.insert(key.to_string(), value).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sticky_session_serialization() {
+        let session = StickySession {
+            provider: ProviderKind::Quicknode.to_string(),
+            block: Some(100),
+            expires_at: 1,
+        };
+        let serialized = serde_json::to_string(&session).unwrap();
+        let deserialized = serde_json::from_str::<StickySession>(&serialized).unwrap();
+        assert_eq!(deserialized, session);
+        assert_eq!(deserialized.provider_kind(), Some(ProviderKind::Quicknode));
+    }
+
+    #[tokio::test]
+    async fn test_expired_session_is_ignored() {
+        let moka_cache = Cache::builder().build();
+        let key = construct_session_key("eip155:1", "session");
+        let session = StickySession {
+            provider: ProviderKind::Quicknode.to_string(),
+            block: None,
+            expires_at: now_unix_ms() + 60_000,
+        };
+        set_session(&key, &session, Duration::from_secs(60), &moka_cache, None).await;
+        assert_eq!(get_session(&key, &moka_cache, None).await, Some(session));
+
+        let expired = StickySession {
+            provider: ProviderKind::Quicknode.to_string(),
+            block: None,
+            expires_at: now_unix_ms() - 1,
+        };
+        set_session(&key, &expired, Duration::from_secs(60), &moka_cache, None).await;
+        assert_eq!(get_session(&key, &moka_cache, None).await, None);
+    }
+}
@@ -40,6 +40,7 @@ impl SelfProviderPool {
                 project_id: self.project_id.to_string(),
                 provider_id: None,
                 session_id: self.session_id.clone(),
+                private_tx: None,
                 source: Some(source),
                 sdk_info: self.sdk_info.clone(),
             },
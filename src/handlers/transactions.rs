@@ -0,0 +1,46 @@
+use {
+    crate::{error::RpcError, state::AppState},
+    axum::{
+        extract::{Path, Query, State},
+        response::{IntoResponse, Response},
+        Json,
+    },
+    serde::Deserialize,
+    std::sync::Arc,
+    wc::metrics::{future_metrics, FutureExt},
+};
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionQueryParams {
+    pub project_id: String,
+}
+
+pub async fn handler(
+    state: State<Arc<AppState>>,
+    query: Query<TransactionQueryParams>,
+    hash: Path<String>,
+) -> Result<Response, RpcError> {
+    handler_internal(state, query, hash)
+        .with_metrics(future_metrics!("handler_task", "name" => "transaction_status"))
+        .await
+}
+
+/// Lifecycle status of the transaction submitted through the proxy by the
+/// project
+#[tracing::instrument(skip_all, level = "debug")]
+async fn handler_internal(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<TransactionQueryParams>,
+    Path(hash): Path<String>,
+) -> Result<Response, RpcError> {
+    state.validate_project_access(&query.project_id).await?;
+
+    // Transactions submitted by other projects are not disclosed
+    let transaction = state
+        .tx_tracker
+        .get(&hash)
+        .filter(|transaction| transaction.project_id == query.project_id)
+        .ok_or(RpcError::TransactionNotFound(hash))?;
+    Ok(Json(transaction).into_response())
+}
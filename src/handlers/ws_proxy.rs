@@ -1,22 +1,23 @@
 use {
     super::RpcQueryParams,
-    crate::{error::RpcError, state::AppState},
+    crate::{error::RpcError, state::AppState, ws::metering::SessionMeter},
     axum::{
-        extract::{ws::WebSocketUpgrade, Query, State},
+        extract::{ws::WebSocketUpgrade, ConnectInfo, Query, State},
         http::HeaderMap,
         response::Response,
     },
-    std::sync::Arc,
+    std::{net::SocketAddr, sync::Arc},
     wc::metrics::{future_metrics, FutureExt},
 };
 
 pub async fn handler(
     state: State<Arc<AppState>>,
+    addr: ConnectInfo<SocketAddr>,
     query_params: Query<RpcQueryParams>,
     headers: HeaderMap,
     ws: WebSocketUpgrade,
 ) -> Result<Response, RpcError> {
-    handler_internal(state, query_params, headers, ws)
+    handler_internal(state, addr, query_params, headers, ws)
         .with_metrics(future_metrics!("handler_task", "name" => "ws_proxy"))
         .await
 }
@@ -24,6 +25,7 @@ pub async fn handler(
 #[tracing::instrument(skip_all, level = "debug")]
 async fn handler_internal(
     State(state): State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
     Query(query_params): Query<RpcQueryParams>,
     headers: HeaderMap,
     ws: WebSocketUpgrade,
@@ -36,15 +38,46 @@ async fn handler_internal(
         .validate_project_access_and_quota(&query_params.project_id)
         .await?;
 
+    // Tier rules of the method access are applied to the session frames
+    let tier = state
+        .method_access
+        .tier(&state.registry, &query_params.project_id)
+        .await?;
+
     let chain_id = query_params.chain_id.clone();
-    let provider = state
-        .providers
-        .get_ws_provider_for_chain_id(&chain_id)
-        .ok_or(RpcError::UnsupportedChain(chain_id.clone()))?;
+    let meter = SessionMeter::new(&state, query_params.clone(), headers.clone(), addr, tier);
+    let Some(provider) = state.providers.get_ws_provider_for_chain_id(&chain_id) else {
+        // Chains without WebSocket providers are served through the HTTP providers
+        let is_emulation_enabled = state.config.providers.ws_emulation_enabled.unwrap_or(false);
+        if !is_emulation_enabled
+            || state
+                .providers
+                .get_rpc_provider_for_chain_id(&chain_id, 1)
+                .is_err()
+        {
+            return Err(RpcError::UnsupportedChain(chain_id));
+        }
+        state.metrics.add_websocket_emulated_connection(chain_id);
+        return Ok(ws.on_upgrade(move |socket| {
+            crate::ws::emulation::run(state, addr, query_params, headers, meter, socket)
+                .with_metrics(future_metrics!("ws_proxy_task", "name" => "emulation"))
+        }));
+    };
+
+    // Sessions connect the upstream lazily when the subscriptions are served
+    // by the shared subscription hub
+    let upstream = if state.subscription_hub.is_enabled() {
+        None
+    } else {
+        Some(provider.connect(&chain_id).await?)
+    };
 
     state.metrics.add_websocket_connection(chain_id);
 
-    provider.proxy(ws, query_params).await
+    Ok(ws.on_upgrade(move |socket| {
+        crate::ws::session::run(state, query_params, meter, socket, provider, upstream)
+            .with_metrics(future_metrics!("ws_proxy_task", "name" => "session"))
+    }))
 }
 
 /// Check if the request is a WebSocket upgrade request
@@ -14,6 +14,21 @@ mod tests;
 
 pub const JSON_RPC_VERSION_STR: &str = "2.0";
 
+/// JSON-RPC parse error code
+pub const PARSE_ERROR_CODE: i32 = -32700;
+/// JSON-RPC method not found error code
+pub const METHOD_NOT_FOUND_CODE: i32 = -32601;
+/// JSON-RPC invalid params error code
+pub const INVALID_PARAMS_CODE: i32 = -32602;
+/// JSON-RPC internal error code
+pub const INTERNAL_ERROR_CODE: i32 = -32603;
+/// EIP-1474 transaction rejected error code
+pub const TRANSACTION_REJECTED_CODE: i32 = -32003;
+/// EIP-1474 method not supported error code
+pub const METHOD_NOT_SUPPORTED_CODE: i32 = -32004;
+/// EIP-1474 limit exceeded error code
+pub const LIMIT_EXCEEDED_CODE: i32 = -32005;
+
 pub static JSON_RPC_VERSION: once_cell::sync::Lazy<Arc<str>> =
     once_cell::sync::Lazy::new(|| Arc::from(JSON_RPC_VERSION_STR));
 
@@ -1,13 +1,13 @@
 use {
     crate::{
-        env::{Config, GenericConfig},
+        env::{Config, GenericConfig, ProviderConfig},
         handlers::{
             balance::BalanceResponseBody, identity::IdentityResponse, rate_limit_middleware,
             status_latency_metrics_middleware,
         },
         metrics::Metrics,
         project::Registry,
-        providers::ProvidersConfig,
+        providers::{ProviderKind, ProvidersConfig},
         storage::{irn, redis, KeyValueStorage},
     },
     anyhow::Context,
@@ -32,14 +32,14 @@ use {
     hyper::{header::HeaderName, http},
     metrics_exporter_prometheus::PrometheusBuilder,
     providers::{
-        AllnodesProvider, AllnodesWsProvider, ArbitrumProvider, AuroraProvider, BaseProvider,
-        BinanceProvider, BlastProvider, CallStaticProvider, DrpcProvider, DuneProvider,
-        GenericProvider, HiroProvider, MantleProvider, MonadProvider, MoonbeamProvider,
-        MorphProvider, NearProvider, PoktProvider, ProviderRepository, PublicnodeProvider,
-        QuicknodeProvider, QuicknodeWsProvider, RootstockProvider, SolScanProvider, SuiProvider,
-        SyndicaProvider, SyndicaWsProvider, ToncenterApiProvider, TrongridProvider,
-        UnichainProvider, WemixProvider, XrplProvider, ZKSyncProvider, ZerionProvider,
-        ZoraProvider, ZoraWsProvider,
+        http_client, AllnodesProvider, AllnodesWsProvider, ArbitrumProvider, AuroraProvider,
+        BaseProvider, BinanceProvider, BlastProvider, CallStaticProvider, DrpcProvider,
+        DuneProvider, GenericProvider, GenericWsProvider, HiroProvider, MantleProvider,
+        MonadProvider, MoonbeamProvider, MorphProvider, NearProvider, PoktProvider,
+        ProviderRepository, PublicnodeProvider, QuicknodeProvider, QuicknodeWsProvider,
+        RootstockProvider, RpcProviderSet, SolScanProvider, SuiProvider, SyndicaProvider,
+        SyndicaWsProvider, ToncenterApiProvider, TrongridProvider, UnichainProvider, WemixProvider,
+        XrplProvider, ZKSyncProvider, ZerionProvider, ZoraProvider, ZoraWsProvider,
     },
     sqlx::postgres::PgPoolOptions,
     std::{
@@ -65,6 +65,8 @@ use {
 
 const DB_STATS_POLLING_INTERVAL: Duration = Duration::from_secs(3600);
 const GRACEFUL_SHUTDOWN_DELAY: Duration = Duration::from_secs(5);
+const DEFAULT_HEAD_TRACKER_INTERVAL_SECS: u64 = 10;
+const DEFAULT_TX_TRACKER_INTERVAL_SECS: u64 = 5;
 
 mod analytics;
 pub mod chain_config;
@@ -260,6 +262,7 @@ pub async fn bootstrap(config: Config) -> RpcResult<()> {
         .route("/ws", get(handlers::ws_proxy::handler))
         .route("/v1/supported-chains", get(handlers::supported_chains::handler))
         .route("/v1/identity/{address}", get(handlers::identity::handler))
+        .route("/v1/transactions/{hash}", get(handlers::transactions::handler))
         .route(
             "/v1/account/{address}/identity",
             get(handlers::identity::handler),
@@ -426,12 +429,14 @@ pub async fn bootstrap(config: Config) -> RpcResult<()> {
 
     info!("Starting metric server on {}", private_addr);
 
-    let private_app = Router::new()
-        .route(
-            "/metrics",
-            get(move || async move { prometheus_handler.render() }),
-        )
-        .with_state(state_arc.clone());
+    let mut private_app = Router::new().route(
+        "/metrics",
+        get(move || async move { prometheus_handler.render() }),
+    );
+    if let Some(admin_router) = handlers::admin::router(state_arc.clone()) {
+        private_app = private_app.merge(admin_router);
+    }
+    let private_app = private_app.with_state(state_arc.clone());
 
     let public_server = create_server(app, addr);
     let private_server = create_server(private_app, private_addr);
@@ -455,6 +460,88 @@ pub async fn bootstrap(config: Config) -> RpcResult<()> {
         }
     };
 
+    let head_tracker = {
+        let state_arc = state_arc.clone();
+        let interval_secs = state_arc
+            .config
+            .providers
+            .head_tracker_interval_secs
+            .unwrap_or(DEFAULT_HEAD_TRACKER_INTERVAL_SECS);
+        async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(interval_secs.max(1)));
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        state_arc.clone().update_provider_heads().await;
+                    }
+                    _ = signal::ctrl_c() => {
+                        info!("Head tracker received shutdown signal");
+                        break;
+                    }
+                }
+            }
+            Ok(())
+        }
+    };
+
+    let tx_tracker = {
+        let state_arc = state_arc.clone();
+        let interval_secs = state_arc
+            .config
+            .providers
+            .tx_tracker_interval_secs
+            .unwrap_or(DEFAULT_TX_TRACKER_INTERVAL_SECS);
+        async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(interval_secs.max(1)));
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        state_arc.clone().update_tracked_transactions().await;
+                    }
+                    _ = signal::ctrl_c() => {
+                        info!("Transactions tracker received shutdown signal");
+                        break;
+                    }
+                }
+            }
+            Ok(())
+        }
+    };
+
+    let catalogue_watcher = {
+        let state_arc = state_arc.clone();
+        let interval_secs = state_arc
+            .config
+            .providers
+            .catalogue_reload_interval_secs
+            .unwrap_or_default();
+        async move {
+            let Some(path) = state_arc.config.providers.catalogue_path.clone() else {
+                return Ok(());
+            };
+            let mut modified = catalogue_modified(&path).await;
+            let mut interval = tokio::time::interval(Duration::from_secs(interval_secs.max(1)));
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        let current = catalogue_modified(&path).await;
+                        if current.is_some() && current != modified {
+                            info!("Provider catalogue {path} is modified, reloading providers");
+                            modified = current;
+                            // The current providers are kept on the failed reload
+                            let _ = state_arc.reload_providers().await;
+                        }
+                    }
+                    _ = signal::ctrl_c() => {
+                        info!("Provider catalogue watcher received shutdown signal");
+                        break;
+                    }
+                }
+            }
+            Ok(())
+        }
+    };
+
     let system_metrics_updater = {
         let state_arc = state_arc.clone();
         async move {
@@ -489,7 +576,7 @@ pub async fn bootstrap(config: Config) -> RpcResult<()> {
     };
     let state_for_reconciler = state_arc.clone();
 
-    let services = vec![
+    let mut services = vec![
         tokio::spawn(public_server),
         tokio::spawn(private_server),
         tokio::spawn(weights_updater),
@@ -523,6 +610,30 @@ pub async fn bootstrap(config: Config) -> RpcResult<()> {
         }),
     ];
 
+    if state_arc
+        .config
+        .providers
+        .head_tracker_enabled
+        .unwrap_or(false)
+    {
+        services.push(tokio::spawn(head_tracker));
+    }
+
+    if state_arc.tx_tracker.is_enabled() {
+        services.push(tokio::spawn(tx_tracker));
+    }
+
+    // Catalogue changes are watched only when the reload interval is set
+    if state_arc.config.providers.catalogue_path.is_some()
+        && state_arc
+            .config
+            .providers
+            .catalogue_reload_interval_secs
+            .is_some()
+    {
+        services.push(tokio::spawn(catalogue_watcher));
+    }
+
     // Wait for either services to complete or shutdown signal
     tokio::select! {
         result = futures_util::future::select_all(services) => {
@@ -541,6 +652,17 @@ pub async fn bootstrap(config: Config) -> RpcResult<()> {
     Ok(())
 }
 
+/// Last modification time of the provider catalogue file
+async fn catalogue_modified(path: &str) -> Option<std::time::SystemTime> {
+    match tokio::fs::metadata(path).await.and_then(|m| m.modified()) {
+        Ok(modified) => Some(modified),
+        Err(e) => {
+            warn!("Failed to read the provider catalogue {path} metadata: {e}");
+            None
+        }
+    }
+}
+
 async fn create_server(app: Router, addr: SocketAddr) -> Result<(), std::io::Error> {
     let listener = tokio::net::TcpListener::bind(addr)
         .await
@@ -603,9 +725,40 @@ fn init_providers(config: &ProvidersConfig) -> ProviderRepository {
         ));
     };
 
+    let chain_config = chain_config::load_active_config(config.catalogue_path.as_deref())
+        .unwrap_or_else(|e| panic!("Failed to load the chains config: {e}"));
+
+    let mut providers = ProviderRepository::new(config, init_rpc_providers(config, &chain_config));
+
+    providers.add_balance_provider::<ZerionProvider, ZerionConfig>(
+        ZerionConfig::new(config.zerion_api_key.clone()),
+        None,
+    );
+    providers.add_balance_provider::<DuneProvider, DuneConfig>(
+        DuneConfig::new(config.dune_sim_api_key.clone()),
+        None,
+    );
+    providers.add_balance_provider::<SolScanProvider, SolScanConfig>(
+        SolScanConfig::new(config.solscan_api_v2_token.clone()),
+        redis_pool.clone(),
+    );
+
+    providers
+}
+
+/// Build the RPC and WebSocket providers of the built-in chains and the
+/// chains from the provider catalogue
+pub(crate) fn init_rpc_providers(
+    config: &ProvidersConfig,
+    chain_config: &chain_config::Config,
+) -> RpcProviderSet {
+    // Providers share the upstream HTTP clients per provider kind, the clients
+    // are rebuilt on the reload only when their settings are changed
+    http_client::configure(config.upstream_client_config(), &chain_config.http_clients);
+
     // Keep in-sync with SUPPORTED_CHAINS.md
 
-    let mut providers = ProviderRepository::new(config);
+    let mut providers = RpcProviderSet::default();
     providers.add_rpc_provider::<AuroraProvider, AuroraConfig>(AuroraConfig::default());
     providers.add_rpc_provider::<ArbitrumProvider, ArbitrumConfig>(ArbitrumConfig::default());
     providers.add_rpc_provider::<PoktProvider, PoktConfig>(PoktConfig::new(
@@ -663,28 +816,42 @@ fn init_providers(config: &ProvidersConfig) -> ProviderRepository {
         config.quicknode_api_tokens.clone(),
     ));
 
-    for chain in &chain_config::ACTIVE_CONFIG.chains {
+    for chain in &chain_config.chains {
         for provider in &chain.providers {
-            providers.add_rpc_provider::<GenericProvider, GenericConfig>(GenericConfig {
+            let generic_config = GenericConfig {
                 caip2: chain.caip2.clone(),
                 name: chain.name.clone(),
+                kind: provider.kind.clone(),
                 provider: provider.clone(),
-            });
+            };
+            if provider.ws_url.is_some() {
+                providers
+                    .add_ws_provider::<GenericWsProvider, GenericConfig>(generic_config.clone());
+            }
+            if let Some(capabilities) = &provider.capabilities {
+                providers.set_rpc_capabilities(
+                    generic_config.provider_kind(),
+                    chain.caip2.clone(),
+                    capabilities.clone(),
+                );
+            }
+            providers.add_rpc_provider::<GenericProvider, GenericConfig>(generic_config);
         }
     }
 
-    providers.add_balance_provider::<ZerionProvider, ZerionConfig>(
-        ZerionConfig::new(config.zerion_api_key.clone()),
-        None,
-    );
-    providers.add_balance_provider::<DuneProvider, DuneConfig>(
-        DuneConfig::new(config.dune_sim_api_key.clone()),
-        None,
-    );
-    providers.add_balance_provider::<SolScanProvider, SolScanConfig>(
-        SolScanConfig::new(config.solscan_api_v2_token.clone()),
-        redis_pool.clone(),
-    );
+    // Capabilities of the built-in providers declared in the catalogue
+    for (provider, chains) in &chain_config.provider_capabilities {
+        let Some(provider_kind) = ProviderKind::from_str(provider) else {
+            continue;
+        };
+        for (chain_id, capabilities) in chains {
+            providers.set_rpc_capabilities(
+                provider_kind.clone(),
+                chain_id.clone(),
+                capabilities.clone(),
+            );
+        }
+    }
 
     providers
 }
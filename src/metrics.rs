@@ -2,7 +2,7 @@ use {
     crate::{
         database::helpers::get_account_names_stats,
         handlers::identity::IdentityLookupSource,
-        providers::{ProviderKind, RpcProvider},
+        providers::{circuit_breaker::CircuitState, ProviderKind, RpcProvider},
         storage::irn::OperationType,
         utils::crypto::CaipNamespaces,
     },
@@ -61,6 +61,89 @@ impl Metrics {
             .record(retires_count as f64);
     }
 
+    pub fn add_rpc_batch_upstream_chunks(&self, chunks_count: u64, chain_id: String) {
+        histogram!("rpc_batch_upstream_chunks", StringLabel<"chain_id", String> => &chain_id)
+            .record(chunks_count as f64);
+    }
+
+    pub fn add_logs_range_split(&self, chain_id: String, sub_ranges: u64) {
+        histogram!("logs_range_split_sub_ranges", StringLabel<"chain_id", String> => &chain_id)
+            .record(sub_ranges as f64);
+    }
+
+    pub fn add_rpc_method_denied(&self, project_id: String, chain_id: String, list: &str) {
+        counter!("rpc_method_denied_counter",
+            StringLabel<"project_id", String> => &project_id,
+            StringLabel<"chain_id", String> => &chain_id,
+            StringLabel<"list", String> => &list.to_string()
+        )
+        .increment(1);
+    }
+
+    pub fn add_tx_submission(&self, chain_id: String, path: &str) {
+        counter!("tx_submission_counter",
+            StringLabel<"chain_id", String> => &chain_id,
+            StringLabel<"path", String> => &path.to_string()
+        )
+        .increment(1);
+    }
+
+    pub fn add_private_tx_relay_rejected(&self, chain_id: String, relay: String) {
+        counter!("private_tx_relay_rejected_counter",
+            StringLabel<"chain_id", String> => &chain_id,
+            StringLabel<"relay", String> => &relay
+        )
+        .increment(1);
+    }
+
+    pub fn record_pending_transactions(&self, pending: u64) {
+        gauge!("tracked_pending_transactions").set(pending as f64);
+    }
+
+    pub fn record_tx_time_to_inclusion(
+        &self,
+        chain_id: String,
+        provider: String,
+        latency: Duration,
+    ) {
+        histogram!("tx_time_to_inclusion",
+            StringLabel<"chain_id", String> => &chain_id,
+            StringLabel<"provider", String> => &provider
+        )
+        .record(latency.as_secs_f64());
+    }
+
+    pub fn add_tx_lifecycle(&self, chain_id: String, status: &str) {
+        counter!("tx_lifecycle_counter",
+            StringLabel<"chain_id", String> => &chain_id,
+            StringLabel<"status", String> => &status.to_string()
+        )
+        .increment(1);
+    }
+
+    pub fn add_rpc_broadcast_result(&self, chain_id: String, method: String, result: &str) {
+        counter!("rpc_broadcast_result_counter",
+            StringLabel<"chain_id", String> => &chain_id,
+            StringLabel<"method", String> => &method,
+            StringLabel<"result", String> => &result.to_string()
+        )
+        .increment(1);
+    }
+
+    pub fn add_rpc_call_deadline_exceeded(&self, chain_id: String) {
+        counter!("rpc_call_deadline_exceeded_counter",
+            StringLabel<"chain_id", String> => &chain_id
+        )
+        .increment(1);
+    }
+
+    pub fn add_coalesced_rpc_call(&self, chain_id: String, method: String) {
+        counter!("coalesced_rpc_call_counter",
+            StringLabel<"chain_id", String> => &chain_id,
+            StringLabel<"method", String> => &method)
+        .increment(1);
+    }
+
     pub fn add_rpc_cached_call(&self, chain_id: String, method: String) {
         counter!("rpc_cached_call_counter", 
             StringLabel<"chain_id", String> => &chain_id, 
@@ -220,6 +303,66 @@ impl Metrics {
         .set(weight as f64);
     }
 
+    pub fn record_provider_circuit_state(
+        &self,
+        provider: &ProviderKind,
+        chain_id: String,
+        state: CircuitState,
+    ) {
+        gauge!("provider_circuit_state",
+            StringLabel<"provider", String> => &provider.to_string(),
+            StringLabel<"chain_id", String> => &chain_id
+        )
+        .set(state.as_gauge_value());
+    }
+
+    pub fn record_provider_head(&self, provider: &ProviderKind, chain_id: String, head: u64) {
+        gauge!("provider_head_block",
+            StringLabel<"provider", String> => &provider.to_string(),
+            StringLabel<"chain_id", String> => &chain_id
+        )
+        .set(head as f64);
+    }
+
+    pub fn record_provider_head_lag(&self, provider: &ProviderKind, chain_id: String, lag: u64) {
+        gauge!("provider_head_lag",
+            StringLabel<"provider", String> => &provider.to_string(),
+            StringLabel<"chain_id", String> => &chain_id
+        )
+        .set(lag as f64);
+    }
+
+    pub fn add_provider_circuit_opened(&self, provider: &ProviderKind, chain_id: String) {
+        counter!("provider_circuit_opened_counter",
+            StringLabel<"provider", String> => &provider.to_string(),
+            StringLabel<"chain_id", String> => &chain_id
+        )
+        .increment(1);
+    }
+
+    pub fn add_provider_rate_limit_backoff(&self, provider: &ProviderKind, chain_id: String) {
+        counter!("provider_rate_limit_backoff_counter",
+            StringLabel<"provider", String> => &provider.to_string(),
+            StringLabel<"chain_id", String> => &chain_id
+        )
+        .increment(1);
+    }
+
+    pub fn record_provider_monthly_compute_units(&self, provider: &ProviderKind, units: u64) {
+        gauge!("provider_monthly_compute_units",
+            StringLabel<"provider", String> => &provider.to_string()
+        )
+        .set(units as f64);
+    }
+
+    pub fn add_no_capable_providers(&self, chain_id: String, capabilities: String) {
+        counter!("no_capable_providers_counter",
+            StringLabel<"chain_id", String> => &chain_id,
+            StringLabel<"capabilities", String> => &capabilities
+        )
+        .increment(1);
+    }
+
     pub fn add_no_providers_for_chain(&self, chain_id: String) {
         counter!("no_providers_for_chain_counter",
             StringLabel<"chain_id", String> => &chain_id
@@ -253,6 +396,28 @@ impl Metrics {
         );
     }
 
+    pub fn add_hedged_rpc_call(&self, chain_id: String, provider_kind: &ProviderKind) {
+        counter!("hedged_rpc_call_counter",
+            StringLabel<"chain_id", String> => &chain_id,
+            StringLabel<"provider", String> => &provider_kind.to_string()
+        )
+        .increment(1);
+    }
+
+    pub fn add_hedged_rpc_call_winner(
+        &self,
+        chain_id: String,
+        provider_kind: &ProviderKind,
+        is_hedged: bool,
+    ) {
+        counter!("hedged_rpc_call_winner_counter",
+            StringLabel<"chain_id", String> => &chain_id,
+            StringLabel<"provider", String> => &provider_kind.to_string(),
+            StringLabel<"hedged", String> => &is_hedged.to_string()
+        )
+        .increment(1);
+    }
+
     pub fn add_identity_lookup(&self) {
         counter!("identity_lookup_counter").increment(1);
     }
@@ -323,6 +488,99 @@ impl Metrics {
             .increment(1);
     }
 
+    pub fn add_websocket_emulated_connection(&self, chain_id: String) {
+        counter!("websocket_emulated_connection_counter",
+            StringLabel<"chain_id", String> => &chain_id
+        )
+        .increment(1);
+    }
+
+    pub fn add_admin_action(&self, action: String) {
+        counter!("admin_action_counter", StringLabel<"action", String> => &action).increment(1);
+    }
+
+    pub fn add_admin_unauthorized(&self) {
+        counter!("admin_unauthorized_counter").increment(1);
+    }
+
+    pub fn add_providers_reload(&self, success: bool) {
+        let status = if success { "success" } else { "failure" };
+        counter!("providers_reload_counter",
+            StringLabel<"status", String> => &status.to_string()
+        )
+        .increment(1);
+    }
+
+    pub fn record_rpc_providers_count(&self, rpc_providers: usize, ws_providers: usize) {
+        gauge!("rpc_providers_count", StringLabel<"kind", String> => &"http".to_string())
+            .set(rpc_providers as f64);
+        gauge!("rpc_providers_count", StringLabel<"kind", String> => &"ws".to_string())
+            .set(ws_providers as f64);
+    }
+
+    /// New connection of the provider upstream client pool
+    pub fn add_upstream_connection(&self, provider: &str, success: bool, latency: Duration) {
+        let status = if success { "success" } else { "failure" };
+        counter!("upstream_connections_counter",
+            StringLabel<"provider", String> => &provider.to_string(),
+            StringLabel<"status", String> => &status.to_string()
+        )
+        .increment(1);
+        histogram!("upstream_connect_latency_tracker",
+            StringLabel<"provider", String> => &provider.to_string()
+        )
+        .record(latency.as_secs_f64());
+    }
+
+    pub fn add_websocket_upstream_failover(&self, chain_id: String, provider: &ProviderKind) {
+        counter!("websocket_upstream_failover_counter",
+            StringLabel<"chain_id", String> => &chain_id,
+            StringLabel<"provider", String> => &provider.to_string()
+        )
+        .increment(1);
+    }
+
+    pub fn add_websocket_subscriptions_replayed(&self, chain_id: String, count: u64) {
+        counter!("websocket_subscriptions_replayed_counter",
+            StringLabel<"chain_id", String> => &chain_id
+        )
+        .increment(count);
+    }
+
+    pub fn add_websocket_message(&self, chain_id: String) {
+        counter!("websocket_message_counter", StringLabel<"chain_id", String> => &chain_id)
+            .increment(1);
+    }
+
+    pub fn add_websocket_method_rejected(&self, chain_id: String, method: String) {
+        counter!("websocket_method_rejected_counter",
+            StringLabel<"chain_id", String> => &chain_id,
+            StringLabel<"method", String> => &method
+        )
+        .increment(1);
+    }
+
+    pub fn add_websocket_quota_exceeded(&self, chain_id: String) {
+        counter!("websocket_quota_exceeded_counter",
+            StringLabel<"chain_id", String> => &chain_id
+        )
+        .increment(1);
+    }
+
+    pub fn record_websocket_hub_topics(&self, chain_id: String, count: usize) {
+        gauge!("websocket_hub_topics",
+            StringLabel<"chain_id", String> => &chain_id
+        )
+        .set(count as f64);
+    }
+
+    pub fn add_websocket_hub_subscription(&self, chain_id: String) {
+        counter!("websocket_hub_subscription_counter",
+            StringLabel<"chain_id", String> => &chain_id
+        )
+        .increment(1);
+    }
+
     pub fn add_history_lookup(&self, provider: &ProviderKind) {
         counter!("history_lookup_counter", StringLabel<"provider", String> => &provider.to_string())
             .increment(1);
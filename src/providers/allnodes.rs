@@ -1,22 +1,20 @@
 use {
     super::{
-        Provider, ProviderKind, RateLimited, RpcProvider, RpcProviderFactory, RpcQueryParams,
-        RpcWsProvider,
+        http_client, rate_limits, Provider, ProviderKind, RateLimited, RpcProvider,
+        RpcProviderFactory, RpcWsProvider,
     },
     crate::{
         env::AllnodesConfig,
         error::{RpcError, RpcResult},
-        ws,
     },
     async_trait::async_trait,
+    async_tungstenite::{tokio::ConnectStream, WebSocketStream},
     axum::{
-        extract::ws::WebSocketUpgrade,
         http::HeaderValue,
         response::{IntoResponse, Response},
     },
     hyper::http,
     std::collections::HashMap,
-    wc::metrics::{future_metrics, FutureExt},
 };
 
 #[derive(Debug)]
@@ -49,13 +47,7 @@ impl Provider for AllnodesWsProvider {
 #[async_trait]
 impl RpcWsProvider for AllnodesWsProvider {
     #[tracing::instrument(skip_all, fields(provider = %self.provider_kind()), level = "debug")]
-    async fn proxy(
-        &self,
-        ws: WebSocketUpgrade,
-        query_params: RpcQueryParams,
-    ) -> RpcResult<Response> {
-        let chain_id = &query_params.chain_id;
-        let project_id = query_params.project_id;
+    async fn connect(&self, chain_id: &str) -> RpcResult<WebSocketStream<ConnectStream>> {
         let token = &self
             .supported_chains
             .get(chain_id)
@@ -71,11 +63,7 @@ impl RpcWsProvider for AllnodesWsProvider {
         let (websocket_provider, _) = async_tungstenite::tokio::connect_async(uri)
             .await
             .map_err(|e| RpcError::WebSocketError(e.to_string()))?;
-
-        Ok(ws.on_upgrade(move |socket| {
-            ws::proxy(project_id, socket, websocket_provider)
-                .with_metrics(future_metrics!("ws_proxy_task", "name" => "allnodes"))
-        }))
+        Ok(websocket_provider)
     }
 }
 
@@ -137,8 +125,9 @@ impl RpcProvider for AllnodesProvider {
             .send()
             .await?;
         let status = response.status();
-        let body = response.bytes().await?;
-        let mut response = (status, body).into_response();
+        let rate_limit_headers = rate_limits::forwarded_headers(response.headers());
+        let body = http_client::read_body(response).await?;
+        let mut response = (status, rate_limit_headers, body).into_response();
         response
             .headers_mut()
             .insert("Content-Type", HeaderValue::from_static("application/json"));
@@ -149,7 +138,7 @@ impl RpcProvider for AllnodesProvider {
 impl RpcProviderFactory<AllnodesConfig> for AllnodesProvider {
     #[tracing::instrument(level = "debug")]
     fn new(provider_config: &AllnodesConfig) -> Self {
-        let forward_proxy_client = reqwest::Client::new();
+        let forward_proxy_client = http_client::upstream_client(&ProviderKind::Allnodes);
         let supported_chains: HashMap<String, String> = provider_config
             .supported_chains
             .iter()
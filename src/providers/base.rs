@@ -1,5 +1,8 @@
 use {
-    super::{Provider, ProviderKind, RateLimited, RpcProvider, RpcProviderFactory},
+    super::{
+        http_client, rate_limits, Provider, ProviderKind, RateLimited, RpcProvider,
+        RpcProviderFactory,
+    },
     crate::{
         env::BaseConfig,
         error::{RpcError, RpcResult},
@@ -60,8 +63,9 @@ impl RpcProvider for BaseProvider {
             .send()
             .await?;
         let status = response.status();
-        let body = response.bytes().await?;
-        let mut response = (status, body).into_response();
+        let rate_limit_headers = rate_limits::forwarded_headers(response.headers());
+        let body = http_client::read_body(response).await?;
+        let mut response = (status, rate_limit_headers, body).into_response();
         response
             .headers_mut()
             .insert("Content-Type", HeaderValue::from_static("application/json"));
@@ -72,7 +76,7 @@ impl RpcProvider for BaseProvider {
 impl RpcProviderFactory<BaseConfig> for BaseProvider {
     #[tracing::instrument(level = "debug")]
     fn new(provider_config: &BaseConfig) -> Self {
-        let forward_proxy_client = reqwest::Client::new();
+        let forward_proxy_client = http_client::upstream_client(&ProviderKind::Base);
         let supported_chains: HashMap<String, String> = provider_config
             .supported_chains
             .iter()
@@ -1,7 +1,7 @@
 use {
     crate::{
         error::RpcError,
-        providers::{ChainOrchestrationProvider, ProviderKind},
+        providers::{http_client, ChainOrchestrationProvider, ProviderKind},
         utils::crypto::disassemble_caip2,
         Metrics,
     },
@@ -78,7 +78,7 @@ pub struct BungeeProvider {
 impl BungeeProvider {
     pub fn new(api_key: String) -> Self {
         let base_api_url = "https://api.socket.tech".to_string();
-        let http_client = reqwest::Client::new();
+        let http_client = http_client::upstream_client(&ProviderKind::Bungee);
         Self {
             provider_kind: ProviderKind::Bungee,
             api_key,
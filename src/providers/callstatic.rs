@@ -1,5 +1,8 @@
 use {
-    super::{Provider, ProviderKind, RateLimited, RpcProvider, RpcProviderFactory},
+    super::{
+        http_client, rate_limits, Provider, ProviderKind, RateLimited, RpcProvider,
+        RpcProviderFactory,
+    },
     crate::{
         env::CallStaticConfig,
         error::{RpcError, RpcResult},
@@ -60,9 +63,11 @@ impl RpcProvider for CallStaticProvider {
             .send()
             .await?;
         let status = response.status();
-        let body = response.bytes().await?;
+        let rate_limit_headers = rate_limits::forwarded_headers(response.headers());
+        let body = http_client::read_body(response).await?;
         let response = (
             status,
+            rate_limit_headers,
             [(
                 hyper::header::CONTENT_TYPE,
                 HeaderValue::from_static("application/json"),
@@ -77,7 +82,7 @@ impl RpcProvider for CallStaticProvider {
 impl RpcProviderFactory<CallStaticConfig> for CallStaticProvider {
     #[tracing::instrument(level = "debug")]
     fn new(provider_config: &CallStaticConfig) -> Self {
-        let forward_proxy_client = reqwest::Client::new();
+        let forward_proxy_client = http_client::upstream_client(&ProviderKind::CallStatic);
         let supported_chains: HashMap<String, String> = provider_config
             .supported_chains
             .iter()
@@ -0,0 +1,421 @@
+use {
+    crate::json_rpc::JsonRpcRequest,
+    serde::{Deserialize, Serialize},
+    serde_json::Value,
+    std::fmt::{self, Display},
+};
+
+/// Blocks behind the chain head for which the state is available on the
+/// pruned (full) nodes, older state requires the archive node
+const ARCHIVE_BLOCKS_DEPTH: u64 = 128;
+
+/// Methods reading the chain state at the block with the block parameter index
+const STATE_METHODS_BLOCK_PARAM: &[(&str, usize)] = &[
+    ("eth_getBalance", 1),
+    ("eth_getCode", 1),
+    ("eth_getTransactionCount", 1),
+    ("eth_getStorageAt", 2),
+    ("eth_call", 1),
+    ("eth_estimateGas", 1),
+    ("eth_getProof", 2),
+];
+
+/// Capabilities of the provider for the chain declared in the provider
+/// catalogue. Providers without the declared capabilities are assumed to
+/// support all methods.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProviderCapabilities {
+    /// Historical state older than the last 128 blocks
+    #[serde(default)]
+    pub archive: bool,
+    /// `trace_*` methods
+    #[serde(default)]
+    pub trace: bool,
+    /// `debug_*` methods
+    #[serde(default)]
+    pub debug: bool,
+    /// `txpool_*` methods
+    #[serde(default)]
+    pub txpool: bool,
+    /// Maximum `eth_getLogs` blocks range, not limited if not set
+    #[serde(default)]
+    pub max_logs_block_range: Option<u64>,
+    /// Maximum amount of the batch request items, not limited if not set
+    #[serde(default)]
+    pub max_batch_size: Option<usize>,
+}
+
+impl ProviderCapabilities {
+    pub fn satisfies(&self, required: &RequiredCapabilities) -> bool {
+        (!required.archive || self.archive)
+            && (!required.trace || self.trace)
+            && (!required.debug || self.debug)
+            && (!required.txpool || self.txpool)
+            && match (required.logs_block_range, self.max_logs_block_range) {
+                (Some(range), Some(max_range)) => range <= max_range,
+                _ => true,
+            }
+            && match (required.batch_size, self.max_batch_size) {
+                (Some(size), Some(max_size)) => size <= max_size,
+                _ => true,
+            }
+    }
+}
+
+/// Capabilities required by the JSON-RPC request
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RequiredCapabilities {
+    pub archive: bool,
+    pub trace: bool,
+    pub debug: bool,
+    pub txpool: bool,
+    /// Requested `eth_getLogs` blocks range
+    pub logs_block_range: Option<u64>,
+    /// Amount of the batch request items, the providers accepting it are
+    /// only preferred when the batch splitting is disabled
+    pub batch_size: Option<usize>,
+}
+
+impl RequiredCapabilities {
+    /// Capabilities required by the request, `head` is the latest known
+    /// chain block used to resolve the block tags
+    pub fn for_request(request: &JsonRpcRequest, head: Option<u64>) -> Self {
+        let method = request.method.as_ref();
+        let mut required = Self {
+            trace: method.starts_with("trace_"),
+            debug: method.starts_with("debug_"),
+            txpool: method.starts_with("txpool_"),
+            ..Default::default()
+        };
+
+        if method == "eth_getLogs" {
+            required.logs_block_range = request
+                .params
+                .get(0)
+                .and_then(|filter| logs_block_range(filter, head));
+        } else if let Some((_, index)) = STATE_METHODS_BLOCK_PARAM
+            .iter()
+            .find(|(name, _)| *name == method)
+        {
+            required.archive = match (request.params.get(*index).and_then(block_number), head) {
+                (Some(BlockNumber::Earliest), _) => true,
+                (Some(BlockNumber::Number(block)), Some(head)) => {
+                    block.saturating_add(ARCHIVE_BLOCKS_DEPTH) < head
+                }
+                _ => false,
+            };
+        }
+        required
+    }
+
+    /// Capabilities required by all the batch requests
+    pub fn for_batch(requests: &[JsonRpcRequest], head: Option<u64>) -> Self {
+        requests
+            .iter()
+            .map(|request| Self::for_request(request, head))
+            .fold(
+                Self {
+                    batch_size: Some(requests.len()),
+                    ..Default::default()
+                },
+                |acc, required| Self {
+                    archive: acc.archive || required.archive,
+                    trace: acc.trace || required.trace,
+                    debug: acc.debug || required.debug,
+                    txpool: acc.txpool || required.txpool,
+                    logs_block_range: acc.logs_block_range.max(required.logs_block_range),
+                    batch_size: acc.batch_size,
+                },
+            )
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Whether the requirement is not satisfied by some of the `declared`
+    /// providers capabilities. Any logs range and batch size are within the
+    /// limits of the declared providers up to the smallest declared maximum.
+    pub fn is_constraining<'a>(
+        &self,
+        declared: impl IntoIterator<Item = &'a ProviderCapabilities>,
+    ) -> bool {
+        if self.archive || self.trace || self.debug || self.txpool {
+            return true;
+        }
+        let (smallest_logs_range, smallest_batch_size) = declared.into_iter().fold(
+            (None, None),
+            |(logs_range, batch_size): (Option<u64>, Option<usize>), capabilities| {
+                (
+                    min_limit(logs_range, capabilities.max_logs_block_range),
+                    min_limit(batch_size, capabilities.max_batch_size),
+                )
+            },
+        );
+        self.logs_block_range
+            .zip(smallest_logs_range)
+            .is_some_and(|(range, max_range)| range > max_range)
+            || self
+                .batch_size
+                .zip(smallest_batch_size)
+                .is_some_and(|(size, max_size)| size > max_size)
+    }
+
+    /// Names of the required capabilities without the logs range value
+    pub fn names(&self) -> Vec<&'static str> {
+        [
+            (self.archive, "archive"),
+            (self.trace, "trace"),
+            (self.debug, "debug"),
+            (self.txpool, "txpool"),
+            (self.logs_block_range.is_some(), "logs_range"),
+            (self.batch_size.is_some(), "batch_size"),
+        ]
+        .into_iter()
+        .filter(|(required, _)| *required)
+        .map(|(_, name)| name)
+        .collect()
+    }
+}
+
+impl Display for RequiredCapabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut capabilities = self
+            .names()
+            .into_iter()
+            .filter(|name| *name != "logs_range" && *name != "batch_size")
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        if let Some(range) = self.logs_block_range {
+            capabilities.push(format!("{range} blocks logs range"));
+        }
+        if let Some(size) = self.batch_size {
+            capabilities.push(format!("{size} items batch"));
+        }
+        write!(f, "{}", capabilities.join(", "))
+    }
+}
+
+/// Smaller of the declared limits
+fn min_limit<T: Ord>(limit: Option<T>, declared: Option<T>) -> Option<T> {
+    match (limit, declared) {
+        (Some(limit), Some(declared)) => Some(limit.min(declared)),
+        (limit, declared) => limit.or(declared),
+    }
+}
+
+enum BlockNumber {
+    Earliest,
+    Number(u64),
+}
+
+/// Block number of the block parameter, `None` for the latest block tags,
+/// the block hash or the missing parameter
+fn block_number(param: &Value) -> Option<BlockNumber> {
+    match param {
+        Value::String(tag) if tag == "earliest" => Some(BlockNumber::Earliest),
+        Value::String(number) => number
+            .strip_prefix("0x")
+            .and_then(|hex| u64::from_str_radix(hex, 16).ok())
+            .map(BlockNumber::Number),
+        // EIP-1898 block parameter object
+        Value::Object(object) => object.get("blockNumber").and_then(block_number),
+        _ => None,
+    }
+}
+
+/// Block of the logs filter range
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogsBlock {
+    Number(u64),
+    /// Block tag, e.g. `latest` or `finalized`
+    Tag(String),
+}
+
+/// First and last blocks of the logs filter, the missing blocks are the
+/// `latest` tag. `None` for the block hash filter and the invalid blocks.
+pub fn logs_filter_blocks(filter: &Value) -> Option<(LogsBlock, LogsBlock)> {
+    if filter.get("blockHash").is_some() {
+        return None;
+    }
+    let block = |param: Option<&Value>| match param {
+        None => Some(LogsBlock::Tag("latest".into())),
+        Some(param) => match block_number(param) {
+            Some(BlockNumber::Earliest) => Some(LogsBlock::Number(0)),
+            Some(BlockNumber::Number(block)) => Some(LogsBlock::Number(block)),
+            None => param.as_str().map(|tag| LogsBlock::Tag(tag.to_string())),
+        },
+    };
+    Some((
+        block(filter.get("fromBlock"))?,
+        block(filter.get("toBlock"))?,
+    ))
+}
+
+/// First and last blocks of the logs filter, the missing blocks and the
+/// block tags resolve to the `head`. `None` for the block hash filter.
+fn logs_block_bounds(filter: &Value, head: Option<u64>) -> Option<(u64, u64)> {
+    if filter.get("blockHash").is_some() {
+        return None;
+    }
+    let resolve = |param: Option<&Value>| match param.and_then(block_number) {
+        Some(BlockNumber::Earliest) => Some(0),
+        Some(BlockNumber::Number(block)) => Some(block),
+        None => head,
+    };
+    Some((
+        resolve(filter.get("fromBlock"))?,
+        resolve(filter.get("toBlock"))?,
+    ))
+}
+
+/// Blocks range of the logs filter
+fn logs_block_range(filter: &Value, head: Option<u64>) -> Option<u64> {
+    logs_block_bounds(filter, head)
+        .map(|(from_block, to_block)| to_block.saturating_sub(from_block))
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, serde_json::json};
+
+    fn request(method: &str, params: Value) -> JsonRpcRequest {
+        JsonRpcRequest::new_with_params(json!(1), method.into(), params)
+    }
+
+    #[test]
+    fn test_required_capabilities() {
+        let head = Some(20_000_000);
+        let required =
+            RequiredCapabilities::for_request(&request("debug_traceTransaction", json!([])), head);
+        assert!(required.debug && !required.trace);
+        assert!(
+            !RequiredCapabilities::for_request(&request("eth_chainId", json!([])), head).archive
+        );
+
+        let balance = |block: Value| {
+            RequiredCapabilities::for_request(
+                &request("eth_getBalance", json!(["0x0", block])),
+                head,
+            )
+        };
+        assert!(balance(json!("0x1")).archive);
+        assert!(balance(json!("earliest")).archive);
+        assert!(balance(json!({ "blockNumber": "0x1" })).archive);
+        assert!(!balance(json!("latest")).archive);
+        assert!(!balance(json!(format!("{:#x}", 20_000_000 - 10))).archive);
+
+        let logs = RequiredCapabilities::for_request(
+            &request("eth_getLogs", json!([{ "fromBlock": "0x1312d00" }])),
+            head,
+        );
+        assert_eq!(logs.logs_block_range, Some(0));
+        let logs = RequiredCapabilities::for_request(
+            &request(
+                "eth_getLogs",
+                json!([{ "fromBlock": "0x0", "toBlock": "0x2710" }]),
+            ),
+            None,
+        );
+        assert_eq!(logs.logs_block_range, Some(10_000));
+        assert_eq!(logs.to_string(), "10000 blocks logs range");
+        assert_eq!(logs.names(), vec!["logs_range"]);
+        assert_eq!(
+            logs_block_bounds(&json!({ "fromBlock": "0x10" }), head),
+            Some((16, 20_000_000))
+        );
+        assert_eq!(
+            logs_block_bounds(&json!({ "blockHash": "0x0" }), head),
+            None
+        );
+    }
+
+    #[test]
+    fn test_logs_filter_blocks() {
+        assert_eq!(
+            logs_filter_blocks(&json!({ "fromBlock": "earliest", "toBlock": "0x10" })),
+            Some((LogsBlock::Number(0), LogsBlock::Number(16)))
+        );
+        assert_eq!(
+            logs_filter_blocks(&json!({ "fromBlock": "0x10", "toBlock": "finalized" })),
+            Some((LogsBlock::Number(16), LogsBlock::Tag("finalized".into())))
+        );
+        assert_eq!(
+            logs_filter_blocks(&json!({ "fromBlock": "0x10" })),
+            Some((LogsBlock::Number(16), LogsBlock::Tag("latest".into())))
+        );
+        assert_eq!(logs_filter_blocks(&json!({ "blockHash": "0x0" })), None);
+        assert_eq!(logs_filter_blocks(&json!({ "toBlock": 16 })), None);
+    }
+
+    #[test]
+    fn test_provider_capabilities_satisfy() {
+        let capabilities = toml::from_str::<ProviderCapabilities>(
+            "archive = true\ntrace = true\nmax_logs_block_range = 5000",
+        )
+        .unwrap();
+        let batch = RequiredCapabilities::for_batch(
+            &[
+                request("trace_block", json!(["0x1"])),
+                request(
+                    "eth_getLogs",
+                    json!([{ "fromBlock": "0x0", "toBlock": "0x64" }]),
+                ),
+            ],
+            None,
+        );
+        assert!(capabilities.satisfies(&batch));
+        assert_eq!(batch.batch_size, Some(2));
+        assert!(!ProviderCapabilities::default().satisfies(&batch));
+        assert!(!ProviderCapabilities {
+            max_batch_size: Some(1),
+            ..capabilities.clone()
+        }
+        .satisfies(&batch));
+        assert!(!capabilities.satisfies(&RequiredCapabilities {
+            logs_block_range: Some(10_000),
+            ..Default::default()
+        }));
+    }
+
+    #[test]
+    fn test_required_capabilities_constraining() {
+        let declared = [
+            ProviderCapabilities {
+                max_logs_block_range: Some(1000),
+                ..Default::default()
+            },
+            ProviderCapabilities {
+                archive: true,
+                max_logs_block_range: Some(5000),
+                ..Default::default()
+            },
+        ];
+        let logs = |range| RequiredCapabilities {
+            logs_block_range: Some(range),
+            ..Default::default()
+        };
+        assert!(!logs(0).is_constraining(&declared));
+        assert!(!logs(1000).is_constraining(&declared));
+        assert!(logs(1001).is_constraining(&declared));
+        assert!(!logs(10_000).is_constraining(&[ProviderCapabilities::default()]));
+        assert!(RequiredCapabilities {
+            archive: true,
+            ..Default::default()
+        }
+        .is_constraining(&declared));
+        assert!(!RequiredCapabilities::default().is_constraining(&declared));
+
+        let batch = |size| RequiredCapabilities {
+            batch_size: Some(size),
+            ..Default::default()
+        };
+        let declared = [ProviderCapabilities {
+            max_batch_size: Some(10),
+            ..Default::default()
+        }];
+        assert!(!batch(10).is_constraining(&declared));
+        assert!(batch(11).is_constraining(&declared));
+    }
+}
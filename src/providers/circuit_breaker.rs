@@ -0,0 +1,295 @@
+use {
+    super::{health::ProviderCallResult, ProviderKind},
+    crate::Metrics,
+    serde::Serialize,
+    std::{
+        collections::HashMap,
+        sync::Mutex,
+        time::{Duration, Instant},
+    },
+};
+
+const DEFAULT_FAILURE_THRESHOLD: u32 = 5;
+const DEFAULT_COOLDOWN: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CircuitState {
+    /// Calls are passed to the provider
+    Closed,
+    /// Provider is skipped until the cooldown is passed
+    Open,
+    /// Cooldown is passed and a single probing call is passed to the provider
+    HalfOpen,
+}
+
+impl CircuitState {
+    /// Numeric representation of the state for the metrics gauge
+    pub fn as_gauge_value(&self) -> f64 {
+        match self {
+            CircuitState::Closed => 0.0,
+            CircuitState::Open => 1.0,
+            CircuitState::HalfOpen => 2.0,
+        }
+    }
+}
+
+/// Circuit breaker per RPC provider and chain.
+/// The circuit is opened after the configured amount of consecutive
+/// connection errors, timeouts or rate-limited responses and the provider is
+/// skipped during the providers selection until the cooldown is passed. After
+/// the cooldown a single probing call is passed to the provider and the
+/// circuit is closed on success or opened again on failure.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    circuits: Mutex<HashMap<(ProviderKind, String), Circuit>>,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: Option<u32>, cooldown: Option<Duration>) -> Self {
+        Self {
+            failure_threshold: failure_threshold.unwrap_or(DEFAULT_FAILURE_THRESHOLD),
+            cooldown: cooldown.unwrap_or(DEFAULT_COOLDOWN),
+            circuits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.failure_threshold > 0 && !self.cooldown.is_zero()
+    }
+
+    /// Whether the provider can be selected for the chain, the half-open
+    /// circuit is selectable without taking the probing call slot
+    pub fn is_selectable(&self, provider_kind: &ProviderKind, chain_id: &str) -> bool {
+        !self.is_enabled() || self.state(provider_kind, chain_id) != CircuitState::Open
+    }
+
+    /// Whether the call to the provider for the chain is permitted.
+    /// Takes the probing call slot when the circuit is half-open, so it's
+    /// called only right before calling the selected provider.
+    pub fn try_acquire_call(&self, provider_kind: &ProviderKind, chain_id: &str) -> bool {
+        if !self.is_enabled() {
+            return true;
+        }
+        let Ok(mut circuits) = self.circuits.lock() else {
+            return true;
+        };
+        circuits
+            .get_mut(&(provider_kind.clone(), chain_id.to_string()))
+            .is_none_or(|circuit| circuit.try_acquire(Instant::now(), self.cooldown))
+    }
+
+    /// Current circuit state of the provider for the chain
+    pub fn state(&self, provider_kind: &ProviderKind, chain_id: &str) -> CircuitState {
+        let Ok(circuits) = self.circuits.lock() else {
+            return CircuitState::Closed;
+        };
+        circuits
+            .get(&(provider_kind.clone(), chain_id.to_string()))
+            .map_or(CircuitState::Closed, |circuit| {
+                circuit.state(Instant::now(), self.cooldown)
+            })
+    }
+
+    /// Record the provider call outcome and update the circuit state
+    pub fn record(
+        &self,
+        metrics: &Metrics,
+        provider_kind: &ProviderKind,
+        chain_id: &str,
+        result: ProviderCallResult,
+    ) {
+        if !self.is_enabled() {
+            return;
+        }
+        let Ok(mut circuits) = self.circuits.lock() else {
+            return;
+        };
+        let circuit = circuits
+            .entry((provider_kind.clone(), chain_id.to_string()))
+            .or_default();
+        let previous_state = circuit.state(Instant::now(), self.cooldown);
+
+        match result {
+            ProviderCallResult::Success(_) => circuit.close(),
+            ProviderCallResult::ConnectionError
+            | ProviderCallResult::Timeout
+            | ProviderCallResult::RateLimited => {
+                circuit.record_failure(Instant::now(), self.failure_threshold)
+            }
+            // Provider responded, but with an error status, which is not
+            // a reason to break the circuit
+            ProviderCallResult::Failure => {}
+        }
+
+        let state = circuit.state(Instant::now(), self.cooldown);
+        if state != previous_state {
+            if state == CircuitState::Open {
+                metrics.add_provider_circuit_opened(provider_kind, chain_id.to_string());
+            }
+            metrics.record_provider_circuit_state(provider_kind, chain_id.to_string(), state);
+        }
+    }
+
+    /// Record the current circuits states as metrics
+    pub fn record_states(&self, metrics: &Metrics) {
+        let Ok(circuits) = self.circuits.lock() else {
+            return;
+        };
+        let now = Instant::now();
+        for ((provider_kind, chain_id), circuit) in circuits.iter() {
+            metrics.record_provider_circuit_state(
+                provider_kind,
+                chain_id.clone(),
+                circuit.state(now, self.cooldown),
+            );
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Circuit {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    probe_started_at: Option<Instant>,
+}
+
+impl Circuit {
+    fn state(&self, now: Instant, cooldown: Duration) -> CircuitState {
+        match self.opened_at {
+            None => CircuitState::Closed,
+            Some(opened_at) if now.duration_since(opened_at) < cooldown => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+
+    fn try_acquire(&mut self, now: Instant, cooldown: Duration) -> bool {
+        match self.state(now, cooldown) {
+            CircuitState::Closed => true,
+            CircuitState::Open => false,
+            CircuitState::HalfOpen => {
+                // Only a single probing call at a time, the probe is expired
+                // after the cooldown in case the probing call was dropped
+                // before recording its result
+                let is_probing = self
+                    .probe_started_at
+                    .is_some_and(|started_at| now.duration_since(started_at) < cooldown);
+                if is_probing {
+                    return false;
+                }
+                self.probe_started_at = Some(now);
+                true
+            }
+        }
+    }
+
+    fn close(&mut self) {
+        self.consecutive_failures = 0;
+        self.opened_at = None;
+        self.probe_started_at = None;
+    }
+
+    fn record_failure(&mut self, now: Instant, failure_threshold: u32) {
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        // Failed probing call opens the circuit again for the next cooldown
+        if self.consecutive_failures >= failure_threshold || self.probe_started_at.is_some() {
+            self.opened_at = Some(now);
+            self.probe_started_at = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_circuit_opens_after_consecutive_failures() {
+        let cooldown = Duration::from_secs(30);
+        let now = Instant::now();
+        let mut circuit = Circuit::default();
+
+        circuit.record_failure(now, 3);
+        circuit.record_failure(now, 3);
+        assert_eq!(circuit.state(now, cooldown), CircuitState::Closed);
+
+        // Success resets the consecutive failures counter
+        circuit.close();
+        circuit.record_failure(now, 3);
+        circuit.record_failure(now, 3);
+        assert_eq!(circuit.state(now, cooldown), CircuitState::Closed);
+        assert!(circuit.try_acquire(now, cooldown));
+
+        circuit.record_failure(now, 3);
+        assert_eq!(circuit.state(now, cooldown), CircuitState::Open);
+        assert!(!circuit.try_acquire(now + Duration::from_secs(10), cooldown));
+    }
+
+    #[test]
+    fn test_circuit_half_open_probing() {
+        let cooldown = Duration::from_secs(30);
+        let opened_at = Instant::now();
+        let mut circuit = Circuit::default();
+        circuit.record_failure(opened_at, 1);
+        assert_eq!(circuit.state(opened_at, cooldown), CircuitState::Open);
+
+        // Only a single probe is permitted after the cooldown
+        let after_cooldown = opened_at + cooldown;
+        assert_eq!(
+            circuit.state(after_cooldown, cooldown),
+            CircuitState::HalfOpen
+        );
+        assert!(circuit.try_acquire(after_cooldown, cooldown));
+        assert!(!circuit.try_acquire(after_cooldown, cooldown));
+
+        // Failed probe opens the circuit again
+        circuit.record_failure(after_cooldown, 1);
+        assert_eq!(circuit.state(after_cooldown, cooldown), CircuitState::Open);
+
+        // Successful probe closes the circuit
+        let after_second_cooldown = after_cooldown + cooldown;
+        assert!(circuit.try_acquire(after_second_cooldown, cooldown));
+        circuit.close();
+        assert_eq!(
+            circuit.state(after_second_cooldown, cooldown),
+            CircuitState::Closed
+        );
+        assert!(circuit.try_acquire(after_second_cooldown, cooldown));
+    }
+
+    #[test]
+    fn test_circuit_breaker_ignores_error_statuses() {
+        let metrics = Metrics::new();
+        let breaker = CircuitBreaker::new(Some(1), None);
+        let provider = ProviderKind::Quicknode;
+
+        breaker.record(&metrics, &provider, "eip155:1", ProviderCallResult::Failure);
+        assert!(breaker.try_acquire_call(&provider, "eip155:1"));
+
+        breaker.record(&metrics, &provider, "eip155:1", ProviderCallResult::Timeout);
+        assert!(!breaker.is_selectable(&provider, "eip155:1"));
+        assert!(!breaker.try_acquire_call(&provider, "eip155:1"));
+        // Circuits are tracked per chain
+        assert!(breaker.try_acquire_call(&provider, "eip155:10"));
+    }
+
+    #[test]
+    fn test_circuit_breaker_selection_keeps_probe() {
+        let metrics = Metrics::new();
+        let breaker = CircuitBreaker::new(Some(1), Some(Duration::from_millis(1)));
+        let provider = ProviderKind::Quicknode;
+
+        breaker.record(&metrics, &provider, "eip155:1", ProviderCallResult::Timeout);
+        std::thread::sleep(Duration::from_millis(2));
+        assert_eq!(breaker.state(&provider, "eip155:1"), CircuitState::HalfOpen);
+
+        // Selecting the half-open provider doesn't take the probing call slot
+        assert!(breaker.is_selectable(&provider, "eip155:1"));
+        assert!(breaker.is_selectable(&provider, "eip155:1"));
+        assert!(breaker.try_acquire_call(&provider, "eip155:1"));
+        assert!(!breaker.try_acquire_call(&provider, "eip155:1"));
+        assert!(breaker.is_selectable(&provider, "eip155:1"));
+    }
+}
@@ -13,7 +13,7 @@ use {
                 quotes::{OnRampBuyQuotesParams, OnRampBuyQuotesResponse},
             },
         },
-        providers::{ProviderKind, TokenMetadataCacheProvider},
+        providers::{http_client, ProviderKind, TokenMetadataCacheProvider},
         utils::crypto::ChainId,
         Metrics,
     },
@@ -40,7 +40,7 @@ impl CoinbaseProvider {
             api_key,
             app_id,
             base_api_url,
-            http_client: reqwest::Client::new(),
+            http_client: http_client::upstream_client(&ProviderKind::Coinbase),
         }
     }
 
@@ -8,7 +8,7 @@ use {
         },
         providers::{
             balance::{BalanceItem, BalanceQuantity},
-            ProviderKind, TokenMetadataCacheProvider,
+            http_client, ProviderKind, TokenMetadataCacheProvider,
         },
         utils::{capitalize_first_letter, crypto},
         Metrics,
@@ -355,7 +355,7 @@ impl BalanceProvider for DuneProvider {
 
 impl BalanceProviderFactory<DuneConfig> for DuneProvider {
     fn new(provider_config: &DuneConfig, _cache: Option<Arc<Pool>>) -> Self {
-        let http_client = reqwest::Client::new();
+        let http_client = http_client::upstream_client(&ProviderKind::Dune);
         Self {
             provider_kind: ProviderKind::Dune,
             api_key: provider_config.api_key.clone(),
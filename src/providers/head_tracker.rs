@@ -0,0 +1,300 @@
+use {
+    super::{ProviderKind, RpcProvider},
+    axum::body::{to_bytes, Bytes},
+    serde_json::Value,
+    std::{
+        collections::{HashMap, HashSet},
+        sync::RwLock,
+        time::{Duration, Instant},
+    },
+    tokio::time::timeout,
+};
+
+/// Timeout of the provider chain head request
+const HEAD_REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+/// Maximum size of the chain head response, NEAR block response is the largest
+const HEAD_RESPONSE_MAX_BYTES: usize = 1024 * 1024;
+/// Default maximum lag behind the best provider in blocks (or slots)
+const DEFAULT_MAX_HEAD_LAG: u64 = 15;
+/// Observed heads older than this are not trusted
+const HEAD_MAX_AGE: Duration = Duration::from_secs(60);
+
+/// Latest observed block heights of the RPC providers per chain
+#[derive(Debug, Default)]
+pub struct ProviderHeads {
+    heads: RwLock<HashMap<(ProviderKind, String), ProviderHead>>,
+    /// Providers lagging behind the best provider for the chain with the
+    /// time they were marked as lagging
+    lagging: RwLock<HashMap<(ProviderKind, String), Instant>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ProviderHead {
+    block_number: u64,
+    updated_at: Instant,
+}
+
+impl ProviderHeads {
+    pub fn record(&self, provider_kind: &ProviderKind, chain_id: &str, block_number: u64) {
+        let Ok(mut heads) = self.heads.write() else {
+            return;
+        };
+        heads.insert(
+            (provider_kind.clone(), chain_id.to_string()),
+            ProviderHead {
+                block_number,
+                updated_at: Instant::now(),
+            },
+        );
+    }
+
+    /// Latest observed provider block height for the chain, `None` if stale
+    pub fn get(&self, provider_kind: &ProviderKind, chain_id: &str) -> Option<u64> {
+        self.heads
+            .read()
+            .ok()?
+            .get(&(provider_kind.clone(), chain_id.to_string()))
+            .filter(|head| head.updated_at.elapsed() < HEAD_MAX_AGE)
+            .map(|head| head.block_number)
+    }
+
+    /// Best observed block height of the chain providers, `None` if stale
+    pub fn chain_head(&self, chain_id: &str) -> Option<u64> {
+        self.heads
+            .read()
+            .ok()?
+            .iter()
+            .filter(|((_, head_chain_id), head)| {
+                head_chain_id == chain_id && head.updated_at.elapsed() < HEAD_MAX_AGE
+            })
+            .map(|(_, head)| head.block_number)
+            .max()
+    }
+
+    /// Update the lagging state of the probed providers for the chain. The
+    /// lagging state of the providers which were not probed expires after
+    /// the head max age.
+    pub fn set_lagging(
+        &self,
+        chain_id: &str,
+        heads: &[(ProviderKind, u64)],
+        providers: &HashSet<ProviderKind>,
+    ) {
+        let Ok(mut lagging) = self.lagging.write() else {
+            return;
+        };
+        lagging.retain(|_, marked_at| marked_at.elapsed() < HEAD_MAX_AGE);
+        for (provider_kind, _) in heads {
+            let key = (provider_kind.clone(), chain_id.to_string());
+            if providers.contains(provider_kind) {
+                lagging.insert(key, Instant::now());
+            } else {
+                lagging.remove(&key);
+            }
+        }
+    }
+
+    /// Whether the provider is lagging behind the best provider for the chain
+    pub fn is_lagging(&self, provider_kind: &ProviderKind, chain_id: &str) -> bool {
+        self.lagging.read().is_ok_and(|lagging| {
+            lagging
+                .get(&(provider_kind.clone(), chain_id.to_string()))
+                .is_some_and(|marked_at| marked_at.elapsed() < HEAD_MAX_AGE)
+        })
+    }
+}
+
+/// Chain head JSON-RPC request for the chain namespace,
+/// `None` if the chain head tracking is not supported for the namespace
+fn head_request(chain_id: &str) -> Option<&'static str> {
+    let (namespace, _) = chain_id.split_once(':')?;
+    match namespace {
+        "eip155" => Some(r#"{"jsonrpc":"2.0","id":1,"method":"eth_blockNumber","params":[]}"#),
+        "solana" => Some(r#"{"jsonrpc":"2.0","id":1,"method":"getSlot","params":[]}"#),
+        "near" => {
+            Some(r#"{"jsonrpc":"2.0","id":1,"method":"block","params":{"finality":"final"}}"#)
+        }
+        "sui" => Some(
+            r#"{"jsonrpc":"2.0","id":1,"method":"sui_getLatestCheckpointSequenceNumber","params":[]}"#,
+        ),
+        "ton" => Some(r#"{"jsonrpc":"2.0","id":1,"method":"getMasterchainInfo","params":{}}"#),
+        _ => None,
+    }
+}
+
+/// Whether the chain head tracking is supported for the chain namespace
+pub fn is_head_tracking_supported(chain_id: &str) -> bool {
+    head_request(chain_id).is_some()
+}
+
+/// Parse the chain head from the namespace-specific JSON-RPC result
+fn parse_head(chain_id: &str, result: &Value) -> Option<u64> {
+    let (namespace, _) = chain_id.split_once(':')?;
+    match namespace {
+        "eip155" => u64::from_str_radix(result.as_str()?.strip_prefix("0x")?, 16).ok(),
+        "solana" => result.as_u64(),
+        "near" => result.get("header")?.get("height")?.as_u64(),
+        // Sui returns the checkpoint sequence number as a decimal string
+        "sui" => result.as_str()?.parse().ok(),
+        "ton" => result.get("last")?.get("seqno")?.as_u64(),
+        _ => None,
+    }
+}
+
+/// Request the current chain head from the provider
+pub async fn fetch_head(provider: &dyn RpcProvider, chain_id: &str) -> Option<u64> {
+    let request = head_request(chain_id)?;
+    let response = timeout(
+        HEAD_REQUEST_TIMEOUT,
+        provider.proxy(chain_id, Bytes::from_static(request.as_bytes())),
+    )
+    .await
+    .ok()?
+    .ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    let body = to_bytes(response.into_body(), HEAD_RESPONSE_MAX_BYTES)
+        .await
+        .ok()?;
+    let response = serde_json::from_slice::<Value>(&body).ok()?;
+    parse_head(chain_id, response.get("result")?)
+}
+
+/// Maximum lag behind the best provider for the chain, roughly 30 seconds
+/// of the chain blocks (or slots)
+pub fn max_head_lag(chain_id: &str) -> u64 {
+    match chain_id {
+        // Ethereum mainnet and Sepolia, 12s blocks
+        "eip155:1" | "eip155:11155111" => 3,
+        // Gnosis, 5s blocks
+        "eip155:100" => 6,
+        // Arbitrum One and Sepolia, 250ms blocks
+        "eip155:42161" | "eip155:421614" => 120,
+        // BSC, 3s blocks
+        "eip155:56" => 10,
+        // Polygon, 2s blocks
+        "eip155:137" => 15,
+        _ => match chain_id.split_once(':').map(|(namespace, _)| namespace) {
+            // 400ms slots
+            Some("solana") => 75,
+            // ~1s blocks
+            Some("near") => 30,
+            // ~250ms checkpoints
+            Some("sui") => 120,
+            // ~5s masterchain blocks
+            Some("ton") => 6,
+            _ => DEFAULT_MAX_HEAD_LAG,
+        },
+    }
+}
+
+/// Lag of each provider behind the best provider head
+pub fn head_lags(heads: &[(ProviderKind, u64)]) -> Vec<(ProviderKind, u64)> {
+    let best_head = heads
+        .iter()
+        .map(|(_, head)| *head)
+        .max()
+        .unwrap_or_default();
+    heads
+        .iter()
+        .map(|(provider_kind, head)| (provider_kind.clone(), best_head.saturating_sub(*head)))
+        .collect()
+}
+
+/// Providers lagging behind the best provider more than the maximum lag
+pub fn lagging_providers(heads: &[(ProviderKind, u64)], max_lag: u64) -> HashSet<ProviderKind> {
+    head_lags(heads)
+        .into_iter()
+        .filter(|(_, lag)| *lag > max_lag)
+        .map(|(provider_kind, _)| provider_kind)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, serde_json::json};
+
+    #[test]
+    fn test_provider_heads() {
+        let heads = ProviderHeads::default();
+        assert_eq!(heads.get(&ProviderKind::Quicknode, "eip155:1"), None);
+
+        heads.record(&ProviderKind::Quicknode, "eip155:1", 100);
+        heads.record(&ProviderKind::Quicknode, "eip155:10", 200);
+        assert_eq!(heads.get(&ProviderKind::Quicknode, "eip155:1"), Some(100));
+        assert_eq!(heads.get(&ProviderKind::Quicknode, "eip155:10"), Some(200));
+
+        // The latest observed height is kept
+        heads.record(&ProviderKind::Quicknode, "eip155:1", 99);
+        assert_eq!(heads.get(&ProviderKind::Quicknode, "eip155:1"), Some(99));
+
+        heads.record(&ProviderKind::Publicnode, "eip155:1", 101);
+        assert_eq!(heads.chain_head("eip155:1"), Some(101));
+        assert_eq!(heads.chain_head("eip155:56"), None);
+    }
+
+    #[test]
+    fn test_parse_head() {
+        assert_eq!(parse_head("eip155:1", &json!("0x10")), Some(16));
+        assert_eq!(parse_head("eip155:1", &json!(16)), None);
+        assert_eq!(parse_head("solana:mainnet", &json!(300)), Some(300));
+        assert_eq!(
+            parse_head("near:mainnet", &json!({ "header": { "height": 42 } })),
+            Some(42)
+        );
+        assert_eq!(parse_head("sui:mainnet", &json!("1234")), Some(1234));
+        assert_eq!(
+            parse_head("ton:mainnet", &json!({ "last": { "seqno": 7 } })),
+            Some(7)
+        );
+        assert_eq!(parse_head("tron:0x2b6653dc", &json!("0x10")), None);
+        assert!(!is_head_tracking_supported("tron:0x2b6653dc"));
+    }
+
+    #[test]
+    fn test_lagging_providers() {
+        let heads = vec![
+            (ProviderKind::Quicknode, 100),
+            (ProviderKind::Publicnode, 98),
+            (ProviderKind::Drpc, 90),
+        ];
+        assert_eq!(
+            lagging_providers(&heads, 3),
+            HashSet::from([ProviderKind::Drpc])
+        );
+        assert!(lagging_providers(&heads, 10).is_empty());
+
+        let provider_heads = ProviderHeads::default();
+        provider_heads.set_lagging("eip155:1", &heads, &lagging_providers(&heads, 1));
+        provider_heads.set_lagging(
+            "eip155:10",
+            &[(ProviderKind::Quicknode, 100)],
+            &HashSet::from([ProviderKind::Quicknode]),
+        );
+        assert!(provider_heads.is_lagging(&ProviderKind::Drpc, "eip155:1"));
+        assert!(provider_heads.is_lagging(&ProviderKind::Publicnode, "eip155:1"));
+        assert!(!provider_heads.is_lagging(&ProviderKind::Quicknode, "eip155:1"));
+
+        // Only the probed providers are updated per chain, the failed probe
+        // doesn't re-admit the lagging provider
+        let heads = vec![
+            (ProviderKind::Quicknode, 100),
+            (ProviderKind::Publicnode, 99),
+        ];
+        provider_heads.set_lagging("eip155:1", &heads, &lagging_providers(&heads, 1));
+        assert!(!provider_heads.is_lagging(&ProviderKind::Publicnode, "eip155:1"));
+        assert!(provider_heads.is_lagging(&ProviderKind::Drpc, "eip155:1"));
+        assert!(provider_heads.is_lagging(&ProviderKind::Quicknode, "eip155:10"));
+
+        // Lagging state expires if the provider is not probed anymore
+        if let Some(marked_at) = Instant::now().checked_sub(HEAD_MAX_AGE) {
+            provider_heads
+                .lagging
+                .write()
+                .unwrap()
+                .insert((ProviderKind::Drpc, "eip155:1".to_string()), marked_at);
+            assert!(!provider_heads.is_lagging(&ProviderKind::Drpc, "eip155:1"));
+        }
+    }
+}
@@ -0,0 +1,282 @@
+use {
+    super::{
+        weights::{Availability, ParsedLatencies, ParsedWeights},
+        ProviderKind,
+    },
+    crate::env::ChainId,
+    serde::Deserialize,
+    std::{
+        collections::{HashMap, VecDeque},
+        sync::Mutex,
+        time::{Duration, Instant},
+    },
+};
+
+/// Duration of the single bucket of the health sliding window
+const HEALTH_BUCKET_DURATION: Duration = Duration::from_secs(10);
+const DEFAULT_HEALTH_WINDOW: Duration = Duration::from_secs(300);
+/// Maximum amount of latency samples kept per provider and chain
+const MAX_LATENCY_SAMPLES: usize = 200;
+const LATENCY_PERCENTILE: usize = 95;
+
+/// Source of the providers health data for the weights recalculation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WeightsSource {
+    /// Providers status codes and latencies scraped by the Prometheus
+    #[default]
+    Prometheus,
+    /// In-process sliding window of the providers calls outcomes
+    Local,
+}
+
+/// Outcome of the RPC provider call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProviderCallResult {
+    Success(Duration),
+    /// Provider responded with an error status
+    Failure,
+    ConnectionError,
+    Timeout,
+    RateLimited,
+}
+
+/// In-process providers health tracker fed by the RPC provider calls outcomes
+#[derive(Debug)]
+pub struct ProviderHealthTracker {
+    window: Duration,
+    windows: Mutex<HashMap<(ProviderKind, String), HealthWindow>>,
+}
+
+impl ProviderHealthTracker {
+    pub fn new(window: Option<Duration>) -> Self {
+        Self {
+            window: window.unwrap_or(DEFAULT_HEALTH_WINDOW),
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn record(&self, provider_kind: &ProviderKind, chain_id: &str, result: ProviderCallResult) {
+        let Ok(mut windows) = self.windows.lock() else {
+            return;
+        };
+        windows
+            .entry((provider_kind.clone(), chain_id.to_string()))
+            .or_default()
+            .record(Instant::now(), self.window, result);
+    }
+
+    /// Availabilities and p95 latencies of the providers within the window
+    /// in the same format as parsed from the Prometheus
+    pub fn snapshot(&self) -> (ParsedWeights, ParsedLatencies) {
+        let mut parsed_weights: ParsedWeights = HashMap::new();
+        let mut parsed_latencies: ParsedLatencies = HashMap::new();
+        let Ok(mut windows) = self.windows.lock() else {
+            return (parsed_weights, parsed_latencies);
+        };
+
+        let now = Instant::now();
+        windows.retain(|_, window| {
+            window.evict(now, self.window);
+            !window.is_empty()
+        });
+
+        for ((provider_kind, chain_id), window) in windows.iter() {
+            let chain_availability = window.availability();
+            let (chains, provider_availability) = parsed_weights
+                .entry(provider_kind.clone())
+                .or_insert_with(|| (HashMap::new(), Availability::new(0, 0)));
+            chains.insert(ChainId(chain_id.clone()), chain_availability);
+            *provider_availability = provider_availability.add(chain_availability);
+
+            if let Some(latency) = window.p95_latency() {
+                parsed_latencies
+                    .entry(provider_kind.clone())
+                    .or_default()
+                    .insert(ChainId(chain_id.clone()), latency);
+            }
+        }
+
+        (parsed_weights, parsed_latencies)
+    }
+}
+
+#[derive(Debug)]
+struct HealthBucket {
+    started_at: Instant,
+    success: u64,
+    failure: u64,
+}
+
+#[derive(Debug, Default)]
+struct HealthWindow {
+    buckets: VecDeque<HealthBucket>,
+    /// Successful calls latencies in seconds
+    latencies: VecDeque<(Instant, f64)>,
+}
+
+impl HealthWindow {
+    fn record(&mut self, now: Instant, window: Duration, result: ProviderCallResult) {
+        let bucket = match self.buckets.back_mut() {
+            Some(bucket) if now.duration_since(bucket.started_at) < HEALTH_BUCKET_DURATION => {
+                bucket
+            }
+            _ => {
+                self.buckets.push_back(HealthBucket {
+                    started_at: now,
+                    success: 0,
+                    failure: 0,
+                });
+                self.buckets
+                    .back_mut()
+                    .expect("Bucket was just pushed to the window")
+            }
+        };
+
+        match result {
+            ProviderCallResult::Success(latency) => {
+                bucket.success += 1;
+                if self.latencies.len() == MAX_LATENCY_SAMPLES {
+                    self.latencies.pop_front();
+                }
+                self.latencies.push_back((now, latency.as_secs_f64()));
+            }
+            ProviderCallResult::Failure
+            | ProviderCallResult::ConnectionError
+            | ProviderCallResult::Timeout
+            | ProviderCallResult::RateLimited => bucket.failure += 1,
+        }
+
+        self.evict(now, window);
+    }
+
+    fn evict(&mut self, now: Instant, window: Duration) {
+        while self
+            .buckets
+            .front()
+            .is_some_and(|bucket| now.duration_since(bucket.started_at) > window)
+        {
+            self.buckets.pop_front();
+        }
+        while self
+            .latencies
+            .front()
+            .is_some_and(|(recorded_at, _)| now.duration_since(*recorded_at) > window)
+        {
+            self.latencies.pop_front();
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.buckets.is_empty()
+    }
+
+    fn availability(&self) -> Availability {
+        let (success, failure) = self
+            .buckets
+            .iter()
+            .fold((0, 0), |(success, failure), bucket| {
+                (success + bucket.success, failure + bucket.failure)
+            });
+        Availability::new(success, failure)
+    }
+
+    fn p95_latency(&self) -> Option<f64> {
+        if self.latencies.is_empty() {
+            return None;
+        }
+        let mut sorted = self
+            .latencies
+            .iter()
+            .map(|(_, latency)| *latency)
+            .collect::<Vec<_>>();
+        sorted.sort_unstable_by(f64::total_cmp);
+        sorted
+            .get((sorted.len() - 1) * LATENCY_PERCENTILE / 100)
+            .copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_health_window_availability() {
+        let window = Duration::from_secs(60);
+        let start = Instant::now();
+        let mut health = HealthWindow::default();
+
+        health.record(start, window, ProviderCallResult::Success(Duration::ZERO));
+        health.record(start, window, ProviderCallResult::Timeout);
+        health.record(
+            start + Duration::from_secs(30),
+            window,
+            ProviderCallResult::RateLimited,
+        );
+        health.record(
+            start + Duration::from_secs(30),
+            window,
+            ProviderCallResult::Success(Duration::ZERO),
+        );
+        assert_eq!(health.availability(), Availability::new(2, 2));
+
+        // The first bucket is evicted from the window
+        health.evict(start + Duration::from_secs(61), window);
+        assert_eq!(health.availability(), Availability::new(1, 1));
+
+        health.evict(start + Duration::from_secs(120), window);
+        assert!(health.is_empty());
+    }
+
+    #[test]
+    fn test_health_window_p95_latency() {
+        let window = Duration::from_secs(60);
+        let now = Instant::now();
+        let mut health = HealthWindow::default();
+        assert_eq!(health.p95_latency(), None);
+
+        for ms in 1..=100 {
+            health.record(
+                now,
+                window,
+                ProviderCallResult::Success(Duration::from_millis(ms)),
+            );
+        }
+        assert_eq!(health.p95_latency(), Some(0.095));
+    }
+
+    #[test]
+    fn test_health_tracker_snapshot() {
+        let tracker = ProviderHealthTracker::new(None);
+        tracker.record(
+            &ProviderKind::Quicknode,
+            "eip155:1",
+            ProviderCallResult::Success(Duration::from_millis(100)),
+        );
+        tracker.record(
+            &ProviderKind::Quicknode,
+            "eip155:10",
+            ProviderCallResult::Failure,
+        );
+
+        let (weights, latencies) = tracker.snapshot();
+        let (chains, provider_availability) = weights.get(&ProviderKind::Quicknode).unwrap();
+        assert_eq!(*provider_availability, Availability::new(1, 1));
+        assert_eq!(
+            chains.get(&ChainId("eip155:1".into())),
+            Some(&Availability::new(1, 0))
+        );
+        assert_eq!(
+            chains.get(&ChainId("eip155:10".into())),
+            Some(&Availability::new(0, 1))
+        );
+
+        let quicknode_latencies = latencies.get(&ProviderKind::Quicknode).unwrap();
+        assert_eq!(
+            quicknode_latencies.get(&ChainId("eip155:1".into())),
+            Some(&0.1)
+        );
+        assert_eq!(quicknode_latencies.get(&ChainId("eip155:10".into())), None);
+    }
+}
@@ -1,5 +1,8 @@
 use {
-    super::{Provider, ProviderKind, RateLimited, RpcProvider, RpcProviderFactory},
+    super::{
+        http_client, rate_limits, Provider, ProviderKind, RateLimited, RpcProvider,
+        RpcProviderFactory,
+    },
     crate::{
         env::HiroConfig,
         error::{RpcError, RpcResult},
@@ -109,7 +112,8 @@ impl HiroProvider {
             .send()
             .await?;
         let status = response.status();
-        let body = response.bytes().await?;
+        let rate_limit_headers = rate_limits::forwarded_headers(response.headers());
+        let body = http_client::read_body(response).await?;
 
         if let Ok(response) = serde_json::from_slice::<jsonrpc::Response>(&body) {
             if response.error.is_some() && status.is_success() {
@@ -121,7 +125,7 @@ impl HiroProvider {
         }
 
         let wrapped_body = self.wrap_response_in_result(&body)?;
-        let mut response = (status, wrapped_body).into_response();
+        let mut response = (status, rate_limit_headers, wrapped_body).into_response();
         response
             .headers_mut()
             .insert("Content-Type", HeaderValue::from_static("application/json"));
@@ -143,7 +147,8 @@ impl HiroProvider {
             .send()
             .await?;
         let status = response.status();
-        let body = response.bytes().await?;
+        let rate_limit_headers = rate_limits::forwarded_headers(response.headers());
+        let body = http_client::read_body(response).await?;
 
         if let Ok(response) = serde_json::from_slice::<jsonrpc::Response>(&body) {
             if response.error.is_some() && status.is_success() {
@@ -155,7 +160,7 @@ impl HiroProvider {
         }
 
         let wrapped_body = self.wrap_response_in_result(&body)?;
-        let mut response = (status, wrapped_body).into_response();
+        let mut response = (status, rate_limit_headers, wrapped_body).into_response();
         response
             .headers_mut()
             .insert("Content-Type", HeaderValue::from_static("application/json"));
@@ -189,7 +194,8 @@ impl HiroProvider {
             .send()
             .await?;
         let status = response.status();
-        let body = response.bytes().await?;
+        let rate_limit_headers = rate_limits::forwarded_headers(response.headers());
+        let body = http_client::read_body(response).await?;
 
         if let Ok(response) = serde_json::from_slice::<jsonrpc::Response>(&body) {
             if response.error.is_some() && status.is_success() {
@@ -201,7 +207,7 @@ impl HiroProvider {
         }
 
         let wrapped_body = self.wrap_response_in_result(&body)?;
-        let mut response = (status, wrapped_body).into_response();
+        let mut response = (status, rate_limit_headers, wrapped_body).into_response();
         response
             .headers_mut()
             .insert("Content-Type", HeaderValue::from_static("application/json"));
@@ -218,7 +224,8 @@ impl HiroProvider {
 
         let response = self.client.get(uri).send().await?;
         let status = response.status();
-        let body = response.bytes().await?;
+        let rate_limit_headers = rate_limits::forwarded_headers(response.headers());
+        let body = http_client::read_body(response).await?;
 
         if let Ok(response) = serde_json::from_slice::<jsonrpc::Response>(&body) {
             if response.error.is_some() && status.is_success() {
@@ -230,7 +237,7 @@ impl HiroProvider {
         }
 
         let wrapped_body = self.wrap_response_in_result(&body)?;
-        let mut response = (status, wrapped_body).into_response();
+        let mut response = (status, rate_limit_headers, wrapped_body).into_response();
         response
             .headers_mut()
             .insert("Content-Type", HeaderValue::from_static("application/json"));
@@ -256,7 +263,8 @@ impl HiroProvider {
             .send()
             .await?;
         let status = response.status();
-        let body = response.bytes().await?;
+        let rate_limit_headers = rate_limits::forwarded_headers(response.headers());
+        let body = http_client::read_body(response).await?;
 
         if let Ok(response) = serde_json::from_slice::<jsonrpc::Response>(&body) {
             if response.error.is_some() && status.is_success() {
@@ -268,7 +276,7 @@ impl HiroProvider {
         }
 
         let wrapped_body = self.wrap_response_in_result(&body)?;
-        let mut response = (status, wrapped_body).into_response();
+        let mut response = (status, rate_limit_headers, wrapped_body).into_response();
         response
             .headers_mut()
             .insert("Content-Type", HeaderValue::from_static("application/json"));
@@ -372,7 +380,7 @@ impl RpcProvider for HiroProvider {
 impl RpcProviderFactory<HiroConfig> for HiroProvider {
     #[tracing::instrument(level = "debug")]
     fn new(provider_config: &HiroConfig) -> Self {
-        let forward_proxy_client = reqwest::Client::new();
+        let forward_proxy_client = http_client::upstream_client(&ProviderKind::Hiro);
         let supported_chains: HashMap<String, String> = provider_config
             .supported_chains
             .iter()
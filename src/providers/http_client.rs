@@ -0,0 +1,278 @@
+use {
+    super::ProviderKind,
+    crate::{
+        error::{RpcError, RpcResult},
+        handlers::proxy::PROVIDER_RESPONSE_MAX_BYTES,
+        Metrics,
+    },
+    bytes::{Bytes, BytesMut},
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::{BTreeMap, HashMap},
+        future::Future,
+        pin::Pin,
+        sync::{Arc, LazyLock, RwLock},
+        task::{Context, Poll},
+        time::{Duration, Instant},
+    },
+    tower::{Layer, Service},
+    tracing::{debug, error},
+};
+
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_TCP_KEEPALIVE: Duration = Duration::from_secs(60);
+
+/// Upstream HTTP clients shared by the providers, one connection pool per
+/// provider kind
+static UPSTREAM_CLIENTS: LazyLock<RwLock<UpstreamClients>> =
+    LazyLock::new(|| RwLock::new(UpstreamClients::default()));
+
+/// Upstream HTTP client settings, unset settings are inherited from the
+/// defaults or left to the `reqwest` defaults
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UpstreamClientConfig {
+    /// TCP and TLS connection establishment timeout
+    pub connect_timeout_ms: Option<u64>,
+    /// Whole request timeout including the response body
+    pub timeout_ms: Option<u64>,
+    /// Maximum amount of the idle connections kept per host
+    pub pool_max_idle_per_host: Option<usize>,
+    /// Idle connections are closed after this timeout
+    pub pool_idle_timeout_secs: Option<u64>,
+    pub tcp_keepalive_secs: Option<u64>,
+    /// Use HTTP/2 without the HTTP/1.1 upgrade, only for the providers known
+    /// to support it. HTTP/2 is negotiated by ALPN otherwise.
+    pub http2_prior_knowledge: Option<bool>,
+    /// Negotiate the gzip, brotli and deflate response compression
+    pub compression: Option<bool>,
+}
+
+impl UpstreamClientConfig {
+    /// Settings overridden by the `other` settings
+    pub fn merge(&self, other: &Self) -> Self {
+        Self {
+            connect_timeout_ms: other.connect_timeout_ms.or(self.connect_timeout_ms),
+            timeout_ms: other.timeout_ms.or(self.timeout_ms),
+            pool_max_idle_per_host: other.pool_max_idle_per_host.or(self.pool_max_idle_per_host),
+            pool_idle_timeout_secs: other.pool_idle_timeout_secs.or(self.pool_idle_timeout_secs),
+            tcp_keepalive_secs: other.tcp_keepalive_secs.or(self.tcp_keepalive_secs),
+            http2_prior_knowledge: other.http2_prior_knowledge.or(self.http2_prior_knowledge),
+            compression: other.compression.or(self.compression),
+        }
+    }
+
+    fn builder(&self, provider: &str) -> reqwest::ClientBuilder {
+        let mut builder = reqwest::Client::builder()
+            .connect_timeout(
+                self.connect_timeout_ms
+                    .map_or(DEFAULT_CONNECT_TIMEOUT, Duration::from_millis),
+            )
+            .tcp_keepalive(
+                self.tcp_keepalive_secs
+                    .map_or(DEFAULT_TCP_KEEPALIVE, Duration::from_secs),
+            )
+            .connector_layer(ConnectionMetricsLayer {
+                provider: Arc::from(provider),
+            });
+        if let Some(timeout_ms) = self.timeout_ms {
+            builder = builder.timeout(Duration::from_millis(timeout_ms));
+        }
+        if let Some(pool_max_idle_per_host) = self.pool_max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(pool_max_idle_per_host);
+        }
+        if let Some(pool_idle_timeout_secs) = self.pool_idle_timeout_secs {
+            builder = builder.pool_idle_timeout(Duration::from_secs(pool_idle_timeout_secs));
+        }
+        if self.http2_prior_knowledge.unwrap_or(false) {
+            builder = builder.http2_prior_knowledge();
+        }
+        if let Some(compression) = self.compression {
+            builder = builder
+                .gzip(compression)
+                .brotli(compression)
+                .deflate(compression);
+        }
+        builder
+    }
+}
+
+#[derive(Debug, Default)]
+struct UpstreamClients {
+    defaults: UpstreamClientConfig,
+    /// Settings per provider name
+    overrides: BTreeMap<String, UpstreamClientConfig>,
+    clients: HashMap<ProviderKind, (UpstreamClientConfig, reqwest::Client)>,
+}
+
+impl UpstreamClients {
+    fn config(&self, settings: &str) -> UpstreamClientConfig {
+        self.overrides.get(settings).map_or_else(
+            || self.defaults.clone(),
+            |config| self.defaults.merge(config),
+        )
+    }
+}
+
+/// Configure the upstream clients defaults and the per provider settings.
+/// The clients with the changed settings are rebuilt on the next
+/// `upstream_client` call, the others keep their connection pools.
+pub fn configure(
+    defaults: UpstreamClientConfig,
+    overrides: &BTreeMap<String, UpstreamClientConfig>,
+) {
+    let Ok(mut clients) = UPSTREAM_CLIENTS.write() else {
+        return;
+    };
+    clients.defaults = defaults;
+    clients.overrides = overrides.clone();
+}
+
+/// Shared upstream HTTP client of the provider
+pub fn upstream_client(provider_kind: &ProviderKind) -> reqwest::Client {
+    upstream_client_with_settings(provider_kind, &provider_kind.to_string())
+}
+
+/// Shared upstream HTTP client of the provider with the settings of the
+/// `settings` provider name, e.g. the catalogue kind of the generic provider
+pub fn upstream_client_with_settings(
+    provider_kind: &ProviderKind,
+    settings: &str,
+) -> reqwest::Client {
+    let Ok(mut clients) = UPSTREAM_CLIENTS.write() else {
+        return reqwest::Client::new();
+    };
+    let config = clients.config(settings);
+    if let Some((client_config, client)) = clients.clients.get(provider_kind) {
+        if *client_config == config {
+            return client.clone();
+        }
+    }
+
+    let client = match config.builder(&provider_kind.to_string()).build() {
+        Ok(client) => client,
+        Err(e) => {
+            error!("Failed to build the {provider_kind} upstream client, using the defaults: {e}");
+            reqwest::Client::new()
+        }
+    };
+    debug!("Built the {provider_kind} upstream client with {config:?}");
+    clients
+        .clients
+        .insert(provider_kind.clone(), (config, client.clone()));
+    client
+}
+
+/// Read the provider response body up to the `PROVIDER_RESPONSE_MAX_BYTES`
+pub async fn read_body(mut response: reqwest::Response) -> RpcResult<Bytes> {
+    if response
+        .content_length()
+        .is_some_and(|length| length > PROVIDER_RESPONSE_MAX_BYTES as u64)
+    {
+        return Err(RpcError::ProviderResponseTooLarge(
+            PROVIDER_RESPONSE_MAX_BYTES,
+        ));
+    }
+    let mut body = BytesMut::new();
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > PROVIDER_RESPONSE_MAX_BYTES {
+            return Err(RpcError::ProviderResponseTooLarge(
+                PROVIDER_RESPONSE_MAX_BYTES,
+            ));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body.freeze())
+}
+
+/// Connector layer recording the new upstream connections of the provider
+/// pool, the requests served without the new connection reuse the pool
+#[derive(Debug, Clone)]
+struct ConnectionMetricsLayer {
+    provider: Arc<str>,
+}
+
+impl<S> Layer<S> for ConnectionMetricsLayer {
+    type Service = ConnectionMetrics<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ConnectionMetrics {
+            inner,
+            provider: self.provider.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ConnectionMetrics<S> {
+    inner: S,
+    provider: Arc<str>,
+}
+
+impl<S, R> Service<R> for ConnectionMetrics<S>
+where
+    S: Service<R>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        let provider = self.provider.clone();
+        let connect = self.inner.call(request);
+        Box::pin(async move {
+            let start = Instant::now();
+            let result = connect.await;
+            Metrics::new().add_upstream_connection(&provider, result.is_ok(), start.elapsed());
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upstream_client_config_merge() {
+        let defaults = UpstreamClientConfig {
+            connect_timeout_ms: Some(1000),
+            pool_max_idle_per_host: Some(16),
+            ..Default::default()
+        };
+        let overrides = toml::from_str::<UpstreamClientConfig>(
+            "pool_max_idle_per_host = 64\nhttp2_prior_knowledge = true",
+        )
+        .unwrap();
+
+        let config = defaults.merge(&overrides);
+        assert_eq!(config.connect_timeout_ms, Some(1000));
+        assert_eq!(config.pool_max_idle_per_host, Some(64));
+        assert_eq!(config.http2_prior_knowledge, Some(true));
+        assert_eq!(config.timeout_ms, None);
+
+        assert!(toml::from_str::<UpstreamClientConfig>("pool_size = 1").is_err());
+    }
+
+    #[test]
+    fn test_provider_overrides() {
+        let clients = UpstreamClients {
+            overrides: BTreeMap::from([(
+                "example".to_string(),
+                UpstreamClientConfig {
+                    timeout_ms: Some(1000),
+                    ..Default::default()
+                },
+            )]),
+            ..Default::default()
+        };
+        assert_eq!(clients.config("example").timeout_ms, Some(1000));
+        assert_eq!(clients.config("examples").timeout_ms, None);
+        assert_eq!(clients.config("example-eip155:1").timeout_ms, None);
+    }
+}
@@ -3,7 +3,8 @@ use {
         error::{RpcError, RpcResult},
         handlers::{fungible_price::FungiblePriceItem, SupportedCurrencies},
         providers::{
-            FungiblePriceProvider, PriceResponseBody, ProviderKind, TokenMetadataCacheProvider,
+            http_client, FungiblePriceProvider, PriceResponseBody, ProviderKind,
+            TokenMetadataCacheProvider,
         },
         utils::crypto,
         Metrics,
@@ -26,7 +27,7 @@ pub struct LifiProvider {
 impl LifiProvider {
     pub fn new(api_key: Option<String>) -> Self {
         let base_api_url = "https://li.quest/v1".to_string();
-        let http_client = reqwest::Client::new();
+        let http_client = http_client::upstream_client(&ProviderKind::Lifi);
         Self {
             provider_kind: ProviderKind::Lifi,
             api_key,
@@ -8,7 +8,7 @@ use {
             providers::{ProvidersResponse, QueryParams as ProvidersQueryParams},
             widget::{QueryParams as WidgetQueryParams, SessionData, WidgetResponse},
         },
-        providers::ProviderKind,
+        providers::{http_client, ProviderKind},
         Metrics,
     },
     async_trait::async_trait,
@@ -53,7 +53,7 @@ impl MeldProvider {
             provider_kind: ProviderKind::Meld,
             api_key,
             api_base_url,
-            http_client: reqwest::Client::new(),
+            http_client: http_client::upstream_client(&ProviderKind::Meld),
         }
     }
 
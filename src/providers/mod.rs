@@ -1,6 +1,7 @@
 use {
     self::coinbase::CoinbaseProvider,
     crate::{
+        chain_config::{self, CatalogueError},
         env::{BalanceProviderConfig, ProviderConfig},
         error::{RpcError, RpcResult},
         handlers::{
@@ -34,7 +35,8 @@ use {
                 },
             },
             portfolio::{PortfolioQueryParams, PortfolioResponseBody},
-            RpcQueryParams, SupportedCurrencies,
+            proxy::retry::BroadcastMode,
+            SupportedCurrencies,
         },
         utils::crypto::{CaipNamespaces, Erc20FunctionType},
         Metrics,
@@ -43,22 +45,33 @@ use {
         primitives::{Address, Bytes, B256, U256},
         rpc::json_rpc::Id,
     },
+    arc_swap::ArcSwap,
     async_trait::async_trait,
-    axum::{extract::ws::WebSocketUpgrade, response::Response},
+    async_tungstenite::{tokio::ConnectStream, WebSocketStream},
+    axum::response::Response,
+    capabilities::{ProviderCapabilities, RequiredCapabilities},
+    circuit_breaker::{CircuitBreaker, CircuitState},
     deadpool_redis::Pool,
+    futures_util::{stream, StreamExt},
+    head_tracker::ProviderHeads,
+    health::{ProviderCallResult, ProviderHealthTracker, WeightsSource},
+    http_client::UpstreamClientConfig,
     hyper::http::HeaderValue,
     mock_alto::{MockAltoProvider, MockAltoUrls},
+    overrides::ProviderOverrides,
     rand::{distributions::WeightedIndex, prelude::Distribution, rngs::OsRng},
+    rate_limits::ProviderRateLimits,
     serde::{Deserialize, Serialize},
     serde_json::Value,
     std::{
-        collections::{HashMap, HashSet},
+        collections::{BTreeMap, HashMap, HashSet},
         fmt::{Debug, Display},
         hash::Hash,
         str::FromStr,
         sync::Arc,
+        time::Duration,
     },
-    tracing::{debug, error, log::warn},
+    tracing::{debug, error, info, log::warn},
     yttrium::chain_abstraction::api::Transaction,
 };
 
@@ -136,6 +149,32 @@ pub fn is_known_rpc_error_message(error_message: &str) -> bool {
         .any(|pattern| error_message.contains(pattern))
 }
 
+/// Checks if a JSON-RPC error message indicates the `eth_getLogs` blocks range
+/// or the results count exceeds the provider limits, so the range should be split.
+pub fn is_logs_range_error_rpc_message(error_message: &str) -> bool {
+    const LOGS_RANGE_ERROR_PATTERNS: &[&str] = &[
+        "query returned more than",
+        "block range too large",
+        "block range is too large",
+        "block range is too wide",
+        "range too large",
+        "range is too large",
+        "exceed maximum block range",
+        "exceeds the range allowed",
+        "log response size exceeded",
+        "response size exceeded",
+        "limited to a",
+        "too many logs",
+        "logs matched by query exceeds",
+        "query timeout exceeded",
+    ];
+
+    let error_message = error_message.to_lowercase();
+    LOGS_RANGE_ERROR_PATTERNS
+        .iter()
+        .any(|pattern| error_message.contains(pattern))
+}
+
 /// Checks if a JSON-RPC error code indicates a server error specific codes.
 pub fn is_internal_error_rpc_code(error_code: i32) -> bool {
     (-32099..=-32000).contains(&error_code)
@@ -149,11 +188,16 @@ mod binance;
 mod blast;
 mod bungee;
 mod callstatic;
+pub mod capabilities;
+pub mod circuit_breaker;
 mod coinbase;
 mod drpc;
 mod dune;
 pub mod generic;
+pub mod head_tracker;
+pub mod health;
 mod hiro;
+pub mod http_client;
 mod lifi;
 mod mantle;
 mod meld;
@@ -163,10 +207,12 @@ mod moonbeam;
 mod morph;
 mod near;
 mod one_inch;
+pub mod overrides;
 mod pimlico;
 mod pokt;
 mod publicnode;
 mod quicknode;
+pub mod rate_limits;
 mod rootstock;
 mod solscan;
 mod sui;
@@ -174,6 +220,7 @@ mod syndica;
 pub mod tenderly;
 mod toncenter;
 mod trongrid;
+pub mod tx_tracker;
 mod unichain;
 mod weights;
 mod wemix;
@@ -193,7 +240,7 @@ pub use {
     callstatic::CallStaticProvider,
     drpc::DrpcProvider,
     dune::DuneProvider,
-    generic::GenericProvider,
+    generic::{GenericProvider, GenericWsProvider},
     hiro::HiroProvider,
     lifi::LifiProvider,
     mantle::MantleProvider,
@@ -233,13 +280,136 @@ pub const WEIGHT_RECALCULATION_EXCLUDED_PROVIDERS: &[ProviderKind] = &[ProviderK
 /// TON sendBoc wrapped method name
 pub const TON_SEND_BOC_METHOD: &str = "ton_sendBoc";
 
+/// Maximum amount of the concurrent providers chain head requests
+const HEAD_TRACKER_CONCURRENCY: usize = 16;
+
 #[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
 pub struct ProvidersConfig {
     pub prometheus_query_url: Option<String>,
     pub prometheus_workspace_header: Option<String>,
+    /// Source of the providers health data for the weights calculation,
+    /// `prometheus` (default) or `local` in-process sliding window
+    pub weights_source: Option<WeightsSource>,
+    /// Local providers health sliding window duration in seconds
+    pub health_window_secs: Option<u64>,
+    /// Amount of consecutive connection errors, timeouts or rate-limited
+    /// responses to open the provider circuit for the chain, 0 disables it
+    pub circuit_breaker_failure_threshold: Option<u32>,
+    /// Open provider circuit cooldown before the probing call in milliseconds
+    pub circuit_breaker_cooldown_ms: Option<u64>,
+    /// Backoff of the rate-limited provider for the chain in milliseconds when
+    /// the provider didn't request it by the headers, 0 disables the backoff
+    pub rate_limit_backoff_ms: Option<u64>,
+    /// Client-side providers budgets as `<provider>:<rps>:<monthly_compute_units>`
+    /// entries, empty limits are not enforced (e.g. `Quicknode:50:`)
+    pub provider_budgets: Option<Vec<String>>,
+
+    /// Enables speculative (hedged) requests to the next provider for the
+    /// idempotent methods when the first provider is slow to respond
+    pub hedging_enabled: Option<bool>,
+    /// Chain latency percentile used as the hedging delay (e.g. 95 for p95)
+    pub hedging_latency_percentile: Option<u8>,
+    /// Upper bound of the hedging delay in milliseconds
+    pub hedging_max_delay_ms: Option<u64>,
+    /// Enables splitting of the batch requests into the cached items and
+    /// upstream chunks, duplicate request ids in a batch are rejected
+    pub batch_splitting_enabled: Option<bool>,
+    /// Maximum amount of the batch items sent upstream in a single request,
+    /// lowered to the providers declared `max_batch_size`
+    pub batch_max_size: Option<usize>,
+    /// Maximum amount of the items in the split batch request
+    pub batch_max_items: Option<usize>,
+    /// Maximum number of the concurrent upstream chunk calls of the split batch
+    pub batch_split_max_concurrency: Option<usize>,
+    /// Enables splitting of the `eth_getLogs` blocks range into the
+    /// concurrently queried sub-ranges when the provider rejects the range
+    pub logs_splitting_enabled: Option<bool>,
+    /// Maximum depth of the `eth_getLogs` range halving, up to 2^depth sub-ranges
+    pub logs_split_max_depth: Option<u32>,
+    /// Maximum number of the concurrent `eth_getLogs` sub-range calls of the request
+    pub logs_split_max_concurrency: Option<usize>,
+    /// JSON-RPC methods allowed for the scope as `<method>[@<scope>]` entries,
+    /// the scope is `project:<id>`, `chain:<caip2>` or `tier:<tier>` and the
+    /// method ending with `*` matches the methods prefix
+    pub method_allowlist: Option<Vec<String>>,
+    /// JSON-RPC methods rejected for the scope in the allowlist entries format
+    pub method_denylist: Option<Vec<String>>,
+    /// Project tier the tier rules are applied with when the project data
+    /// lookup fails, the requests are rejected if not set
+    pub method_access_fallback_tier: Option<String>,
+    /// Private transaction relays as `<caip2>=<relay_url>` entries, the chain
+    /// relays are tried in order before the public providers
+    pub private_tx_relays: Option<Vec<String>>,
+    /// Projects submitting the raw transactions through the private relays
+    /// by default, the `privateTx` query param overrides it
+    pub private_tx_projects: Option<Vec<String>>,
+    /// Window in seconds during which the sessionId requests are pinned to
+    /// the same provider per chain
+    pub sticky_session_window_secs: Option<u64>,
+    /// JSON-RPC methods for which the identical in-flight requests are
+    /// coalesced into a single upstream call
+    pub coalescing_methods: Option<Vec<String>>,
+    /// Enables the background providers chain head tracking and exclusion of
+    /// the providers lagging behind the best provider for the chain
+    pub head_tracker_enabled: Option<bool>,
+    /// Providers chain head polling interval in seconds
+    pub head_tracker_interval_secs: Option<u64>,
+    /// Enables the lifecycle tracking of the transactions submitted through
+    /// the proxy and the transaction status endpoint
+    pub tx_tracker_enabled: Option<bool>,
+    /// Pending transactions status polling interval in seconds
+    pub tx_tracker_interval_secs: Option<u64>,
+    /// Seconds after which the pending transaction unknown to the provider
+    /// is considered dropped
+    pub tx_tracker_drop_timeout_secs: Option<u64>,
+    /// Broadcasting mode of the transactions sending methods,
+    /// `once` (default) or `all` providers concurrently
+    pub write_broadcast_mode: Option<BroadcastMode>,
+    /// Maximum amount of the providers tried for the read methods
+    pub read_retry_budget: Option<usize>,
+    /// Overall deadline of the read methods call including retries in milliseconds
+    pub read_deadline_ms: Option<u64>,
+    /// Maximum amount of the WebSocket upstream reconnection attempts on the
+    /// provider failure, 0 disables the failover
+    pub ws_max_reconnects: Option<u32>,
+    /// Share the upstream `newHeads`, `logs` and `slotSubscribe` WebSocket
+    /// subscriptions between the client sessions
+    pub ws_subscription_hub_enabled: Option<bool>,
+    /// JSON-RPC methods allowed on the WebSocket connections,
+    /// all methods are allowed if not set
+    pub ws_allowed_methods: Option<Vec<String>>,
+    /// Interval of the project quota checks during the WebSocket connection
+    pub ws_quota_check_interval_secs: Option<u64>,
+    /// Accept the WebSocket connections for the chains without WebSocket
+    /// providers through the HTTP providers
+    pub ws_emulation_enabled: Option<bool>,
+    /// Chain head polling interval of the emulated WebSocket subscriptions
+    pub ws_emulation_poll_interval_ms: Option<u64>,
+    /// Maximum amount of the emulated WebSocket subscriptions per session
+    pub ws_emulation_max_subscriptions: Option<usize>,
+    /// Maximum amount of the in-flight proxy calls per emulated WebSocket session
+    pub ws_emulation_max_in_flight_calls: Option<usize>,
+    /// Path of the TOML provider catalogue with the chains and their generic
+    /// providers, replaces the built-in chains config
+    pub catalogue_path: Option<String>,
+    /// Provider catalogue changes polling interval in seconds, the providers
+    /// are reloaded without restart when the catalogue file is modified
+    pub catalogue_reload_interval_secs: Option<u64>,
+    /// Default upstream HTTP clients settings, overridden per provider by the
+    /// catalogue `http_clients` tables
+    pub upstream_connect_timeout_ms: Option<u64>,
+    pub upstream_timeout_ms: Option<u64>,
+    pub upstream_pool_max_idle_per_host: Option<usize>,
+    pub upstream_pool_idle_timeout_secs: Option<u64>,
+    pub upstream_tcp_keepalive_secs: Option<u64>,
+    pub upstream_http2_prior_knowledge: Option<bool>,
+    pub upstream_compression: Option<bool>,
 
     /// Redis address for provider's responses caching
     pub cache_redis_addr: Option<String>,
+    /// Enables the JSON-RPC responses caching in the provider's responses
+    /// Redis in addition to the in-memory cache
+    pub json_rpc_cache_redis_enabled: Option<bool>,
 
     pub pokt_project_id: String,
     pub quicknode_api_tokens: String,
@@ -285,19 +455,136 @@ pub struct ProvidersConfig {
     pub override_bundler_urls: Option<MockAltoUrls>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+impl ProvidersConfig {
+    /// Default upstream HTTP clients settings
+    pub fn upstream_client_config(&self) -> UpstreamClientConfig {
+        UpstreamClientConfig {
+            connect_timeout_ms: self.upstream_connect_timeout_ms,
+            timeout_ms: self.upstream_timeout_ms,
+            pool_max_idle_per_host: self.upstream_pool_max_idle_per_host,
+            pool_idle_timeout_secs: self.upstream_pool_idle_timeout_secs,
+            tcp_keepalive_secs: self.upstream_tcp_keepalive_secs,
+            http2_prior_knowledge: self.upstream_http2_prior_knowledge,
+            compression: self.upstream_compression,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct SupportedChains {
     pub http: HashSet<String>,
     pub ws: HashSet<String>,
 }
 
-pub struct ProviderRepository {
-    pub rpc_supported_chains: SupportedChains,
+/// Current selection state of the RPC provider for the chain
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderStatus {
+    pub provider: String,
+    pub weight: u64,
+    pub circuit_state: CircuitState,
+    /// Remaining rate limit backoff in milliseconds
+    pub backoff_ms: Option<u64>,
+    pub head: Option<u64>,
+    pub lagging: bool,
+    pub disabled: bool,
+    pub pinned: bool,
+    pub capabilities: Option<ProviderCapabilities>,
+}
+
+/// RPC and WebSocket providers with their weights. The set is built as a
+/// whole and swapped atomically on the providers reload.
+#[derive(Default)]
+pub struct RpcProviderSet {
+    pub supported_chains: SupportedChains,
     rpc_providers: HashMap<ProviderKind, Arc<dyn RpcProvider>>,
     rpc_weight_resolver: ChainsWeightResolver,
+    /// Declared capabilities of the RPC providers per chain
+    rpc_capabilities: HashMap<(ProviderKind, String), ProviderCapabilities>,
 
     ws_providers: HashMap<ProviderKind, Arc<dyn RpcWsProvider>>,
     ws_weight_resolver: ChainsWeightResolver,
+}
+
+impl RpcProviderSet {
+    pub fn add_ws_provider<
+        T: RpcProviderFactory<C> + RpcWsProvider + 'static,
+        C: ProviderConfig,
+    >(
+        &mut self,
+        provider_config: C,
+    ) {
+        let ws_provider = T::new(&provider_config);
+        let arc_ws_provider = Arc::new(ws_provider);
+
+        self.ws_providers
+            .insert(provider_config.provider_kind(), arc_ws_provider);
+
+        let provider_kind = provider_config.provider_kind();
+        let supported_ws_chains = provider_config.supported_ws_chains();
+
+        supported_ws_chains
+            .into_iter()
+            .for_each(|(chain_id, (_, weight))| {
+                self.supported_chains.ws.insert(chain_id.clone());
+                self.ws_weight_resolver
+                    .entry(chain_id)
+                    .or_default()
+                    .insert(provider_kind.clone(), weight);
+            });
+    }
+
+    pub fn add_rpc_provider<T: RpcProviderFactory<C> + RpcProvider + 'static, C: ProviderConfig>(
+        &mut self,
+        provider_config: C,
+    ) {
+        let provider = T::new(&provider_config);
+        let arc_provider = Arc::new(provider);
+
+        self.rpc_providers
+            .insert(provider_config.provider_kind(), arc_provider);
+
+        let provider_kind = provider_config.provider_kind();
+        let supported_chains = provider_config.supported_chains();
+
+        supported_chains
+            .into_iter()
+            .for_each(|(chain_id, (_, weight))| {
+                self.supported_chains.http.insert(chain_id.clone());
+                self.rpc_weight_resolver
+                    .entry(chain_id)
+                    .or_default()
+                    .insert(provider_kind.clone(), weight);
+            });
+        debug!("Added provider: {}", provider_kind);
+    }
+
+    /// Declare the RPC provider capabilities for the chain
+    pub fn set_rpc_capabilities(
+        &mut self,
+        provider_kind: ProviderKind,
+        chain_id: String,
+        capabilities: ProviderCapabilities,
+    ) {
+        self.rpc_capabilities
+            .insert((provider_kind, chain_id), capabilities);
+    }
+
+    /// Number of the RPC providers in the set
+    pub fn rpc_providers_count(&self) -> usize {
+        self.rpc_providers.len()
+    }
+
+    /// Number of the WebSocket providers in the set
+    pub fn ws_providers_count(&self) -> usize {
+        self.ws_providers.len()
+    }
+}
+
+pub struct ProviderRepository {
+    /// RPC and WebSocket providers, in-flight requests keep using the set
+    /// they were routed with when it is swapped by the reload
+    rpc_provider_set: ArcSwap<RpcProviderSet>,
 
     balance_supported_namespaces: HashSet<CaipNamespaces>,
     balance_providers: HashMap<ProviderKind, Arc<dyn BalanceProvider>>,
@@ -316,14 +603,25 @@ pub struct ProviderRepository {
     pub simulation_provider: Arc<dyn SimulationProvider>,
 
     pub token_metadata_cache: Arc<dyn TokenMetadataCacheProvider>,
+    /// Redis pool for the provider's responses caching
+    pub cache_redis_pool: Option<Arc<Pool>>,
+    /// Redis pool for the JSON-RPC responses caching if enabled
+    pub json_rpc_cache_redis_pool: Option<Arc<Pool>>,
 
     prometheus_client: Option<prometheus_http_query::Client>,
     prometheus_workspace_header: String,
+    weights_source: WeightsSource,
+    health_tracker: ProviderHealthTracker,
+    circuit_breaker: CircuitBreaker,
+    rate_limits: ProviderRateLimits,
+    provider_heads: ProviderHeads,
+    /// Providers disabled or pinned through the admin API
+    pub overrides: ProviderOverrides,
 }
 
 impl ProviderRepository {
     #[allow(clippy::new_without_default)]
-    pub fn new(config: &ProvidersConfig) -> Self {
+    pub fn new(config: &ProvidersConfig, rpc_provider_set: RpcProviderSet) -> Self {
         let prometheus_client =
             config
                 .prometheus_query_url
@@ -456,19 +754,28 @@ impl ProviderRepository {
         let token_metadata_cache = Arc::new(TokenMetadataCache::new(redis_pool.clone()));
 
         Self {
-            rpc_supported_chains: SupportedChains {
-                http: HashSet::new(),
-                ws: HashSet::new(),
-            },
-            rpc_providers: HashMap::new(),
-            rpc_weight_resolver: HashMap::new(),
-            ws_providers: HashMap::new(),
-            ws_weight_resolver: HashMap::new(),
+            rpc_provider_set: ArcSwap::from_pointee(rpc_provider_set),
             balance_supported_namespaces: HashSet::new(),
             balance_providers: HashMap::new(),
             balance_weight_resolver: HashMap::new(),
             prometheus_client,
             prometheus_workspace_header,
+            weights_source: config.weights_source.unwrap_or_default(),
+            health_tracker: ProviderHealthTracker::new(
+                config.health_window_secs.map(Duration::from_secs),
+            ),
+            circuit_breaker: CircuitBreaker::new(
+                config.circuit_breaker_failure_threshold,
+                config
+                    .circuit_breaker_cooldown_ms
+                    .map(Duration::from_millis),
+            ),
+            rate_limits: ProviderRateLimits::new(
+                config.rate_limit_backoff_ms.map(Duration::from_millis),
+                config.provider_budgets.as_deref().unwrap_or_default(),
+            ),
+            provider_heads: ProviderHeads::default(),
+            overrides: ProviderOverrides::default(),
             history_providers,
             portfolio_provider,
             coinbase_pay_provider: coinbase_pay_provider.clone(),
@@ -480,16 +787,77 @@ impl ProviderRepository {
             chain_orchestrator_provider,
             simulation_provider,
             token_metadata_cache,
+            cache_redis_pool: redis_pool.clone(),
+            json_rpc_cache_redis_pool: redis_pool
+                .filter(|_| config.json_rpc_cache_redis_enabled.unwrap_or(false)),
         }
     }
 
+    /// Largest batch size accepted by the chain providers declaring the
+    /// maximum batch size, `None` if any chain provider doesn't limit it
+    pub fn rpc_max_batch_size(&self, chain_id: &str) -> Option<usize> {
+        let rpc_provider_set = self.rpc_provider_set.load();
+        rpc_provider_set
+            .rpc_weight_resolver
+            .get(chain_id)?
+            .keys()
+            .map(|provider_kind| {
+                rpc_provider_set
+                    .rpc_capabilities
+                    .get(&(provider_kind.clone(), chain_id.to_string()))
+                    .and_then(|capabilities| capabilities.max_batch_size)
+            })
+            .collect::<Option<Vec<_>>>()?
+            .into_iter()
+            .max()
+    }
+
+    /// Largest `eth_getLogs` blocks range accepted by the chain providers
+    /// declaring the maximum range, `None` if any chain provider doesn't
+    /// limit it
+    pub fn rpc_max_logs_block_range(&self, chain_id: &str) -> Option<u64> {
+        let rpc_provider_set = self.rpc_provider_set.load();
+        rpc_provider_set
+            .rpc_weight_resolver
+            .get(chain_id)?
+            .keys()
+            .map(|provider_kind| {
+                rpc_provider_set
+                    .rpc_capabilities
+                    .get(&(provider_kind.clone(), chain_id.to_string()))
+                    .and_then(|capabilities| capabilities.max_logs_block_range)
+            })
+            .collect::<Option<Vec<_>>>()?
+            .into_iter()
+            .max()
+    }
+
     #[tracing::instrument(skip(self), level = "debug")]
     pub fn get_rpc_provider_for_chain_id(
         &self,
         chain_id: &str,
         max_providers: usize,
     ) -> Result<Vec<Arc<dyn RpcProvider>>, RpcError> {
-        let Some(providers) = self.rpc_weight_resolver.get(chain_id) else {
+        self.get_rpc_provider_for_chain_id_with_capabilities(
+            chain_id,
+            max_providers,
+            &RequiredCapabilities::default(),
+        )
+    }
+
+    /// Weighted RPC providers for the chain having the required capabilities.
+    /// Providers declaring the required capabilities are preferred for the
+    /// constraining requirements, the providers without the declared
+    /// capabilities are used only if there are no such providers.
+    #[tracing::instrument(skip(self), level = "debug")]
+    pub fn get_rpc_provider_for_chain_id_with_capabilities(
+        &self,
+        chain_id: &str,
+        max_providers: usize,
+        required: &RequiredCapabilities,
+    ) -> Result<Vec<Arc<dyn RpcProvider>>, RpcError> {
+        let rpc_provider_set = self.rpc_provider_set.load();
+        let Some(providers) = rpc_provider_set.rpc_weight_resolver.get(chain_id) else {
             return Err(RpcError::UnsupportedChain(chain_id.to_string()));
         };
 
@@ -497,10 +865,89 @@ impl ProviderRepository {
             return Err(RpcError::UnsupportedChain(chain_id.to_string()));
         }
 
+        let capable: Vec<_> = providers
+            .keys()
+            .map(|provider_kind| {
+                if required.is_empty() {
+                    return true;
+                }
+                rpc_provider_set
+                    .rpc_capabilities
+                    .get(&(provider_kind.clone(), chain_id.to_string()))
+                    .is_none_or(|capabilities| capabilities.satisfies(required))
+            })
+            .collect();
+        let declared_capable: Vec<_> = providers
+            .keys()
+            .zip(&capable)
+            .map(|(provider_kind, capable)| {
+                *capable
+                    && rpc_provider_set
+                        .rpc_capabilities
+                        .contains_key(&(provider_kind.clone(), chain_id.to_string()))
+            })
+            .collect();
+        // Declared providers are preferred only for the requirements that
+        // some of the providers may not satisfy
+        let declared = providers.keys().filter_map(|provider_kind| {
+            rpc_provider_set
+                .rpc_capabilities
+                .get(&(provider_kind.clone(), chain_id.to_string()))
+        });
+        let capable = if required.is_constraining(declared) && declared_capable.contains(&true) {
+            declared_capable
+        } else {
+            capable
+        };
+        if !capable.contains(&true) {
+            return Err(RpcError::NoCapableProviders {
+                chain_id: chain_id.to_string(),
+                capabilities: required.to_string(),
+            });
+        }
+
+        // Pinned provider is the only provider used for the chain
+        if let Some(provider) = self
+            .overrides
+            .pinned(chain_id)
+            .filter(|provider_kind| providers.contains_key(provider_kind))
+            .and_then(|provider_kind| rpc_provider_set.rpc_providers.get(&provider_kind).cloned())
+        {
+            return Ok(vec![provider]);
+        }
+
+        // Disabled providers, providers with the open circuit and rate-limited
+        // providers are excluded from the sampling
+        let permitted: Vec<_> = providers
+            .iter()
+            .zip(&capable)
+            .map(|((provider_kind, _), capable)| {
+                *capable
+                    && !self.overrides.is_disabled(provider_kind, chain_id)
+                    && self.rate_limits.is_call_permitted(provider_kind, chain_id)
+                    && self.circuit_breaker.is_selectable(provider_kind, chain_id)
+            })
+            .collect();
+        // Providers lagging behind the best provider for the chain are excluded
+        // as well, unless there are no other permitted providers left
+        let lagging: Vec<_> = providers
+            .iter()
+            .map(|(provider_kind, _)| self.provider_heads.is_lagging(provider_kind, chain_id))
+            .collect();
+        let exclude_lagging = permitted
+            .iter()
+            .zip(&lagging)
+            .any(|(permitted, lagging)| *permitted && !lagging);
         let weights: Vec<_> = providers
-            .values()
-            .map(|weight| weight.value())
-            .map(|w| w.max(1))
+            .iter()
+            .zip(permitted.iter().zip(&lagging))
+            .map(|((_, weight), (permitted, lagging))| {
+                if *permitted && !(exclude_lagging && *lagging) {
+                    weight.value().max(1)
+                } else {
+                    0
+                }
+            })
             .collect();
         let non_zero_weight_providers = weights.iter().filter(|&x| *x > 0).count();
         let keys = providers.keys().cloned().collect::<Vec<_>>();
@@ -528,11 +975,15 @@ impl ProviderRepository {
                             }
                         };
 
-                        self.rpc_providers.get(provider).cloned().ok_or_else(|| {
-                            RpcError::WeightedProvidersIndex(format!(
+                        rpc_provider_set
+                            .rpc_providers
+                            .get(provider)
+                            .cloned()
+                            .ok_or_else(|| {
+                                RpcError::WeightedProvidersIndex(format!(
                                 "Provider not found during the weighted index check: {provider}"
                             ))
-                        })
+                            })
                     })
                     .collect::<Result<Vec<_>, _>>()?;
                 Ok(providers_result)
@@ -658,12 +1109,44 @@ impl ProviderRepository {
 
     #[tracing::instrument(skip(self), level = "debug")]
     pub fn get_ws_provider_for_chain_id(&self, chain_id: &str) -> Option<Arc<dyn RpcWsProvider>> {
-        let providers = self.ws_weight_resolver.get(chain_id)?;
+        self.get_ws_provider_for_chain_id_excluding(chain_id, &[])
+    }
+
+    /// Weighted WebSocket provider for the chain other than the excluded ones
+    pub fn get_ws_provider_for_chain_id_excluding(
+        &self,
+        chain_id: &str,
+        excluded: &[ProviderKind],
+    ) -> Option<Arc<dyn RpcWsProvider>> {
+        let rpc_provider_set = self.rpc_provider_set.load();
+        let providers = rpc_provider_set.ws_weight_resolver.get(chain_id)?;
         if providers.is_empty() {
             return None;
         }
 
-        let weights: Vec<_> = providers.values().map(|weight| weight.value()).collect();
+        if let Some(provider) = self
+            .overrides
+            .pinned(chain_id)
+            .filter(|provider_kind| {
+                providers.contains_key(provider_kind) && !excluded.contains(provider_kind)
+            })
+            .and_then(|provider_kind| rpc_provider_set.ws_providers.get(&provider_kind).cloned())
+        {
+            return Some(provider);
+        }
+
+        let weights: Vec<_> = providers
+            .iter()
+            .map(|(provider_kind, weight)| {
+                if excluded.contains(provider_kind)
+                    || self.overrides.is_disabled(provider_kind, chain_id)
+                {
+                    0
+                } else {
+                    weight.value()
+                }
+            })
+            .collect();
         let keys = providers.keys().cloned().collect::<Vec<_>>();
         match WeightedIndex::new(weights) {
             Ok(dist) => {
@@ -672,7 +1155,7 @@ impl ProviderRepository {
                     .get(random)
                     .expect("Failed to get random provider: out of index");
 
-                self.ws_providers.get(provider).cloned()
+                rpc_provider_set.ws_providers.get(provider).cloned()
             }
             Err(e) => {
                 warn!("Failed to create weighted index: {e}");
@@ -681,56 +1164,100 @@ impl ProviderRepository {
         }
     }
 
-    pub fn add_ws_provider<
-        T: RpcProviderFactory<C> + RpcWsProvider + 'static,
-        C: ProviderConfig,
-    >(
-        &mut self,
-        provider_config: C,
-    ) {
-        let ws_provider = T::new(&provider_config);
-        let arc_ws_provider = Arc::new(ws_provider);
-
-        self.ws_providers
-            .insert(provider_config.provider_kind(), arc_ws_provider);
+    /// Selection state of the RPC providers per chain
+    pub fn rpc_providers_status(&self) -> BTreeMap<String, Vec<ProviderStatus>> {
+        let rpc_provider_set = self.rpc_provider_set.load();
+        rpc_provider_set
+            .rpc_weight_resolver
+            .iter()
+            .map(|(chain_id, providers)| {
+                let pinned = self.overrides.pinned(chain_id);
+                let mut statuses = providers
+                    .iter()
+                    .map(|(provider_kind, weight)| ProviderStatus {
+                        provider: provider_kind.to_string(),
+                        weight: weight.value(),
+                        circuit_state: self.circuit_breaker.state(provider_kind, chain_id),
+                        backoff_ms: self
+                            .rate_limits
+                            .backoff_remaining(provider_kind, chain_id)
+                            .map(|backoff| backoff.as_millis() as u64),
+                        head: self.provider_heads.get(provider_kind, chain_id),
+                        lagging: self.provider_heads.is_lagging(provider_kind, chain_id),
+                        disabled: self.overrides.is_disabled(provider_kind, chain_id),
+                        pinned: pinned.as_ref() == Some(provider_kind),
+                        capabilities: rpc_provider_set
+                            .rpc_capabilities
+                            .get(&(provider_kind.clone(), chain_id.clone()))
+                            .cloned(),
+                    })
+                    .collect::<Vec<_>>();
+                statuses.sort_by(|a, b| a.provider.cmp(&b.provider));
+                (chain_id.clone(), statuses)
+            })
+            .collect()
+    }
 
-        let provider_kind = provider_config.provider_kind();
-        let supported_ws_chains = provider_config.supported_ws_chains();
+    /// Whether the RPC or WebSocket provider is configured for the chain
+    pub fn is_chain_provider(&self, provider_kind: &ProviderKind, chain_id: &str) -> bool {
+        let rpc_provider_set = self.rpc_provider_set.load();
+        [
+            &rpc_provider_set.rpc_weight_resolver,
+            &rpc_provider_set.ws_weight_resolver,
+        ]
+        .iter()
+        .any(|resolver| {
+            resolver
+                .get(chain_id)
+                .is_some_and(|providers| providers.contains_key(provider_kind))
+        })
+    }
 
-        supported_ws_chains
-            .into_iter()
-            .for_each(|(chain_id, (_, weight))| {
-                self.rpc_supported_chains.ws.insert(chain_id.clone());
-                self.ws_weight_resolver
-                    .entry(chain_id)
-                    .or_default()
-                    .insert(provider_kind.clone(), weight);
-            });
+    /// Chains supported by the RPC and WebSocket providers
+    pub fn rpc_supported_chains(&self) -> SupportedChains {
+        self.rpc_provider_set.load().supported_chains.clone()
     }
 
-    pub fn add_rpc_provider<T: RpcProviderFactory<C> + RpcProvider + 'static, C: ProviderConfig>(
-        &mut self,
-        provider_config: C,
-    ) {
-        let provider = T::new(&provider_config);
-        let arc_provider = Arc::new(provider);
+    /// Current RPC and WebSocket providers set
+    pub fn rpc_provider_set(&self) -> Arc<RpcProviderSet> {
+        self.rpc_provider_set.load_full()
+    }
 
-        self.rpc_providers
-            .insert(provider_config.provider_kind(), arc_provider);
+    /// Atomically replace the RPC and WebSocket providers, returns the
+    /// previous set which is released once the in-flight requests complete
+    pub fn swap_rpc_provider_set(&self, rpc_provider_set: RpcProviderSet) -> Arc<RpcProviderSet> {
+        self.rpc_provider_set.swap(Arc::new(rpc_provider_set))
+    }
 
-        let provider_kind = provider_config.provider_kind();
-        let supported_chains = provider_config.supported_chains();
+    /// Rebuild the RPC and WebSocket providers from the provider catalogue and
+    /// swap them in, the current providers are kept if the catalogue fails to
+    /// load
+    pub fn reload_rpc_providers(
+        &self,
+        config: &ProvidersConfig,
+        metrics: &crate::Metrics,
+    ) -> Result<(), CatalogueError> {
+        let rpc_provider_set =
+            match chain_config::load_active_config(config.catalogue_path.as_deref()) {
+                Ok(chain_config) => crate::init_rpc_providers(config, &chain_config),
+                Err(e) => {
+                    error!("Failed to reload providers, keeping the current providers: {e}");
+                    metrics.add_providers_reload(false);
+                    return Err(e);
+                }
+            };
+        let (rpc_providers, ws_providers) = (
+            rpc_provider_set.rpc_providers_count(),
+            rpc_provider_set.ws_providers_count(),
+        );
+        self.swap_rpc_provider_set(rpc_provider_set);
 
-        supported_chains
-            .into_iter()
-            .for_each(|(chain_id, (_, weight))| {
-                self.rpc_supported_chains.http.insert(chain_id.clone());
-                self.rpc_weight_resolver
-                    .entry(chain_id)
-                    .or_default()
-                    .insert(provider_kind.clone(), weight);
-            });
-        debug!("Added provider: {}", provider_kind);
+        info!(
+            "Providers reloaded: {rpc_providers} RPC providers, {ws_providers} WebSocket providers"
+        );
+        metrics.add_providers_reload(true);
+        metrics.record_rpc_providers_count(rpc_providers, ws_providers);
+        Ok(())
     }
 
     pub fn add_balance_provider<
@@ -762,9 +1289,82 @@ impl ProviderRepository {
         debug!("Balance provider added: {}", provider_kind);
     }
 
+    /// Poll the chain heads of the RPC providers, record them as metrics and
+    /// mark the providers lagging behind the best provider for the chain
+    #[tracing::instrument(skip_all, level = "debug")]
+    pub async fn update_heads(&self, metrics: &crate::Metrics) {
+        debug!("Updating providers chain heads");
+        let rpc_provider_set = self.rpc_provider_set.load_full();
+        let rpc_providers = &rpc_provider_set.rpc_providers;
+        // Backed off, over budget and open circuit providers are not probed
+        let probes = rpc_provider_set
+            .rpc_weight_resolver
+            .iter()
+            .filter(|(chain_id, _)| head_tracker::is_head_tracking_supported(chain_id))
+            .flat_map(|(chain_id, providers)| {
+                providers.keys().filter_map(move |provider_kind| {
+                    let provider = rpc_providers.get(provider_kind)?.clone();
+                    Some((chain_id.clone(), provider_kind.clone(), provider))
+                })
+            })
+            .filter(|(chain_id, provider_kind, _)| {
+                self.try_consume_background_call(metrics, provider_kind, chain_id, 1)
+            })
+            .collect::<Vec<_>>();
+
+        let heads = stream::iter(probes)
+            .map(|(chain_id, provider_kind, provider)| async move {
+                let head = head_tracker::fetch_head(provider.as_ref(), &chain_id).await;
+                (chain_id, provider_kind, head)
+            })
+            .buffer_unordered(HEAD_TRACKER_CONCURRENCY)
+            .collect::<Vec<_>>()
+            .await;
+
+        let mut chains_heads: HashMap<String, Vec<(ProviderKind, u64)>> = HashMap::new();
+        for (chain_id, provider_kind, head) in heads {
+            match head {
+                Some(head) => {
+                    self.provider_heads.record(&provider_kind, &chain_id, head);
+                    metrics.record_provider_head(&provider_kind, chain_id.clone(), head);
+                    chains_heads
+                        .entry(chain_id)
+                        .or_default()
+                        .push((provider_kind, head));
+                }
+                None => debug!("Failed to get the chain head of {provider_kind} for {chain_id}"),
+            }
+        }
+
+        for (chain_id, heads) in chains_heads {
+            for (provider_kind, lag) in head_tracker::head_lags(&heads) {
+                metrics.record_provider_head_lag(&provider_kind, chain_id.clone(), lag);
+            }
+            let lagging =
+                head_tracker::lagging_providers(&heads, head_tracker::max_head_lag(&chain_id));
+            if !lagging.is_empty() {
+                warn!("Providers lagging behind the chain head for {chain_id}: {lagging:?}");
+            }
+            self.provider_heads.set_lagging(&chain_id, &heads, &lagging);
+        }
+    }
+
     #[tracing::instrument(skip_all, level = "debug")]
     pub async fn update_weights(&self, metrics: &crate::Metrics) {
         debug!("Updating weights");
+        let rpc_provider_set = self.rpc_provider_set.load_full();
+        self.circuit_breaker.record_states(metrics);
+
+        if self.weights_source == WeightsSource::Local {
+            let (parsed_weights, parsed_latencies) = self.health_tracker.snapshot();
+            weights::update_values(
+                &rpc_provider_set.rpc_weight_resolver,
+                parsed_weights,
+                &parsed_latencies,
+            );
+            weights::record_values(&rpc_provider_set.rpc_weight_resolver, metrics);
+            return;
+        }
 
         let Some(prometheus_client) = &self.prometheus_client else {
             debug!("Prometheus client not configured, skipping weight update");
@@ -779,6 +1379,24 @@ impl ProviderRepository {
             return;
         };
 
+        // Providers p95 latencies are optional for the weights calculation
+        // and the weights are calculated by the availability only if missing
+        let parsed_latencies = match prometheus_client
+            .query(
+                "max by (provider, chain_id) \
+                 (http_external_latency_tracker{quantile=\"0.95\", endpoint=\"\"})",
+            )
+            .header("host", header_value.clone())
+            .get()
+            .await
+        {
+            Ok(data) => weights::parse_latencies(data),
+            Err(e) => {
+                warn!("Failed to get providers latencies from prometheus: {e}");
+                HashMap::new()
+            }
+        };
+
         match prometheus_client
             .query("round(increase(provider_status_code_counter_total[3h]))")
             .header("host", header_value)
@@ -787,8 +1405,12 @@ impl ProviderRepository {
         {
             Ok(data) => {
                 let parsed_weights = weights::parse_weights(data);
-                weights::update_values(&self.rpc_weight_resolver, parsed_weights);
-                weights::record_values(&self.rpc_weight_resolver, metrics);
+                weights::update_values(
+                    &rpc_provider_set.rpc_weight_resolver,
+                    parsed_weights,
+                    &parsed_latencies,
+                );
+                weights::record_values(&rpc_provider_set.rpc_weight_resolver, metrics);
             }
             Err(e) => {
                 warn!("Failed to update weights from prometheus: {e}");
@@ -796,6 +1418,90 @@ impl ProviderRepository {
         }
     }
 
+    /// Record the RPC provider call outcome for the local health tracking
+    /// and the provider circuit breaker
+    pub fn record_rpc_call_result(
+        &self,
+        metrics: &crate::Metrics,
+        provider_kind: &ProviderKind,
+        chain_id: &str,
+        result: ProviderCallResult,
+    ) {
+        self.health_tracker.record(provider_kind, chain_id, result);
+        self.circuit_breaker
+            .record(metrics, provider_kind, chain_id, result);
+    }
+
+    /// Whether the selected RPC provider can be called for the chain, takes
+    /// the circuit breaker probing call slot if the circuit is half-open
+    pub fn try_acquire_rpc_call(&self, provider_kind: &ProviderKind, chain_id: &str) -> bool {
+        self.circuit_breaker
+            .try_acquire_call(provider_kind, chain_id)
+    }
+
+    /// Put the rate-limited provider into the backoff window for the chain
+    pub fn record_rate_limited(
+        &self,
+        metrics: &crate::Metrics,
+        provider_kind: &ProviderKind,
+        chain_id: &str,
+        requested_backoff: Option<Duration>,
+    ) {
+        if let Some(backoff) = self
+            .rate_limits
+            .back_off(provider_kind, chain_id, requested_backoff)
+        {
+            debug!("Provider {provider_kind} is rate-limited for {chain_id}, backing off for {backoff:?}");
+            metrics.add_provider_rate_limit_backoff(provider_kind, chain_id.to_string());
+        }
+    }
+
+    /// Whether the background call to the provider, e.g. the chain head probe,
+    /// is permitted by the provider backoff, the client-side budget and the
+    /// circuit breaker. The permitted call consumes the provider budget.
+    pub fn try_consume_background_call(
+        &self,
+        metrics: &crate::Metrics,
+        provider_kind: &ProviderKind,
+        chain_id: &str,
+        compute_units: u64,
+    ) -> bool {
+        if !self.rate_limits.is_call_permitted(provider_kind, chain_id)
+            || !self.circuit_breaker.is_selectable(provider_kind, chain_id)
+        {
+            return false;
+        }
+        self.rate_limits
+            .consume(metrics, provider_kind, compute_units);
+        true
+    }
+
+    /// Consume the client-side provider budget by the call compute units
+    pub fn consume_provider_budget(
+        &self,
+        metrics: &crate::Metrics,
+        provider_kind: &ProviderKind,
+        compute_units: u64,
+    ) {
+        self.rate_limits
+            .consume(metrics, provider_kind, compute_units);
+    }
+
+    /// Record the latest observed provider block height for the chain
+    pub fn record_provider_head(&self, provider_kind: &ProviderKind, chain_id: &str, block: u64) {
+        self.provider_heads.record(provider_kind, chain_id, block);
+    }
+
+    /// Best observed block height of the chain providers
+    pub fn chain_head(&self, chain_id: &str) -> Option<u64> {
+        self.provider_heads.chain_head(chain_id)
+    }
+
+    /// Latest observed provider block height for the chain
+    pub fn get_provider_head(&self, provider_kind: &ProviderKind, chain_id: &str) -> Option<u64> {
+        self.provider_heads.get(provider_kind, chain_id)
+    }
+
     #[tracing::instrument(skip(self), level = "debug")]
     pub fn get_rpc_provider_by_provider_id(
         &self,
@@ -811,7 +1517,8 @@ impl ProviderRepository {
         &self,
         provider_kind: &ProviderKind,
     ) -> Option<Arc<dyn RpcProvider>> {
-        self.rpc_providers.get(provider_kind).cloned()
+        let rpc_provider_set = self.rpc_provider_set.load();
+        rpc_provider_set.rpc_providers.get(provider_kind).cloned()
     }
 }
 
@@ -959,11 +1666,8 @@ pub trait RpcProviderFactory<T: ProviderConfig>: Provider {
 
 #[async_trait]
 pub trait RpcWsProvider: Provider {
-    async fn proxy(
-        &self,
-        ws: WebSocketUpgrade,
-        query_params: RpcQueryParams,
-    ) -> RpcResult<Response>;
+    /// Connect to the provider's WebSocket endpoint for the chain
+    async fn connect(&self, chain_id: &str) -> RpcResult<WebSocketStream<ConnectStream>>;
 }
 
 const MAX_PRIORITY: u64 = 100;
@@ -1365,6 +2069,90 @@ mod tests {
         assert!(Priority::from_str("").is_err());
     }
 
+    fn generic_provider_set(chain_id: &str) -> RpcProviderSet {
+        let mut providers = RpcProviderSet::default();
+        providers.add_rpc_provider::<GenericProvider, crate::env::GenericConfig>(
+            crate::env::GenericConfig {
+                caip2: chain_id.to_string(),
+                name: "Test chain".to_string(),
+                kind: Some("example".to_string()),
+                provider: crate::chain_config::ProviderConfig {
+                    url: "https://rpc.example.com".to_string(),
+                    priority: Priority::Normal,
+                    kind: Some("example".to_string()),
+                    ws_url: None,
+                    headers: Default::default(),
+                    auth: None,
+                    rate_limit: None,
+                    capabilities: None,
+                },
+            },
+        );
+        providers
+    }
+
+    fn providers_config(catalogue_path: &str) -> ProvidersConfig {
+        let mut config = serde_json::Map::new();
+        for key in [
+            "pokt_project_id",
+            "quicknode_api_tokens",
+            "zerion_api_key",
+            "pimlico_api_key",
+            "solscan_api_v2_token",
+            "bungee_api_key",
+            "tenderly_api_key",
+            "tenderly_account_id",
+            "tenderly_project_id",
+            "dune_sim_api_key",
+            "syndica_api_key",
+            "allnodes_api_key",
+            "meld_api_key",
+            "meld_api_url",
+            "callstatic_api_key",
+            "blast_api_key",
+        ] {
+            config.insert(key.to_string(), "test".into());
+        }
+        config.insert("catalogue_path".to_string(), catalogue_path.into());
+        serde_json::from_value(config.into()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_reload_rpc_providers() {
+        let recorder = metrics_exporter_prometheus::PrometheusBuilder::new().build_recorder();
+        let prometheus = recorder.handle();
+        let metrics = crate::Metrics::new();
+        let repository = ProviderRepository::new(
+            &providers_config("missing.toml"),
+            generic_provider_set("eip155:999999"),
+        );
+        // Snapshot of the in-flight request routed before the reload
+        let in_flight = repository.rpc_provider_set();
+
+        // The current providers are kept if the catalogue fails to load
+        let reloaded = metrics::with_local_recorder(&recorder, || {
+            repository.reload_rpc_providers(&providers_config("missing.toml"), &metrics)
+        });
+        assert!(reloaded.is_err());
+        assert!(Arc::ptr_eq(&repository.rpc_provider_set(), &in_flight));
+        assert!(prometheus
+            .render()
+            .contains(r#"providers_reload_counter{status="failure"}"#));
+
+        let catalogue = concat!(env!("CARGO_MANIFEST_DIR"), "/providers.example.toml");
+        metrics::with_local_recorder(&recorder, || {
+            repository.reload_rpc_providers(&providers_config(catalogue), &metrics)
+        })
+        .unwrap();
+        let current = repository.rpc_supported_chains();
+        assert!(current.http.contains("eip155:10"));
+        assert!(!current.http.contains("eip155:999999"));
+        assert!(in_flight.supported_chains.http.contains("eip155:999999"));
+        assert!(prometheus
+            .render()
+            .contains(r#"providers_reload_counter{status="success"}"#));
+    }
+
     #[test]
     fn test_is_node_error_rpc_message() {
         let rate_limited_messages = vec![
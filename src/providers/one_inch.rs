@@ -20,8 +20,8 @@ use {
             SupportedCurrencies,
         },
         providers::{
-            ConversionProvider, FungiblePriceProvider, PriceResponseBody, ProviderKind,
-            TokenMetadataCacheProvider,
+            http_client, ConversionProvider, FungiblePriceProvider, PriceResponseBody,
+            ProviderKind, TokenMetadataCacheProvider,
         },
         utils::crypto,
         Metrics,
@@ -48,7 +48,7 @@ pub struct OneInchProvider {
 impl OneInchProvider {
     pub fn new(api_key: String, referrer: Option<String>) -> Self {
         let base_api_url = "https://api.1inch.dev".to_string();
-        let http_client = reqwest::Client::new();
+        let http_client = http_client::upstream_client(&ProviderKind::OneInch);
         Self {
             provider_kind: ProviderKind::OneInch,
             api_key,
@@ -0,0 +1,82 @@
+use {
+    super::ProviderKind,
+    std::{
+        collections::{HashMap, HashSet},
+        sync::RwLock,
+    },
+};
+
+/// Operator overrides of the RPC providers selection set through the admin API.
+/// Disabled providers are skipped for the chain, a pinned provider is the
+/// only provider used for the chain while it is supported.
+#[derive(Debug, Default)]
+pub struct ProviderOverrides {
+    disabled: RwLock<HashSet<(ProviderKind, String)>>,
+    pinned: RwLock<HashMap<String, ProviderKind>>,
+}
+
+impl ProviderOverrides {
+    /// Disable the provider for the chain, returns `false` if already disabled
+    pub fn disable(&self, provider_kind: &ProviderKind, chain_id: &str) -> bool {
+        let Ok(mut disabled) = self.disabled.write() else {
+            return false;
+        };
+        disabled.insert((provider_kind.clone(), chain_id.to_string()))
+    }
+
+    /// Enable the disabled provider for the chain, returns `false` if the
+    /// provider was not disabled
+    pub fn enable(&self, provider_kind: &ProviderKind, chain_id: &str) -> bool {
+        let Ok(mut disabled) = self.disabled.write() else {
+            return false;
+        };
+        disabled.remove(&(provider_kind.clone(), chain_id.to_string()))
+    }
+
+    pub fn is_disabled(&self, provider_kind: &ProviderKind, chain_id: &str) -> bool {
+        self.disabled
+            .read()
+            .is_ok_and(|disabled| disabled.contains(&(provider_kind.clone(), chain_id.to_string())))
+    }
+
+    /// Pin the provider for the chain replacing the previously pinned one
+    pub fn pin(&self, provider_kind: &ProviderKind, chain_id: &str) {
+        if let Ok(mut pinned) = self.pinned.write() {
+            pinned.insert(chain_id.to_string(), provider_kind.clone());
+        }
+    }
+
+    /// Unpin the chain provider, returns the unpinned provider
+    pub fn unpin(&self, chain_id: &str) -> Option<ProviderKind> {
+        self.pinned.write().ok()?.remove(chain_id)
+    }
+
+    pub fn pinned(&self, chain_id: &str) -> Option<ProviderKind> {
+        self.pinned.read().ok()?.get(chain_id).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provider_overrides() {
+        let overrides = ProviderOverrides::default();
+        let provider = ProviderKind::Publicnode;
+
+        assert!(overrides.disable(&provider, "eip155:1"));
+        assert!(!overrides.disable(&provider, "eip155:1"));
+        assert!(overrides.is_disabled(&provider, "eip155:1"));
+        assert!(!overrides.is_disabled(&provider, "eip155:10"));
+        assert!(overrides.enable(&provider, "eip155:1"));
+        assert!(!overrides.enable(&provider, "eip155:1"));
+        assert!(!overrides.is_disabled(&provider, "eip155:1"));
+
+        overrides.pin(&provider, "eip155:1");
+        overrides.pin(&ProviderKind::Drpc, "eip155:1");
+        assert_eq!(overrides.pinned("eip155:1"), Some(ProviderKind::Drpc));
+        assert_eq!(overrides.unpin("eip155:1"), Some(ProviderKind::Drpc));
+        assert_eq!(overrides.pinned("eip155:1"), None);
+    }
+}
@@ -1,5 +1,8 @@
 use {
-    super::{Provider, ProviderKind, RateLimited, RpcProvider, RpcProviderFactory},
+    super::{
+        http_client, rate_limits, Provider, ProviderKind, RateLimited, RpcProvider,
+        RpcProviderFactory,
+    },
     crate::{
         env::PoktConfig,
         error::{RpcError, RpcResult},
@@ -65,7 +68,8 @@ impl RpcProvider for PoktProvider {
             .send()
             .await?;
         let status = response.status();
-        let body = response.bytes().await?;
+        let rate_limit_headers = rate_limits::forwarded_headers(response.headers());
+        let body = http_client::read_body(response).await?;
 
         if status.is_success() || status.is_client_error() {
             if let Ok(response) = serde_json::from_slice::<jsonrpc::Response>(&body) {
@@ -106,7 +110,7 @@ impl RpcProvider for PoktProvider {
             }
         }
 
-        let mut response = (status, body).into_response();
+        let mut response = (status, rate_limit_headers, body).into_response();
         response
             .headers_mut()
             .insert("Content-Type", HeaderValue::from_static("application/json"));
@@ -117,7 +121,7 @@ impl RpcProvider for PoktProvider {
 impl RpcProviderFactory<PoktConfig> for PoktProvider {
     #[tracing::instrument(level = "debug")]
     fn new(provider_config: &PoktConfig) -> Self {
-        let forward_proxy_client = reqwest::Client::new();
+        let forward_proxy_client = http_client::upstream_client(&ProviderKind::Pokt);
         let supported_chains: HashMap<String, String> = provider_config
             .supported_chains
             .iter()
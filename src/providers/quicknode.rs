@@ -1,17 +1,16 @@
 use {
     super::{
-        Provider, ProviderKind, RateLimited, RpcProvider, RpcProviderFactory, RpcQueryParams,
-        RpcWsProvider, TON_SEND_BOC_METHOD,
+        http_client, rate_limits, Provider, ProviderKind, RateLimited, RpcProvider,
+        RpcProviderFactory, RpcWsProvider, TON_SEND_BOC_METHOD,
     },
     crate::{
         env::QuicknodeConfig,
         error::{RpcError, RpcResult},
         json_rpc::{JsonRpcRequest, JsonRpcResult},
-        ws,
     },
     async_trait::async_trait,
+    async_tungstenite::{tokio::ConnectStream, WebSocketStream},
     axum::{
-        extract::ws::WebSocketUpgrade,
         http::HeaderValue,
         response::{IntoResponse, Response},
     },
@@ -19,7 +18,6 @@ use {
     serde::{Deserialize, Serialize},
     std::collections::HashMap,
     tracing::debug,
-    wc::metrics::{future_metrics, FutureExt},
 };
 
 #[derive(Debug, Serialize)]
@@ -235,7 +233,7 @@ impl QuicknodeProvider {
             .send()
             .await?;
         let status = response.status();
-        let body = response.bytes().await?;
+        let body = http_client::read_body(response).await?;
 
         // Handle the TON API error response which is HTTP 500 with the error structure response
         if status == http::StatusCode::INTERNAL_SERVER_ERROR
@@ -296,7 +294,7 @@ impl QuicknodeProvider {
             .send()
             .await?;
         let status = response.status();
-        let body = response.bytes().await?;
+        let body = http_client::read_body(response).await?;
 
         // If provider responded with a TON-shaped error body on server error status,
         // convert it to a Bad Request for the RPC layer; otherwise continue.
@@ -398,8 +396,9 @@ impl RpcProvider for QuicknodeProvider {
             .send()
             .await?;
         let status = response.status();
-        let body = response.bytes().await?;
-        let mut response = (status, body).into_response();
+        let rate_limit_headers = rate_limits::forwarded_headers(response.headers());
+        let body = http_client::read_body(response).await?;
+        let mut response = (status, rate_limit_headers, body).into_response();
         response
             .headers_mut()
             .insert("Content-Type", HeaderValue::from_static("application/json"));
@@ -410,7 +409,7 @@ impl RpcProvider for QuicknodeProvider {
 impl RpcProviderFactory<QuicknodeConfig> for QuicknodeProvider {
     #[tracing::instrument(level = "debug")]
     fn new(provider_config: &QuicknodeConfig) -> Self {
-        let forward_proxy_client = reqwest::Client::new();
+        let forward_proxy_client = http_client::upstream_client(&ProviderKind::Quicknode);
         let supported_chains: HashMap<String, String> = provider_config
             .supported_chains
             .iter()
@@ -448,13 +447,7 @@ impl Provider for QuicknodeWsProvider {
 #[async_trait]
 impl RpcWsProvider for QuicknodeWsProvider {
     #[tracing::instrument(skip_all, fields(provider = %self.provider_kind()), level = "debug")]
-    async fn proxy(
-        &self,
-        ws: WebSocketUpgrade,
-        query_params: RpcQueryParams,
-    ) -> RpcResult<Response> {
-        let chain_id = &query_params.chain_id;
-        let project_id = query_params.project_id;
+    async fn connect(&self, chain_id: &str) -> RpcResult<WebSocketStream<ConnectStream>> {
         let token = &self
             .supported_chains
             .get(chain_id)
@@ -470,11 +463,7 @@ impl RpcWsProvider for QuicknodeWsProvider {
         let (websocket_provider, _) = async_tungstenite::tokio::connect_async(uri)
             .await
             .map_err(|e| RpcError::WebSocketError(e.to_string()))?;
-
-        Ok(ws.on_upgrade(move |socket| {
-            ws::proxy(project_id, socket, websocket_provider)
-                .with_metrics(future_metrics!("ws_proxy_task", "name" => "quicknode"))
-        }))
+        Ok(websocket_provider)
     }
 }
 
@@ -0,0 +1,437 @@
+use {
+    super::ProviderKind,
+    crate::Metrics,
+    chrono::{DateTime, Datelike, Utc},
+    hyper::http::{header::RETRY_AFTER, HeaderMap},
+    std::{
+        collections::HashMap,
+        sync::Mutex,
+        time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+    },
+    tracing::log::warn,
+};
+
+const DEFAULT_BACKOFF: Duration = Duration::from_secs(10);
+const MAX_BACKOFF: Duration = Duration::from_secs(5 * 60);
+
+/// Reset header values greater than this are the unix timestamps instead of
+/// the amount of seconds until the reset
+const RESET_TIMESTAMP_THRESHOLD: u64 = 1_000_000_000;
+
+/// Approximate compute units cost of the heavy methods, other methods cost 1
+const METHOD_COMPUTE_UNITS: &[(&str, u64)] = &[
+    ("eth_getLogs", 5),
+    ("eth_estimateGas", 2),
+    ("eth_call", 2),
+    ("eth_getBlockReceipts", 5),
+    ("debug_traceTransaction", 10),
+    ("debug_traceCall", 10),
+    ("trace_block", 10),
+    ("trace_transaction", 10),
+];
+
+/// Response headers of the providers rate limits, the providers are passing
+/// them through to the proxy to honour the requested backoff
+pub fn forwarded_headers(headers: &HeaderMap) -> HeaderMap {
+    headers
+        .iter()
+        .filter(|(name, _)| is_rate_limit_header(name.as_str()))
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect()
+}
+
+/// Remove the provider rate limits headers, they are not relevant to the
+/// clients of the proxy
+pub fn strip_headers(headers: &mut HeaderMap) {
+    let names = headers
+        .keys()
+        .filter(|name| is_rate_limit_header(name.as_str()))
+        .cloned()
+        .collect::<Vec<_>>();
+    for name in names {
+        headers.remove(name);
+    }
+}
+
+fn is_rate_limit_header(name: &str) -> bool {
+    name == RETRY_AFTER.as_str()
+        || name.starts_with("x-ratelimit-")
+        || name.starts_with("ratelimit-")
+}
+
+/// Backoff requested by the provider response headers. The `Retry-After`
+/// header is used first, then the rate limit reset header when the rate
+/// limited response or the remaining limit is exhausted.
+pub fn requested_backoff(headers: &HeaderMap, is_rate_limited: bool) -> Option<Duration> {
+    let now = SystemTime::now();
+    if is_rate_limited {
+        if let Some(retry_after) = header_str(headers, RETRY_AFTER.as_str())
+            .and_then(|value| parse_retry_after(value, now))
+        {
+            return Some(retry_after);
+        }
+    }
+
+    let exhausted = ["x-ratelimit-remaining", "ratelimit-remaining"]
+        .iter()
+        .filter_map(|name| header_str(headers, name))
+        .any(|value| {
+            value
+                .trim()
+                .parse::<f64>()
+                .is_ok_and(|remaining| remaining <= 0.0)
+        });
+    if !is_rate_limited && !exhausted {
+        return None;
+    }
+    ["x-ratelimit-reset", "ratelimit-reset"]
+        .iter()
+        .filter_map(|name| header_str(headers, name))
+        .find_map(|value| parse_reset(value, now))
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+/// `Retry-After` is either the delay in seconds or the HTTP date
+fn parse_retry_after(value: &str, now: SystemTime) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    SystemTime::from(date).duration_since(now).ok()
+}
+
+/// Reset is either the delay in seconds (possibly fractional) or the unix
+/// timestamp in seconds
+fn parse_reset(value: &str, now: SystemTime) -> Option<Duration> {
+    let value = value.trim().trim_end_matches('s').parse::<f64>().ok()?;
+    if !value.is_finite() || value < 0.0 {
+        return None;
+    }
+    if value as u64 > RESET_TIMESTAMP_THRESHOLD {
+        return (UNIX_EPOCH + Duration::from_secs(value as u64))
+            .duration_since(now)
+            .ok();
+    }
+    Some(Duration::from_secs_f64(value))
+}
+
+/// Approximate compute units cost of the JSON-RPC method
+pub fn method_compute_units(method: &str) -> u64 {
+    METHOD_COMPUTE_UNITS
+        .iter()
+        .find(|(name, _)| *name == method)
+        .map_or(1, |(_, units)| *units)
+}
+
+/// Client-side request budget of the provider
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProviderBudgetConfig {
+    pub requests_per_second: Option<u32>,
+    pub monthly_compute_units: Option<u64>,
+}
+
+impl ProviderBudgetConfig {
+    /// Parse the `<provider>:<rps>:<monthly_compute_units>` budget entry,
+    /// empty limits are not enforced
+    pub fn parse(entry: &str) -> Option<(ProviderKind, Self)> {
+        let mut parts = entry.trim().split(':');
+        let provider_kind = ProviderKind::from_str(parts.next()?)?;
+        let requests_per_second = parse_limit(parts.next().unwrap_or_default())?;
+        let monthly_compute_units = parse_limit(parts.next().unwrap_or_default())?;
+        if parts.next().is_some() {
+            return None;
+        }
+        Some((
+            provider_kind,
+            Self {
+                requests_per_second,
+                monthly_compute_units,
+            },
+        ))
+    }
+}
+
+/// Empty limit is `Some(None)`, invalid limit is `None`
+fn parse_limit<T: std::str::FromStr>(value: &str) -> Option<Option<T>> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Some(None);
+    }
+    value.parse().ok().map(Some)
+}
+
+#[derive(Debug)]
+struct ProviderBudget {
+    config: ProviderBudgetConfig,
+    tokens: f64,
+    refilled_at: Instant,
+    /// UTC year and month of the used compute units
+    month: (i32, u32),
+    compute_units: u64,
+}
+
+impl ProviderBudget {
+    fn new(config: ProviderBudgetConfig) -> Self {
+        Self {
+            config,
+            tokens: config.requests_per_second.unwrap_or_default() as f64,
+            refilled_at: Instant::now(),
+            month: current_month(),
+            compute_units: 0,
+        }
+    }
+
+    fn refill(&mut self, now: Instant, month: (i32, u32)) {
+        if let Some(rps) = self.config.requests_per_second {
+            let elapsed = now.saturating_duration_since(self.refilled_at);
+            self.tokens = (self.tokens + elapsed.as_secs_f64() * rps as f64).min(rps as f64);
+            self.refilled_at = now;
+        }
+        if self.month != month {
+            self.month = month;
+            self.compute_units = 0;
+        }
+    }
+
+    fn is_available(&self) -> bool {
+        self.config
+            .requests_per_second
+            .is_none_or(|_| self.tokens >= 1.0)
+            && self
+                .config
+                .monthly_compute_units
+                .is_none_or(|limit| self.compute_units < limit)
+    }
+
+    fn consume(&mut self, compute_units: u64) {
+        if self.config.requests_per_second.is_some() {
+            self.tokens = (self.tokens - 1.0).max(0.0);
+        }
+        self.compute_units = self.compute_units.saturating_add(compute_units);
+    }
+}
+
+fn current_month() -> (i32, u32) {
+    let now = Utc::now();
+    (now.year(), now.month())
+}
+
+/// Providers rate limits awareness.
+/// The rate-limited provider is put into the backoff window for the chain
+/// requested by the `Retry-After` or rate limit reset headers and skipped
+/// during the providers selection until the window is passed. The optional
+/// client-side budgets of the requests per second and monthly compute units
+/// are enforced per provider. Budgets are tracked in-memory per instance.
+#[derive(Debug)]
+pub struct ProviderRateLimits {
+    default_backoff: Duration,
+    backoffs: Mutex<HashMap<(ProviderKind, String), Instant>>,
+    budgets: HashMap<ProviderKind, Mutex<ProviderBudget>>,
+}
+
+impl ProviderRateLimits {
+    pub fn new(default_backoff: Option<Duration>, budgets: &[String]) -> Self {
+        let budgets = budgets
+            .iter()
+            .filter_map(|entry| {
+                let budget = ProviderBudgetConfig::parse(entry);
+                if budget.is_none() {
+                    warn!("Skipping invalid provider budget entry: {entry}");
+                }
+                budget
+            })
+            .map(|(provider_kind, config)| (provider_kind, Mutex::new(ProviderBudget::new(config))))
+            .collect();
+        Self {
+            default_backoff: default_backoff.unwrap_or(DEFAULT_BACKOFF),
+            backoffs: Mutex::new(HashMap::new()),
+            budgets,
+        }
+    }
+
+    /// Put the provider into the backoff window for the chain, the default
+    /// backoff is used if the provider didn't request it.
+    /// Returns the applied backoff, `None` if the backoff is disabled.
+    pub fn back_off(
+        &self,
+        provider_kind: &ProviderKind,
+        chain_id: &str,
+        requested: Option<Duration>,
+    ) -> Option<Duration> {
+        let backoff = requested.unwrap_or(self.default_backoff).min(MAX_BACKOFF);
+        if backoff.is_zero() {
+            return None;
+        }
+        let Ok(mut backoffs) = self.backoffs.lock() else {
+            return None;
+        };
+        let until = Instant::now() + backoff;
+        let entry = backoffs
+            .entry((provider_kind.clone(), chain_id.to_string()))
+            .or_insert(until);
+        *entry = (*entry).max(until);
+        Some(backoff)
+    }
+
+    /// Remaining backoff of the provider for the chain
+    pub fn backoff_remaining(
+        &self,
+        provider_kind: &ProviderKind,
+        chain_id: &str,
+    ) -> Option<Duration> {
+        let Ok(mut backoffs) = self.backoffs.lock() else {
+            return None;
+        };
+        let key = (provider_kind.clone(), chain_id.to_string());
+        let remaining = backoffs
+            .get(&key)?
+            .checked_duration_since(Instant::now())
+            .filter(|remaining| !remaining.is_zero());
+        if remaining.is_none() {
+            backoffs.remove(&key);
+        }
+        remaining
+    }
+
+    /// Whether the provider is not backed off for the chain and has the
+    /// remaining client-side budget
+    pub fn is_call_permitted(&self, provider_kind: &ProviderKind, chain_id: &str) -> bool {
+        self.backoff_remaining(provider_kind, chain_id).is_none() && self.has_budget(provider_kind)
+    }
+
+    fn has_budget(&self, provider_kind: &ProviderKind) -> bool {
+        let Some(budget) = self.budgets.get(provider_kind) else {
+            return true;
+        };
+        let Ok(mut budget) = budget.lock() else {
+            return true;
+        };
+        budget.refill(Instant::now(), current_month());
+        budget.is_available()
+    }
+
+    /// Consume the provider budget by the call
+    pub fn consume(&self, metrics: &Metrics, provider_kind: &ProviderKind, compute_units: u64) {
+        let Some(budget) = self.budgets.get(provider_kind) else {
+            return;
+        };
+        let Ok(mut budget) = budget.lock() else {
+            return;
+        };
+        budget.refill(Instant::now(), current_month());
+        budget.consume(compute_units);
+        if budget.config.monthly_compute_units.is_some() {
+            metrics.record_provider_monthly_compute_units(provider_kind, budget.compute_units);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, hyper::http::HeaderValue};
+
+    #[test]
+    fn test_requested_backoff() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(requested_backoff(&headers, true), None);
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("30"));
+        assert_eq!(
+            requested_backoff(&headers, true),
+            Some(Duration::from_secs(30))
+        );
+        // Retry-After is only honoured for the rate-limited response
+        assert_eq!(requested_backoff(&headers, false), None);
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("0"));
+        headers.insert("x-ratelimit-reset", HeaderValue::from_static("1.5"));
+        assert_eq!(
+            requested_backoff(&headers, false),
+            Some(Duration::from_millis(1500))
+        );
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("10"));
+        assert_eq!(requested_backoff(&headers, false), None);
+
+        let now = SystemTime::now();
+        let reset = now + Duration::from_secs(120);
+        let timestamp = reset.duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let backoff = parse_reset(&timestamp.to_string(), now).unwrap();
+        assert!(backoff <= Duration::from_secs(120) && backoff > Duration::from_secs(115));
+
+        let date = DateTime::<Utc>::from(reset).to_rfc2822();
+        let backoff = parse_retry_after(&date, now).unwrap();
+        assert!(backoff <= Duration::from_secs(120) && backoff > Duration::from_secs(115));
+    }
+
+    #[test]
+    fn test_forwarded_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("1"));
+        headers.insert("x-ratelimit-limit", HeaderValue::from_static("100"));
+        headers.insert("content-length", HeaderValue::from_static("10"));
+
+        let mut forwarded = forwarded_headers(&headers);
+        assert_eq!(forwarded.len(), 2);
+        strip_headers(&mut forwarded);
+        assert!(forwarded.is_empty());
+    }
+
+    #[test]
+    fn test_provider_backoff() {
+        let rate_limits = ProviderRateLimits::new(None, &[]);
+        let provider = ProviderKind::Publicnode;
+
+        assert!(rate_limits.is_call_permitted(&provider, "eip155:1"));
+        assert_eq!(
+            rate_limits.back_off(&provider, "eip155:1", Some(Duration::from_secs(3600))),
+            Some(MAX_BACKOFF)
+        );
+        assert!(!rate_limits.is_call_permitted(&provider, "eip155:1"));
+        assert!(rate_limits.is_call_permitted(&provider, "eip155:10"));
+
+        let rate_limits = ProviderRateLimits::new(Some(Duration::ZERO), &[]);
+        assert_eq!(rate_limits.back_off(&provider, "eip155:1", None), None);
+        assert!(rate_limits.is_call_permitted(&provider, "eip155:1"));
+    }
+
+    #[test]
+    fn test_provider_budget() {
+        assert_eq!(
+            ProviderBudgetConfig::parse("Quicknode:50:"),
+            Some((
+                ProviderKind::Quicknode,
+                ProviderBudgetConfig {
+                    requests_per_second: Some(50),
+                    monthly_compute_units: None,
+                }
+            ))
+        );
+        assert_eq!(ProviderBudgetConfig::parse("Unknown:50:100"), None);
+        assert_eq!(ProviderBudgetConfig::parse("Quicknode:fast"), None);
+
+        let mut budget = ProviderBudget::new(ProviderBudgetConfig {
+            requests_per_second: None,
+            monthly_compute_units: Some(10),
+        });
+        assert!(budget.is_available());
+        budget.consume(method_compute_units("eth_getLogs") * 2);
+        assert!(!budget.is_available());
+        budget.refill(Instant::now(), (budget.month.0 + 1, budget.month.1));
+        assert!(budget.is_available());
+
+        let mut budget = ProviderBudget::new(ProviderBudgetConfig {
+            requests_per_second: Some(1),
+            monthly_compute_units: None,
+        });
+        assert!(budget.is_available());
+        budget.consume(1);
+        assert!(!budget.is_available());
+        budget.refill(budget.refilled_at + Duration::from_secs(1), budget.month);
+        assert!(budget.is_available());
+    }
+}
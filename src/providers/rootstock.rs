@@ -1,5 +1,8 @@
 use {
-    super::{Provider, ProviderKind, RateLimited, RpcProvider, RpcProviderFactory},
+    super::{
+        http_client, rate_limits, Provider, ProviderKind, RateLimited, RpcProvider,
+        RpcProviderFactory,
+    },
     crate::{
         env::RootstockConfig,
         error::{RpcError, RpcResult},
@@ -61,7 +64,8 @@ impl RpcProvider for RootstockProvider {
             .send()
             .await?;
         let status = response.status();
-        let body = response.bytes().await?;
+        let rate_limit_headers = rate_limits::forwarded_headers(response.headers());
+        let body = http_client::read_body(response).await?;
 
         if let Ok(response) = serde_json::from_slice::<jsonrpc::Response>(&body) {
             if response.error.is_some() && status.is_success() {
@@ -72,7 +76,7 @@ impl RpcProvider for RootstockProvider {
             }
         }
 
-        let mut response = (status, body).into_response();
+        let mut response = (status, rate_limit_headers, body).into_response();
         response
             .headers_mut()
             .insert("Content-Type", HeaderValue::from_static("application/json"));
@@ -83,7 +87,7 @@ impl RpcProvider for RootstockProvider {
 impl RpcProviderFactory<RootstockConfig> for RootstockProvider {
     #[tracing::instrument(level = "debug")]
     fn new(provider_config: &RootstockConfig) -> Self {
-        let forward_proxy_client = reqwest::Client::new();
+        let forward_proxy_client = http_client::upstream_client(&ProviderKind::Rootstock);
         let supported_chains: HashMap<String, String> = provider_config
             .supported_chains
             .iter()
@@ -19,7 +19,9 @@ use {
                 HistoryTransactionURLItem,
             },
         },
-        providers::{BalanceProviderFactory, ProviderKind, TokenMetadataCacheProvider},
+        providers::{
+            http_client, BalanceProviderFactory, ProviderKind, TokenMetadataCacheProvider,
+        },
         storage::error::StorageError,
         utils::crypto::{CaipNamespaces, SOLANA_NATIVE_TOKEN_ADDRESS},
         Metrics,
@@ -90,7 +92,7 @@ impl SolScanProvider {
         Self {
             provider_kind: ProviderKind::SolScan,
             api_v2_token,
-            http_client: reqwest::Client::new(),
+            http_client: http_client::upstream_client(&ProviderKind::SolScan),
             redis_caching_pool,
         }
     }
@@ -502,7 +504,7 @@ impl BalanceProviderFactory<SolScanConfig> for SolScanProvider {
         Self {
             provider_kind: ProviderKind::SolScan,
             api_v2_token: provider_config.api_key.clone(),
-            http_client: reqwest::Client::new(),
+            http_client: http_client::upstream_client(&ProviderKind::SolScan),
             redis_caching_pool: cache,
         }
     }
@@ -1,23 +1,21 @@
 use {
     super::{
-        Provider, ProviderKind, RateLimited, RpcProvider, RpcProviderFactory, RpcQueryParams,
-        RpcWsProvider,
+        http_client, rate_limits, Provider, ProviderKind, RateLimited, RpcProvider,
+        RpcProviderFactory, RpcWsProvider,
     },
     crate::{
         env::SyndicaConfig,
         error::{RpcError, RpcResult},
-        ws,
     },
     async_trait::async_trait,
+    async_tungstenite::{tokio::ConnectStream, WebSocketStream},
     axum::{
-        extract::ws::WebSocketUpgrade,
         http::HeaderValue,
         response::{IntoResponse, Response},
     },
     hyper::http,
     std::collections::HashMap,
     tracing::debug,
-    wc::metrics::{future_metrics, FutureExt},
 };
 
 #[derive(Debug)]
@@ -65,7 +63,8 @@ impl RpcProvider for SyndicaProvider {
             .send()
             .await?;
         let status = response.status();
-        let body = response.bytes().await?;
+        let rate_limit_headers = rate_limits::forwarded_headers(response.headers());
+        let body = http_client::read_body(response).await?;
 
         if let Ok(response) = serde_json::from_slice::<jsonrpc::Response>(&body) {
             if response.error.is_some() && status.is_success() {
@@ -76,7 +75,7 @@ impl RpcProvider for SyndicaProvider {
             }
         }
 
-        let mut response = (status, body).into_response();
+        let mut response = (status, rate_limit_headers, body).into_response();
         response
             .headers_mut()
             .insert("Content-Type", HeaderValue::from_static("application/json"));
@@ -87,7 +86,7 @@ impl RpcProvider for SyndicaProvider {
 impl RpcProviderFactory<SyndicaConfig> for SyndicaProvider {
     #[tracing::instrument(level = "debug")]
     fn new(provider_config: &SyndicaConfig) -> Self {
-        let forward_proxy_client = reqwest::Client::new();
+        let forward_proxy_client = http_client::upstream_client(&ProviderKind::Syndica);
         let supported_chains: HashMap<String, String> = provider_config
             .supported_chains
             .iter()
@@ -125,26 +124,17 @@ impl Provider for SyndicaWsProvider {
 #[async_trait]
 impl RpcWsProvider for SyndicaWsProvider {
     #[tracing::instrument(skip_all, fields(provider = %self.provider_kind()), level = "debug")]
-    async fn proxy(
-        &self,
-        ws: WebSocketUpgrade,
-        query_params: RpcQueryParams,
-    ) -> RpcResult<Response> {
+    async fn connect(&self, chain_id: &str) -> RpcResult<WebSocketStream<ConnectStream>> {
         let base_uri = &self
             .supported_chains
-            .get(&query_params.chain_id)
+            .get(chain_id)
             .ok_or(RpcError::ChainNotFound)?;
 
-        let project_id = query_params.project_id;
         let uri = format!("{}/api-key/{}", base_uri, self.api_key);
         let (websocket_provider, _) = async_tungstenite::tokio::connect_async(uri)
             .await
             .map_err(|e| RpcError::WebSocketError(e.to_string()))?;
-
-        Ok(ws.on_upgrade(move |socket| {
-            ws::proxy(project_id, socket, websocket_provider)
-                .with_metrics(future_metrics!("ws_proxy_task", "name" => "syndica"))
-        }))
+        Ok(websocket_provider)
     }
 }
 
@@ -1,7 +1,7 @@
 use {
     crate::{
         error::RpcError,
-        providers::{ProviderKind, SimulationProvider},
+        providers::{http_client, ProviderKind, SimulationProvider},
         storage::error::StorageError,
         utils::crypto::{disassemble_caip2, Erc20FunctionType},
         Metrics,
@@ -116,7 +116,7 @@ impl TenderlyProvider {
     ) -> Self {
         let base_api_url =
             format!("https://api.tenderly.co/api/v1/account/{account_slug}/project/{project_slug}");
-        let http_client = reqwest::Client::new();
+        let http_client = http_client::upstream_client(&ProviderKind::Tenderly);
         Self {
             provider_kind: ProviderKind::Tenderly,
             api_key,
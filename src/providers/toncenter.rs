@@ -1,7 +1,7 @@
 use {
     super::{
-        HistoryProvider, Provider, ProviderKind, RateLimited, RpcProvider, RpcProviderFactory,
-        TokenMetadataCacheProvider, TON_SEND_BOC_METHOD,
+        http_client, rate_limits, HistoryProvider, Provider, ProviderKind, RateLimited,
+        RpcProvider, RpcProviderFactory, TokenMetadataCacheProvider, TON_SEND_BOC_METHOD,
     },
     crate::{
         env::ToncenterV2Config,
@@ -93,7 +93,7 @@ impl ToncenterBalanceProvider {
             provider_kind: ProviderKind::Toncenter,
             api_url,
             api_key,
-            http_client: reqwest::Client::new(),
+            http_client: http_client::upstream_client(&ProviderKind::Toncenter),
         }
     }
 
@@ -459,9 +459,9 @@ impl RpcProvider for ToncenterApiProvider {
             .send()
             .await?;
         let status = response.status();
-        let body = response.bytes().await?;
-
-        let mut response = (status, body).into_response();
+        let rate_limit_headers = rate_limits::forwarded_headers(response.headers());
+        let body = http_client::read_body(response).await?;
+        let mut response = (status, rate_limit_headers, body).into_response();
         response.headers_mut().insert(
             "Content-Type",
             axum::http::HeaderValue::from_static("application/json"),
@@ -480,7 +480,7 @@ impl RpcProviderFactory<ToncenterV2Config> for ToncenterApiProvider {
             .collect();
         ToncenterApiProvider {
             api_key: provider_config.api_key.clone(),
-            http_client: reqwest::Client::new(),
+            http_client: http_client::upstream_client(&ProviderKind::Toncenter),
             supported_chains,
         }
     }
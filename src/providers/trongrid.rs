@@ -1,5 +1,8 @@
 use {
-    super::{Provider, ProviderKind, RateLimited, RpcProvider, RpcProviderFactory},
+    super::{
+        http_client, rate_limits, Provider, ProviderKind, RateLimited, RpcProvider,
+        RpcProviderFactory,
+    },
     crate::{
         env::TrongridConfig,
         error::{RpcError, RpcResult},
@@ -175,7 +178,7 @@ impl TrongridProvider {
             .send()
             .await?;
         let status = response.status();
-        let body = response.bytes().await?;
+        let body = http_client::read_body(response).await?;
 
         if let Ok(response) = serde_json::from_slice::<jsonrpc::Response>(&body) {
             if response.error.is_some() && status.is_success() {
@@ -230,7 +233,8 @@ impl RpcProvider for TrongridProvider {
             .send()
             .await?;
         let status = response.status();
-        let body = response.bytes().await?;
+        let rate_limit_headers = rate_limits::forwarded_headers(response.headers());
+        let body = http_client::read_body(response).await?;
 
         if let Ok(response) = serde_json::from_slice::<jsonrpc::Response>(&body) {
             if response.error.is_some() && status.is_success() {
@@ -241,7 +245,7 @@ impl RpcProvider for TrongridProvider {
             }
         }
 
-        let mut response = (status, body).into_response();
+        let mut response = (status, rate_limit_headers, body).into_response();
         response
             .headers_mut()
             .insert("Content-Type", HeaderValue::from_static("application/json"));
@@ -252,7 +256,7 @@ impl RpcProvider for TrongridProvider {
 impl RpcProviderFactory<TrongridConfig> for TrongridProvider {
     #[tracing::instrument(level = "debug")]
     fn new(provider_config: &TrongridConfig) -> Self {
-        let forward_proxy_client = reqwest::Client::new();
+        let forward_proxy_client = http_client::upstream_client(&ProviderKind::Trongrid);
         let supported_chains: HashMap<String, String> = provider_config
             .supported_chains
             .iter()
@@ -0,0 +1,501 @@
+use {
+    super::{rate_limits::method_compute_units, ProviderRepository, ProvidersConfig, RpcProvider},
+    crate::Metrics,
+    axum::body::{to_bytes, Bytes},
+    ethers::{
+        types::transaction::eip2718::TypedTransaction,
+        utils::{keccak256, rlp::Rlp},
+    },
+    futures_util::{stream, StreamExt},
+    serde::Serialize,
+    serde_json::{json, Value},
+    std::{
+        collections::{HashMap, HashSet},
+        sync::RwLock,
+        time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+    },
+    tokio::time::timeout,
+    tracing::{debug, warn},
+};
+
+/// Pending transactions not found by the provider after this timeout are dropped
+const DEFAULT_DROP_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+/// Finished transactions are kept for the status requests for this duration
+const FINISHED_RETENTION: Duration = Duration::from_secs(60 * 60);
+const MAX_TRACKED_TRANSACTIONS: usize = 100_000;
+const TRACKER_CONCURRENCY: usize = 16;
+const TRACKER_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+const TRACKER_RESPONSE_MAX_BYTES: usize = 1024 * 1024;
+
+/// Lifecycle status of the tracked transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TxStatus {
+    Pending,
+    Included,
+    /// Other transaction with the same sender nonce was included
+    Replaced,
+    /// Transaction is not known to the provider after the drop timeout
+    Dropped,
+}
+
+impl TxStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Included => "included",
+            Self::Replaced => "replaced",
+            Self::Dropped => "dropped",
+        }
+    }
+}
+
+/// Transaction submitted through the proxy
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackedTransaction {
+    pub hash: String,
+    pub chain_id: String,
+    #[serde(skip)]
+    pub project_id: String,
+    /// Providers or private relays the transaction was submitted to
+    pub providers: Vec<String>,
+    pub from: Option<String>,
+    pub nonce: Option<u64>,
+    pub status: TxStatus,
+    /// Submission unix timestamp in seconds
+    pub submitted_at: u64,
+    pub block_number: Option<u64>,
+    /// Execution status of the included transaction
+    pub success: Option<bool>,
+    /// Time from the submission until the inclusion was observed
+    pub time_to_inclusion_ms: Option<u64>,
+    pub replaced_by: Option<String>,
+    #[serde(skip)]
+    submitted: Instant,
+    #[serde(skip)]
+    finished: Option<Instant>,
+}
+
+/// Hash, sender and nonce of the signed raw transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedTransaction {
+    pub hash: String,
+    pub from: Option<String>,
+    pub nonce: Option<u64>,
+}
+
+/// Decode the hex encoded signed raw transaction. The sender and nonce are
+/// `None` for the transaction types not supported by the decoder.
+pub fn decode_raw_transaction(raw_tx: &str) -> Option<DecodedTransaction> {
+    let bytes = hex::decode(raw_tx.strip_prefix("0x").unwrap_or(raw_tx)).ok()?;
+    if bytes.is_empty() {
+        return None;
+    }
+    let signed = TypedTransaction::decode_signed(&Rlp::new(&bytes)).ok();
+    Some(DecodedTransaction {
+        hash: format!("0x{}", hex::encode(keccak256(&bytes))),
+        from: signed
+            .as_ref()
+            .and_then(|(tx, signature)| signature.recover(tx.sighash()).ok())
+            .map(|from| format!("{from:#x}")),
+        nonce: signed
+            .as_ref()
+            .and_then(|(tx, _)| tx.nonce())
+            .map(|nonce| nonce.low_u64()),
+    })
+}
+
+/// Raw transactions of the `eth_sendRawTransaction` requests accepted by the
+/// provider, `raw_transactions` are the request id and raw transaction pairs
+pub fn accepted_transactions<'a>(
+    response: &[u8],
+    raw_transactions: &'a [(String, String)],
+) -> Vec<&'a str> {
+    let accepted_ids = match serde_json::from_slice::<Value>(response) {
+        Ok(Value::Array(responses)) => responses,
+        Ok(response) => vec![response],
+        Err(_) => return Vec::new(),
+    }
+    .into_iter()
+    .filter(|response| {
+        response
+            .get("result")
+            .is_some_and(|result| !result.is_null())
+    })
+    .filter_map(|response| response.get("id").map(Value::to_string))
+    .collect::<HashSet<_>>();
+    raw_transactions
+        .iter()
+        .filter(|(id, _)| accepted_ids.contains(id))
+        .map(|(_, raw_tx)| raw_tx.as_str())
+        .collect()
+}
+
+/// Lifecycle tracking of the transactions submitted through the proxy. The
+/// pending transactions are polled in the background until their inclusion,
+/// replacement or drop.
+#[derive(Debug)]
+pub struct TxTracker {
+    enabled: bool,
+    drop_timeout: Duration,
+    transactions: RwLock<HashMap<String, TrackedTransaction>>,
+}
+
+/// Observed chain state of the pending transaction
+#[derive(Debug, PartialEq, Eq)]
+enum TxCheck {
+    Pending,
+    Included { block_number: u64, success: bool },
+    Replaced,
+    Dropped,
+}
+
+impl TxTracker {
+    pub fn new(config: &ProvidersConfig) -> Self {
+        Self {
+            enabled: config.tx_tracker_enabled.unwrap_or(false),
+            drop_timeout: config
+                .tx_tracker_drop_timeout_secs
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_DROP_TIMEOUT),
+            transactions: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Track the raw transaction submitted to the provider, the repeated
+    /// submissions of the same transaction add the provider
+    pub fn track(&self, raw_tx: &str, chain_id: &str, project_id: &str, provider: &str) {
+        if !self.enabled || !chain_id.starts_with("eip155:") {
+            return;
+        }
+        let Some(decoded) = decode_raw_transaction(raw_tx) else {
+            debug!("Failed to decode the submitted raw transaction for chain_id: {chain_id}");
+            return;
+        };
+        let Ok(mut transactions) = self.transactions.write() else {
+            return;
+        };
+        if let Some(tracked) = transactions.get_mut(&decoded.hash) {
+            if !tracked.providers.iter().any(|tracked| tracked == provider) {
+                tracked.providers.push(provider.to_string());
+            }
+            return;
+        }
+        if transactions.len() >= MAX_TRACKED_TRANSACTIONS {
+            warn!(
+                "Transaction {} is not tracked, the tracker is full",
+                decoded.hash
+            );
+            return;
+        }
+        transactions.insert(
+            decoded.hash.clone(),
+            TrackedTransaction {
+                hash: decoded.hash,
+                chain_id: chain_id.to_string(),
+                project_id: project_id.to_string(),
+                providers: vec![provider.to_string()],
+                from: decoded.from,
+                nonce: decoded.nonce,
+                status: TxStatus::Pending,
+                submitted_at: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs(),
+                block_number: None,
+                success: None,
+                time_to_inclusion_ms: None,
+                replaced_by: None,
+                submitted: Instant::now(),
+                finished: None,
+            },
+        );
+    }
+
+    pub fn get(&self, hash: &str) -> Option<TrackedTransaction> {
+        self.transactions
+            .read()
+            .ok()?
+            .get(&hash.to_lowercase())
+            .cloned()
+    }
+
+    /// Poll the providers for the pending transactions status and evict the
+    /// finished transactions after the retention
+    #[tracing::instrument(skip_all, level = "debug")]
+    pub async fn update(&self, providers: &ProviderRepository, metrics: &Metrics) {
+        let pending = {
+            let Ok(mut transactions) = self.transactions.write() else {
+                return;
+            };
+            transactions.retain(|_, tx| {
+                tx.finished
+                    .is_none_or(|finished| finished.elapsed() < FINISHED_RETENTION)
+            });
+            transactions
+                .values()
+                .filter(|tx| tx.status == TxStatus::Pending)
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        metrics.record_pending_transactions(pending.len() as u64);
+
+        let checks = stream::iter(pending)
+            .map(|tx| async move {
+                let provider = providers
+                    .get_rpc_provider_for_chain_id(&tx.chain_id, 1)
+                    .ok()?
+                    .into_iter()
+                    .next()?;
+                let call = TrackerCall {
+                    providers,
+                    metrics,
+                    provider: provider.as_ref(),
+                };
+                let check = check_transaction(&call, &tx, self.drop_timeout).await?;
+                Some((tx, check))
+            })
+            .buffer_unordered(TRACKER_CONCURRENCY)
+            .collect::<Vec<_>>()
+            .await;
+
+        for (tx, check) in checks.into_iter().flatten() {
+            self.apply(&tx, check, metrics);
+        }
+    }
+
+    fn apply(&self, tx: &TrackedTransaction, check: TxCheck, metrics: &Metrics) {
+        let Ok(mut transactions) = self.transactions.write() else {
+            return;
+        };
+        let status = match check {
+            TxCheck::Pending => return,
+            TxCheck::Included {
+                block_number,
+                success,
+            } => {
+                let time_to_inclusion = tx.submitted.elapsed();
+                if let Some(tracked) = transactions.get_mut(&tx.hash) {
+                    tracked.block_number = Some(block_number);
+                    tracked.success = Some(success);
+                    tracked.time_to_inclusion_ms = Some(time_to_inclusion.as_millis() as u64);
+                }
+                metrics.record_tx_time_to_inclusion(
+                    tx.chain_id.clone(),
+                    tx.providers.first().cloned().unwrap_or_default(),
+                    time_to_inclusion,
+                );
+                // Other tracked transactions with the same nonce are replaced
+                for other in transactions.values_mut().filter(|other| {
+                    other.status == TxStatus::Pending
+                        && other.hash != tx.hash
+                        && other.chain_id == tx.chain_id
+                        && other.from.is_some()
+                        && other.from == tx.from
+                        && other.nonce == tx.nonce
+                }) {
+                    other.status = TxStatus::Replaced;
+                    other.replaced_by = Some(tx.hash.clone());
+                    other.finished = Some(Instant::now());
+                    metrics.add_tx_lifecycle(other.chain_id.clone(), TxStatus::Replaced.as_str());
+                }
+                TxStatus::Included
+            }
+            TxCheck::Replaced => TxStatus::Replaced,
+            TxCheck::Dropped => TxStatus::Dropped,
+        };
+        if let Some(tracked) = transactions
+            .get_mut(&tx.hash)
+            .filter(|tracked| tracked.status == TxStatus::Pending)
+        {
+            debug!("Transaction {} is {}", tx.hash, status.as_str());
+            tracked.status = status;
+            tracked.finished = Some(Instant::now());
+            metrics.add_tx_lifecycle(tx.chain_id.clone(), status.as_str());
+        }
+    }
+}
+
+/// Check the pending transaction receipt, the sender nonce and whether the
+/// provider still knows the transaction after the drop timeout.
+/// Returns `None` if the provider failed to respond.
+async fn check_transaction(
+    call: &TrackerCall<'_>,
+    tx: &TrackedTransaction,
+    drop_timeout: Duration,
+) -> Option<TxCheck> {
+    let receipt = || call.call(&tx.chain_id, "eth_getTransactionReceipt", json!([tx.hash]));
+    if let Some(check) = included(&receipt().await?) {
+        return Some(check);
+    }
+
+    if let (Some(from), Some(nonce)) = (&tx.from, tx.nonce) {
+        let count = call
+            .call(
+                &tx.chain_id,
+                "eth_getTransactionCount",
+                json!([from, "latest"]),
+            )
+            .await?;
+        if parse_quantity(&count).is_some_and(|count| count > nonce) {
+            // The transaction could be included after the receipt request
+            return Some(included(&receipt().await?).unwrap_or(TxCheck::Replaced));
+        }
+    }
+
+    if tx.submitted.elapsed() > drop_timeout {
+        let transaction = call
+            .call(&tx.chain_id, "eth_getTransactionByHash", json!([tx.hash]))
+            .await?;
+        if transaction.is_null() {
+            return Some(TxCheck::Dropped);
+        }
+    }
+    Some(TxCheck::Pending)
+}
+
+fn included(receipt: &Value) -> Option<TxCheck> {
+    Some(TxCheck::Included {
+        block_number: parse_quantity(receipt.get("blockNumber")?)?,
+        success: receipt.get("status").and_then(parse_quantity) == Some(1),
+    })
+}
+
+fn parse_quantity(value: &Value) -> Option<u64> {
+    u64::from_str_radix(value.as_str()?.strip_prefix("0x")?, 16).ok()
+}
+
+/// Provider calls of the transaction status check
+struct TrackerCall<'a> {
+    providers: &'a ProviderRepository,
+    metrics: &'a Metrics,
+    provider: &'a dyn RpcProvider,
+}
+
+impl TrackerCall<'_> {
+    /// JSON-RPC call result, `None` if the call failed or is not permitted
+    /// by the provider backoff, budget or circuit breaker
+    async fn call(&self, chain_id: &str, method: &str, params: Value) -> Option<Value> {
+        if !self.providers.try_consume_background_call(
+            self.metrics,
+            &self.provider.provider_kind(),
+            chain_id,
+            method_compute_units(method),
+        ) {
+            return None;
+        }
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let response = timeout(
+            TRACKER_REQUEST_TIMEOUT,
+            self.provider
+                .proxy(chain_id, Bytes::from(request.to_string())),
+        )
+        .await
+        .ok()?
+        .ok()?;
+        if !response.status().is_success() {
+            return None;
+        }
+        let body = to_bytes(response.into_body(), TRACKER_RESPONSE_MAX_BYTES)
+            .await
+            .ok()?;
+        serde_json::from_slice::<Value>(&body)
+            .ok()?
+            .get_mut("result")
+            .map(Value::take)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        ethers::{
+            signers::{LocalWallet, Signer},
+            types::{Eip1559TransactionRequest, TransactionRequest},
+        },
+    };
+
+    #[test]
+    fn test_decode_raw_transaction() {
+        let wallet = "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"
+            .parse::<LocalWallet>()
+            .unwrap()
+            .with_chain_id(1u64);
+        let transactions: [TypedTransaction; 2] = [
+            TransactionRequest::new()
+                .to(wallet.address())
+                .nonce(7u64)
+                .gas(21_000u64)
+                .gas_price(1u64)
+                .chain_id(1u64)
+                .into(),
+            Eip1559TransactionRequest::new()
+                .to(wallet.address())
+                .nonce(8u64)
+                .gas(21_000u64)
+                .max_fee_per_gas(2u64)
+                .max_priority_fee_per_gas(1u64)
+                .chain_id(1u64)
+                .into(),
+        ];
+        for (tx, nonce) in transactions.iter().zip([7, 8]) {
+            let signature = wallet.sign_transaction_sync(tx).unwrap();
+            let raw_tx = tx.rlp_signed(&signature);
+            let decoded = decode_raw_transaction(&format!("0x{}", hex::encode(&raw_tx))).unwrap();
+            assert_eq!(
+                decoded.hash,
+                format!("0x{}", hex::encode(keccak256(&raw_tx)))
+            );
+            assert_eq!(decoded.from, Some(format!("{:#x}", wallet.address())));
+            assert_eq!(decoded.nonce, Some(nonce));
+        }
+
+        assert_eq!(decode_raw_transaction("0xzz"), None);
+        assert_eq!(
+            decode_raw_transaction("0x03ff").map(|decoded| decoded.from),
+            Some(None)
+        );
+    }
+
+    #[test]
+    fn test_accepted_transactions() {
+        let raw_transactions = vec![
+            ("1".to_string(), "0x01".to_string()),
+            ("\"two\"".to_string(), "0x02".to_string()),
+        ];
+        let response = br#"[
+            {"jsonrpc":"2.0","id":1,"error":{"code":-32000,"message":"nonce too low"}},
+            {"jsonrpc":"2.0","id":"two","result":"0xabc"}
+        ]"#;
+        assert_eq!(
+            accepted_transactions(response, &raw_transactions),
+            vec!["0x02"]
+        );
+        assert!(accepted_transactions(b"{}", &raw_transactions).is_empty());
+    }
+
+    #[test]
+    fn test_included_receipt() {
+        assert_eq!(
+            included(&json!({ "blockNumber": "0x10", "status": "0x1" })),
+            Some(TxCheck::Included {
+                block_number: 16,
+                success: true
+            })
+        );
+        assert_eq!(
+            included(&json!({ "blockNumber": "0x10", "status": "0x0" })),
+            Some(TxCheck::Included {
+                block_number: 16,
+                success: false
+            })
+        );
+        assert_eq!(included(&Value::Null), None);
+    }
+}
@@ -9,11 +9,24 @@ use {
 /// The amount of successful and failed requests to a provider
 ///
 /// Availability(success_counter, failure_counter)
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct Availability(u64, u64);
 
+impl Availability {
+    pub fn new(success: u64, failure: u64) -> Self {
+        Self(success, failure)
+    }
+
+    pub fn add(self, other: Self) -> Self {
+        Self(self.0 + other.0, self.1 + other.1)
+    }
+}
+
 pub type ParsedWeights = HashMap<ProviderKind, (HashMap<ChainId, Availability>, Availability)>;
 
+/// The p95 latency in seconds of a provider per chain
+pub type ParsedLatencies = HashMap<ProviderKind, HashMap<ChainId, f64>>;
+
 #[tracing::instrument(skip_all, level = "debug")]
 pub fn parse_weights(prometheus_data: PromqlResult) -> ParsedWeights {
     let mut weights_data = HashMap::new();
@@ -68,8 +81,74 @@ pub fn parse_weights(prometheus_data: PromqlResult) -> ParsedWeights {
     weights_data
 }
 
+#[tracing::instrument(skip_all, level = "debug")]
+pub fn parse_latencies(prometheus_data: PromqlResult) -> ParsedLatencies {
+    let mut latencies_data: ParsedLatencies = HashMap::new();
+    prometheus_data.data().as_vector().iter().for_each(|v| {
+        for metrics in v.iter() {
+            let mut metric = metrics.metric().to_owned();
+            let Some(chain_id) = metric.remove("chain_id") else {
+                warn!("No chain_id found in latency metric: {metric:?}");
+                continue;
+            };
+
+            let Some(provider) = metric.remove("provider") else {
+                warn!("No provider found in latency metric: {metric:?}");
+                continue;
+            };
+
+            let Some(provider_kind) = ProviderKind::from_str(&provider) else {
+                warn!("Failed to parse provider kind in latency metric: {provider}");
+                continue;
+            };
+
+            let latency = metrics.sample().value();
+            if !latency.is_finite() {
+                continue;
+            }
+
+            latencies_data
+                .entry(provider_kind)
+                .or_default()
+                .insert(ChainId(chain_id), latency);
+        }
+    });
+    latencies_data
+}
+
 const PERFECT_RATIO: f64 = 1.0;
 
+/// Latency in seconds up to which providers are not penalized
+const LATENCY_TARGET_SECS: f64 = 0.25;
+/// Minimal latency factor, so the slow providers are still used as a fallback
+const MIN_LATENCY_FACTOR: f64 = 0.01;
+
+/// Calculates the latency factor within <MIN_LATENCY_FACTOR, 1> range.
+/// Providers responding within the target latency get the perfect factor,
+/// the slower ones are penalized inversely proportional to the latency, e.g.
+/// the provider with 4s p95 latency gets 16 times less weight than the one
+/// responding within the target.
+fn calculate_latency_factor(p95_latency: Option<f64>) -> f64 {
+    match p95_latency {
+        Some(latency) if latency.is_finite() && latency > LATENCY_TARGET_SECS => {
+            (LATENCY_TARGET_SECS / latency).max(MIN_LATENCY_FACTOR)
+        }
+        // No latency data yet, implicitly assuming it's fine
+        _ => PERFECT_RATIO,
+    }
+}
+
+/// Combined chain weight score of the availability and the p95 latency
+#[tracing::instrument(level = "debug")]
+fn calculate_chain_weight_with_latency(
+    provider_availability: Availability,
+    chain_availability: Availability,
+    p95_latency: Option<f64>,
+) -> u64 {
+    let availability_weight = calculate_chain_weight(provider_availability, chain_availability);
+    (availability_weight as f64 * calculate_latency_factor(p95_latency)) as u64
+}
+
 #[tracing::instrument(level = "debug")]
 fn calculate_chain_weight(
     provider_availability: Availability,
@@ -123,7 +202,11 @@ fn calculate_chain_weight(
 }
 
 #[tracing::instrument(skip_all, level = "debug")]
-pub fn update_values(weight_resolver: &ChainsWeightResolver, parsed_weights: ParsedWeights) {
+pub fn update_values(
+    weight_resolver: &ChainsWeightResolver,
+    parsed_weights: ParsedWeights,
+    parsed_latencies: &ParsedLatencies,
+) {
     for (provider, (chain_availabilities, provider_availability)) in parsed_weights {
         // Skip weight recalculation for providers in the exclusion list
         // This prevents weight degradation when requests fail, allowing these providers
@@ -136,9 +219,17 @@ pub fn update_values(weight_resolver: &ChainsWeightResolver, parsed_weights: Par
             continue;
         }
 
+        let provider_latencies = parsed_latencies.get(&provider);
         for (chain_id, chain_availability) in chain_availabilities {
+            let p95_latency = provider_latencies
+                .and_then(|latencies| latencies.get(&chain_id))
+                .copied();
             let chain_id = chain_id.0;
-            let chain_weight = calculate_chain_weight(chain_availability, provider_availability);
+            let chain_weight = calculate_chain_weight_with_latency(
+                chain_availability,
+                provider_availability,
+                p95_latency,
+            );
 
             let Some(provider_chain_weight) = weight_resolver.get(&chain_id) else {
                 warn!("Chain {chain_id} not found in weight resolver: {weight_resolver:?}");
@@ -208,4 +299,64 @@ mod tests {
         // 100% * 100% = 100%
         assert_eq!(weight, 10_000);
     }
+
+    #[test]
+    fn calculate_weights_with_latency() {
+        let perfect_availability = super::Availability(0, 0);
+
+        // No latency data and latency within the target are not penalized
+        for latency in [None, Some(0.04), Some(0.25)] {
+            let weight = super::calculate_chain_weight_with_latency(
+                perfect_availability,
+                perfect_availability,
+                latency,
+            );
+            assert_eq!(weight, 10_000);
+        }
+
+        // 500ms p95 latency is twice the target
+        let weight = super::calculate_chain_weight_with_latency(
+            perfect_availability,
+            perfect_availability,
+            Some(0.5),
+        );
+        assert_eq!(weight, 5_000);
+
+        // 4s p95 latency is 16 times the target
+        let weight = super::calculate_chain_weight_with_latency(
+            perfect_availability,
+            perfect_availability,
+            Some(4.0),
+        );
+        assert_eq!(weight, 625);
+
+        // Extremely slow providers are capped by the minimal factor
+        let weight = super::calculate_chain_weight_with_latency(
+            perfect_availability,
+            perfect_availability,
+            Some(600.0),
+        );
+        assert_eq!(weight, 100);
+    }
+
+    #[test]
+    fn calculate_weights_latency_outweighs_small_error_rate() {
+        // The fast provider with 0.5% of errors
+        let fast_availability = super::Availability(995, 5);
+        let fast_weight = super::calculate_chain_weight_with_latency(
+            fast_availability,
+            fast_availability,
+            Some(0.04),
+        );
+
+        // The slow provider without errors
+        let slow_availability = super::Availability(1000, 0);
+        let slow_weight = super::calculate_chain_weight_with_latency(
+            slow_availability,
+            slow_availability,
+            Some(4.0),
+        );
+
+        assert!(fast_weight > slow_weight);
+    }
 }
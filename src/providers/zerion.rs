@@ -19,7 +19,7 @@ use {
         },
         providers::{
             balance::{BalanceItem, BalanceQuantity},
-            ProviderKind, TokenMetadataCacheProvider,
+            http_client, ProviderKind, TokenMetadataCacheProvider,
         },
         utils::crypto,
         Metrics,
@@ -44,7 +44,7 @@ pub struct ZerionProvider {
 
 impl ZerionProvider {
     pub fn new(api_key: String) -> Self {
-        let http_client = reqwest::Client::new();
+        let http_client = http_client::upstream_client(&ProviderKind::Zerion);
         Self {
             provider_kind: ProviderKind::Zerion,
             api_key,
@@ -609,7 +609,7 @@ impl BalanceProvider for ZerionProvider {
 
 impl BalanceProviderFactory<ZerionConfig> for ZerionProvider {
     fn new(provider_config: &ZerionConfig, _cache: Option<Arc<Pool>>) -> Self {
-        let http_client = reqwest::Client::new();
+        let http_client = http_client::upstream_client(&ProviderKind::Zerion);
         Self {
             provider_kind: ProviderKind::Zerion,
             api_key: provider_config.api_key.clone(),
@@ -1,5 +1,8 @@
 use {
-    super::{Provider, ProviderKind, RateLimited, RpcProvider, RpcProviderFactory},
+    super::{
+        http_client, rate_limits, Provider, ProviderKind, RateLimited, RpcProvider,
+        RpcProviderFactory,
+    },
     crate::{
         env::ZKSyncConfig,
         error::{RpcError, RpcResult},
@@ -58,7 +61,8 @@ impl RpcProvider for ZKSyncProvider {
             .send()
             .await?;
         let status = response.status();
-        let body = response.bytes().await?;
+        let rate_limit_headers = rate_limits::forwarded_headers(response.headers());
+        let body = http_client::read_body(response).await?;
 
         if let Ok(response) = serde_json::from_slice::<jsonrpc::Response>(&body) {
             if response.error.is_some() && status.is_success() {
@@ -69,7 +73,7 @@ impl RpcProvider for ZKSyncProvider {
             }
         }
 
-        let mut response = (status, body).into_response();
+        let mut response = (status, rate_limit_headers, body).into_response();
         response
             .headers_mut()
             .insert("Content-Type", HeaderValue::from_static("application/json"));
@@ -80,7 +84,7 @@ impl RpcProvider for ZKSyncProvider {
 impl RpcProviderFactory<ZKSyncConfig> for ZKSyncProvider {
     #[tracing::instrument(level = "debug")]
     fn new(provider_config: &ZKSyncConfig) -> Self {
-        let forward_proxy_client = reqwest::Client::new();
+        let forward_proxy_client = http_client::upstream_client(&ProviderKind::ZKSync);
         let supported_chains: HashMap<String, String> = provider_config
             .supported_chains
             .iter()
@@ -1,14 +1,23 @@
 use {
     crate::{
         analytics::RPCAnalytics,
+        chain_config::CatalogueError,
         env::Config,
         error::RpcError,
-        handlers::{balance::BalanceResponseBody, identity::IdentityResponse},
+        handlers::{
+            balance::BalanceResponseBody,
+            identity::IdentityResponse,
+            proxy::{
+                access::MethodAccess, coalescing::Coalescing, hedging::Hedging,
+                private_tx::PrivateTxRelays, retry::RetryPolicy,
+            },
+        },
         metrics::Metrics,
         project::{ProjectDataError, Registry},
-        providers::ProviderRepository,
+        providers::{tx_tracker::TxTracker, ProviderRepository},
         storage::{irn::Irn, KeyValueStorage},
-        utils::{build::CompileInfo, rate_limit::RateLimit},
+        utils::{build::CompileInfo, json_rpc_cache, rate_limit::RateLimit},
+        ws::hub::SubscriptionHub,
     },
     cerberus::project::ProjectDataWithLimits,
     moka::future::Cache,
@@ -39,6 +48,19 @@ pub struct AppState {
     pub balance_cache: Option<Arc<dyn KeyValueStorage<BalanceResponseBody>>>,
     // Moka local instance in-memory cache
     pub moka_cache: Cache<String, String>,
+    // Speculative provider requests policy and chains latencies
+    pub hedging: Hedging,
+    pub retry_policy: RetryPolicy,
+    // Identical in-flight requests coalescing
+    pub coalescing: Coalescing,
+    // JSON-RPC methods allowlist and denylist rules
+    pub method_access: MethodAccess,
+    // Private transactions relays per chain
+    pub private_tx: PrivateTxRelays,
+    // Submitted transactions lifecycle tracking
+    pub tx_tracker: TxTracker,
+    // Shared upstream WebSocket subscriptions
+    pub subscription_hub: SubscriptionHub,
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -55,7 +77,17 @@ pub fn new_state(
     identity_cache: Option<Arc<dyn KeyValueStorage<IdentityResponse>>>,
     balance_cache: Option<Arc<dyn KeyValueStorage<BalanceResponseBody>>>,
 ) -> AppState {
-    let moka_cache = Cache::builder().build();
+    let moka_cache = Cache::builder()
+        .weigher(|key: &String, value: &String| (key.len() + value.len()) as u32)
+        .max_capacity(json_rpc_cache::MEM_CACHE_MAX_CAPACITY)
+        .build();
+    let hedging = Hedging::new(&config.providers);
+    let retry_policy = RetryPolicy::new(&config.providers);
+    let coalescing = Coalescing::new(&config.providers);
+    let method_access = MethodAccess::new(&config.providers);
+    let private_tx = PrivateTxRelays::new(&config.providers);
+    let tx_tracker = TxTracker::new(&config.providers);
+    let subscription_hub = SubscriptionHub::new(&config.providers);
     AppState {
         config,
         postgres,
@@ -71,6 +103,13 @@ pub fn new_state(
         identity_cache,
         balance_cache,
         moka_cache,
+        hedging,
+        retry_policy,
+        coalescing,
+        method_access,
+        private_tx,
+        tx_tracker,
+        subscription_hub,
     }
 }
 
@@ -79,6 +118,27 @@ impl AppState {
         self.providers.update_weights(&self.metrics).await;
     }
 
+    pub async fn update_provider_heads(&self) {
+        self.providers.update_heads(&self.metrics).await;
+    }
+
+    pub async fn update_tracked_transactions(&self) {
+        self.tx_tracker.update(&self.providers, &self.metrics).await;
+    }
+
+    /// Rebuild the RPC and WebSocket providers from the provider catalogue and
+    /// atomically swap them in. In-flight requests and WebSocket sessions keep
+    /// the providers they were routed to, the current providers are kept if
+    /// the catalogue fails to load.
+    pub async fn reload_providers(&self) -> Result<(), CatalogueError> {
+        self.providers
+            .reload_rpc_providers(&self.config.providers, &self.metrics)?;
+        // New providers start with their priority weights, recalculate them
+        // from the collected providers health right away
+        self.update_provider_weights().await;
+        Ok(())
+    }
+
     #[tracing::instrument(skip(self), level = "debug")]
     async fn get_project_data_validated(
         &self,
@@ -4,9 +4,27 @@ use {
         metrics::Metrics,
         utils::crypto,
     },
+    deadpool_redis::{redis::AsyncCommands, Pool},
     moka::future::Cache,
+    serde::{Deserialize, Serialize},
+    serde_json::Value,
+    std::{
+        sync::Arc,
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    },
     strum_macros::{Display, EnumString},
-    tracing::error,
+    tracing::{error, warn},
+};
+
+/// Maximum size of the in-memory cache in bytes
+pub const MEM_CACHE_MAX_CAPACITY: u64 = 256 * 1024 * 1024;
+/// Redis keys scan batch size of the cache flush
+const REDIS_FLUSH_SCAN_COUNT: usize = 1000;
+/// Redis TTL for the immutable responses to not keep them forever
+const IMMUTABLE_REDIS_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+const DEFAULT_CHAIN_CACHE_PARAMS: ChainCacheParams = ChainCacheParams {
+    block_time: Duration::from_secs(2),
+    finality_depth: 64,
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumString, Display)]
@@ -19,13 +37,255 @@ pub enum CachedMethods {
     NetVersion,
 }
 
+/// Methods which responses are cached according to the `CachePolicy`
+/// depending on the requested block
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumString, Display)]
+pub enum PolicyCachedMethods {
+    #[strum(serialize = "eth_blockNumber")]
+    EthBlockNumber,
+    #[strum(serialize = "eth_getBlockByNumber")]
+    EthGetBlockByNumber,
+    #[strum(serialize = "eth_getBlockByHash")]
+    EthGetBlockByHash,
+    #[strum(serialize = "eth_getTransactionByHash")]
+    EthGetTransactionByHash,
+    #[strum(serialize = "eth_getTransactionReceipt")]
+    EthGetTransactionReceipt,
+    #[strum(serialize = "eth_getCode")]
+    EthGetCode,
+    #[strum(serialize = "eth_call")]
+    EthCall,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CachePolicy {
+    /// Response will never change, e.g. finalized block or a block by hash
+    Immutable,
+    /// Response can change with the next block
+    Ttl(Duration),
+    /// Response should not be cached
+    Never,
+}
+
+/// Chain block time and the amount of blocks after which the block
+/// is considered final and can't be reorganized
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChainCacheParams {
+    pub block_time: Duration,
+    pub finality_depth: u64,
+}
+
+fn chain_cache_params(caip2_chain_id: &str) -> ChainCacheParams {
+    match caip2_chain_id {
+        // Ethereum mainnet and Sepolia
+        "eip155:1" | "eip155:11155111" => ChainCacheParams {
+            block_time: Duration::from_secs(12),
+            finality_depth: 64,
+        },
+        // Polygon
+        "eip155:137" => ChainCacheParams {
+            block_time: Duration::from_secs(2),
+            finality_depth: 128,
+        },
+        // BNB Smart Chain
+        "eip155:56" => ChainCacheParams {
+            block_time: Duration::from_secs(3),
+            finality_depth: 15,
+        },
+        // Arbitrum One
+        "eip155:42161" => ChainCacheParams {
+            block_time: Duration::from_millis(250),
+            finality_depth: 64,
+        },
+        _ => DEFAULT_CHAIN_CACHE_PARAMS,
+    }
+}
+
+/// Block reference in the JSON-RPC request params
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BlockRef {
+    Number(u64),
+    Hash,
+    Earliest,
+    Latest,
+    Safe,
+    Finalized,
+    Pending,
+}
+
+/// Parse the block tag, number, hash or the EIP-1898 block object
+fn parse_block_ref(value: &Value) -> Option<BlockRef> {
+    match value {
+        Value::String(tag) => match tag.as_str() {
+            "earliest" => Some(BlockRef::Earliest),
+            "latest" => Some(BlockRef::Latest),
+            "safe" => Some(BlockRef::Safe),
+            "finalized" => Some(BlockRef::Finalized),
+            "pending" => Some(BlockRef::Pending),
+            // 32 bytes block hash
+            hash if hash.len() == 66 && hash.starts_with("0x") => Some(BlockRef::Hash),
+            number => parse_hex_u64(number).map(BlockRef::Number),
+        },
+        Value::Object(block) => {
+            if block.contains_key("blockHash") {
+                Some(BlockRef::Hash)
+            } else {
+                block
+                    .get("blockNumber")
+                    .and_then(Value::as_str)
+                    .and_then(parse_hex_u64)
+                    .map(BlockRef::Number)
+            }
+        }
+        _ => None,
+    }
+}
+
+fn parse_hex_u64(value: &str) -> Option<u64> {
+    u64::from_str_radix(value.strip_prefix("0x")?, 16).ok()
+}
+
+/// Explicit block number response is immutable only when the block is final
+fn block_number_policy(
+    block_number: u64,
+    chain_head: Option<u64>,
+    chain_params: &ChainCacheParams,
+) -> CachePolicy {
+    if chain_head
+        .is_some_and(|head| head >= block_number.saturating_add(chain_params.finality_depth))
+    {
+        CachePolicy::Immutable
+    } else {
+        CachePolicy::Ttl(chain_params.block_time)
+    }
+}
+
+fn block_ref_policy(
+    block_ref: BlockRef,
+    chain_head: Option<u64>,
+    chain_params: &ChainCacheParams,
+) -> CachePolicy {
+    match block_ref {
+        BlockRef::Hash | BlockRef::Earliest => CachePolicy::Immutable,
+        BlockRef::Number(block_number) => {
+            block_number_policy(block_number, chain_head, chain_params)
+        }
+        BlockRef::Latest | BlockRef::Safe | BlockRef::Finalized => {
+            CachePolicy::Ttl(chain_params.block_time)
+        }
+        BlockRef::Pending => CachePolicy::Never,
+    }
+}
+
+/// Optional block param policy, missing block param defaults to `latest`
+fn block_param_policy(
+    block_param: Option<&Value>,
+    chain_head: Option<u64>,
+    chain_params: &ChainCacheParams,
+) -> CachePolicy {
+    match block_param {
+        None => block_ref_policy(BlockRef::Latest, chain_head, chain_params),
+        Some(block_param) => match parse_block_ref(block_param) {
+            Some(block_ref) => block_ref_policy(block_ref, chain_head, chain_params),
+            None => CachePolicy::Never,
+        },
+    }
+}
+
+/// Get the cache policy for the method response
+fn cache_policy(
+    method: PolicyCachedMethods,
+    params: &Value,
+    result: &Value,
+    chain_head: Option<u64>,
+    chain_params: &ChainCacheParams,
+) -> CachePolicy {
+    match method {
+        PolicyCachedMethods::EthBlockNumber => CachePolicy::Ttl(chain_params.block_time),
+        PolicyCachedMethods::EthGetBlockByHash => CachePolicy::Immutable,
+        PolicyCachedMethods::EthGetBlockByNumber => {
+            block_param_policy(params.get(0), chain_head, chain_params)
+        }
+        PolicyCachedMethods::EthGetCode => {
+            block_param_policy(params.get(1), chain_head, chain_params)
+        }
+        // Only calls pinned to the exact block are cached
+        PolicyCachedMethods::EthCall => match params.get(1).and_then(parse_block_ref) {
+            Some(BlockRef::Hash) => CachePolicy::Immutable,
+            Some(BlockRef::Number(block_number)) => {
+                block_number_policy(block_number, chain_head, chain_params)
+            }
+            _ => CachePolicy::Never,
+        },
+        // Not mined transactions are not cached
+        PolicyCachedMethods::EthGetTransactionByHash
+        | PolicyCachedMethods::EthGetTransactionReceipt => match result
+            .get("blockNumber")
+            .and_then(Value::as_str)
+            .and_then(parse_hex_u64)
+        {
+            Some(block_number) => block_number_policy(block_number, chain_head, chain_params),
+            None => CachePolicy::Never,
+        },
+    }
+}
+
+/// Get the chain head block number from the response if possible
+fn response_chain_head(method: PolicyCachedMethods, params: &Value, result: &Value) -> Option<u64> {
+    match method {
+        PolicyCachedMethods::EthBlockNumber => result.as_str().and_then(parse_hex_u64),
+        PolicyCachedMethods::EthGetBlockByNumber
+            if params.get(0).and_then(Value::as_str) == Some("latest") =>
+        {
+            result
+                .get("number")
+                .and_then(Value::as_str)
+                .and_then(parse_hex_u64)
+        }
+        _ => None,
+    }
+}
+
+/// Get the block number from the response if the method returns the chain head
+pub fn response_block_number(method: &str, params: &Value, result: &Value) -> Option<u64> {
+    response_chain_head(method.parse().ok()?, params, result)
+}
+
+/// Cached response result with the optional expiration unix timestamp in milliseconds
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedResult {
+    result: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expires_at: Option<u64>,
+}
+
+fn now_unix_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
 /// Check if the response is cached and apply caching
 pub async fn is_cached_response(
     caip2_chain_id: &str,
     request: &JsonRpcRequest,
     metrics: &Metrics,
     moka_cache: &Cache<String, String>,
+    redis_pool: Option<&Arc<Pool>>,
 ) -> Option<JsonRpcResponse> {
+    if let Ok(method) = request.method.as_ref().parse::<PolicyCachedMethods>() {
+        return get_policy_cached_response(
+            caip2_chain_id,
+            method,
+            request,
+            metrics,
+            moka_cache,
+            redis_pool,
+        )
+        .await;
+    }
+
     if let Ok(method) = request.method.as_ref().parse::<CachedMethods>() {
         match method {
             CachedMethods::EthChainId => {
@@ -47,6 +307,153 @@ fn construct_mem_cache_key(method: &str, caip2_chain_id: &str) -> String {
     format!("rpc_cache:{method}:{caip2_chain_id}")
 }
 
+fn construct_policy_cache_key(method: &str, caip2_chain_id: &str, params: &Value) -> String {
+    format!(
+        "rpc_cache:{method}:{caip2_chain_id}:{}",
+        sha256::digest(params.to_string())
+    )
+}
+
+async fn get_redis_cached_response(redis_pool: &Pool, key: &str) -> Option<String> {
+    let mut connection = redis_pool
+        .get()
+        .await
+        .map_err(|e| warn!("Failed to get the Redis connection for the RPC cache: {e}"))
+        .ok()?;
+    connection
+        .get::<_, Option<String>>(key)
+        .await
+        .map_err(|e| warn!("Failed to get the RPC response from the Redis cache: {e}"))
+        .ok()
+        .flatten()
+}
+
+async fn set_redis_cached_response(redis_pool: &Pool, key: &str, value: &str, ttl: Duration) {
+    let mut connection = match redis_pool.get().await {
+        Ok(connection) => connection,
+        Err(e) => {
+            warn!("Failed to get the Redis connection for the RPC cache: {e}");
+            return;
+        }
+    };
+    if let Err(e) = connection
+        .pset_ex::<_, _, ()>(key, value, ttl.as_millis() as u64)
+        .await
+    {
+        warn!("Failed to set the RPC response to the Redis cache: {e}");
+    }
+}
+
+/// Delete the cached RPC responses from the Redis cache,
+/// returns the amount of the deleted keys
+pub async fn flush_redis_cache(redis_pool: &Pool) -> anyhow::Result<usize> {
+    let mut connection = redis_pool.get().await?;
+    let mut cursor = 0u64;
+    let mut deleted = 0;
+    loop {
+        let (next_cursor, keys): (u64, Vec<String>) = deadpool_redis::redis::cmd("SCAN")
+            .arg(cursor)
+            .arg("MATCH")
+            .arg("rpc_cache:*")
+            .arg("COUNT")
+            .arg(REDIS_FLUSH_SCAN_COUNT)
+            .query_async(&mut connection)
+            .await?;
+        if !keys.is_empty() {
+            deleted += connection.del::<_, usize>(&keys).await?;
+        }
+        if next_cursor == 0 {
+            return Ok(deleted);
+        }
+        cursor = next_cursor;
+    }
+}
+
+/// Get the method response cached according to the method cache policy
+/// from the in-memory cache or the Redis cache if configured
+async fn get_policy_cached_response(
+    caip2_chain_id: &str,
+    method: PolicyCachedMethods,
+    request: &JsonRpcRequest,
+    metrics: &Metrics,
+    moka_cache: &Cache<String, String>,
+    redis_pool: Option<&Arc<Pool>>,
+) -> Option<JsonRpcResponse> {
+    let key = construct_policy_cache_key(&request.method, caip2_chain_id, &request.params);
+    let cached = match moka_cache.get(&key).await {
+        Some(cached) => cached,
+        None => {
+            let cached = get_redis_cached_response(redis_pool?, &key).await?;
+            moka_cache.insert(key.clone(), cached.clone()).await;
+            cached
+        }
+    };
+
+    let cached = serde_json::from_str::<CachedResult>(&cached)
+        .map_err(|e| error!("Failed to deserialize the cached RPC response: {e}"))
+        .ok()?;
+    if cached
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= now_unix_ms())
+    {
+        moka_cache.invalidate(&key).await;
+        return None;
+    }
+
+    metrics.add_rpc_cached_call(caip2_chain_id.to_string(), method.to_string());
+    Some(JsonRpcResponse::Result(JsonRpcResult::new(
+        request.id.clone(),
+        cached.result,
+    )))
+}
+
+/// Cache the provider response result according to the method cache policy,
+/// the block finality is checked against the observed `chain_head`
+pub async fn cache_response(
+    caip2_chain_id: &str,
+    request: &JsonRpcRequest,
+    result: &Value,
+    chain_head: Option<u64>,
+    moka_cache: &Cache<String, String>,
+    redis_pool: Option<&Arc<Pool>>,
+) {
+    let Ok(method) = request.method.as_ref().parse::<PolicyCachedMethods>() else {
+        return;
+    };
+    // Not found entities can appear later
+    if result.is_null() {
+        return;
+    }
+
+    let (expires_at, redis_ttl) = match cache_policy(
+        method,
+        &request.params,
+        result,
+        chain_head,
+        &chain_cache_params(caip2_chain_id),
+    ) {
+        CachePolicy::Never => return,
+        CachePolicy::Immutable => (None, IMMUTABLE_REDIS_CACHE_TTL),
+        CachePolicy::Ttl(ttl) => (Some(now_unix_ms() + ttl.as_millis() as u64), ttl),
+    };
+
+    let value = match serde_json::to_string(&CachedResult {
+        result: result.clone(),
+        expires_at,
+    }) {
+        Ok(value) => value,
+        Err(e) => {
+            error!("Failed to serialize the RPC response for caching: {e}");
+            return;
+        }
+    };
+    let key = construct_policy_cache_key(&request.method, caip2_chain_id, &request.params);
+    if let Some(redis_pool) = redis_pool {
+        set_redis_cached_response(redis_pool, &key, &value, redis_ttl).await;
+    }
+    moka_cache.insert(key, value).await;
+}
+
 async fn get_mem_cached_response(
     caip2_chain_id: &str,
     method: &str,
@@ -202,4 +609,150 @@ mod tests {
         assert_eq!(get_evm_chain_id_bytes(""), None);
         assert_eq!(get_evm_chain_id_bytes("abc"), None);
     }
+
+    #[test]
+    fn test_parse_block_ref() {
+        assert_eq!(parse_block_ref(&"latest".into()), Some(BlockRef::Latest));
+        assert_eq!(parse_block_ref(&"pending".into()), Some(BlockRef::Pending));
+        assert_eq!(parse_block_ref(&"0x10".into()), Some(BlockRef::Number(16)));
+        assert_eq!(
+            parse_block_ref(&format!("0x{}", "ab".repeat(32)).into()),
+            Some(BlockRef::Hash)
+        );
+        assert_eq!(
+            parse_block_ref(&serde_json::json!({ "blockHash": "0x00" })),
+            Some(BlockRef::Hash)
+        );
+        assert_eq!(
+            parse_block_ref(&serde_json::json!({ "blockNumber": "0x1" })),
+            Some(BlockRef::Number(1))
+        );
+        assert_eq!(parse_block_ref(&"invalid".into()), None);
+        assert_eq!(parse_block_ref(&Value::Null), None);
+    }
+
+    #[test]
+    fn test_block_number_cache_policy() {
+        let chain_params = chain_cache_params("eip155:1");
+        let params = serde_json::json!(["0x64", false]);
+        let result = serde_json::json!({ "number": "0x64" });
+        let policy = |chain_head| {
+            cache_policy(
+                PolicyCachedMethods::EthGetBlockByNumber,
+                &params,
+                &result,
+                chain_head,
+                &chain_params,
+            )
+        };
+
+        // Unknown chain head or not yet final block
+        assert_eq!(policy(None), CachePolicy::Ttl(chain_params.block_time));
+        assert_eq!(
+            policy(Some(100 + 63)),
+            CachePolicy::Ttl(chain_params.block_time)
+        );
+        // Final block
+        assert_eq!(policy(Some(100 + 64)), CachePolicy::Immutable);
+    }
+
+    #[test]
+    fn test_block_tags_cache_policy() {
+        let chain_params = DEFAULT_CHAIN_CACHE_PARAMS;
+        let result = serde_json::json!("0x");
+        let policy = |method, params: Value| {
+            cache_policy(method, &params, &result, Some(1_000), &chain_params)
+        };
+
+        assert_eq!(
+            policy(
+                PolicyCachedMethods::EthGetBlockByNumber,
+                serde_json::json!(["latest", false])
+            ),
+            CachePolicy::Ttl(chain_params.block_time)
+        );
+        assert_eq!(
+            policy(
+                PolicyCachedMethods::EthGetBlockByNumber,
+                serde_json::json!(["pending", false])
+            ),
+            CachePolicy::Never
+        );
+        // Missing block param defaults to `latest`
+        assert_eq!(
+            policy(PolicyCachedMethods::EthGetCode, serde_json::json!(["0x00"])),
+            CachePolicy::Ttl(chain_params.block_time)
+        );
+        assert_eq!(
+            policy(
+                PolicyCachedMethods::EthCall,
+                serde_json::json!([{}, { "blockHash": "0x00" }])
+            ),
+            CachePolicy::Immutable
+        );
+        assert_eq!(
+            policy(
+                PolicyCachedMethods::EthCall,
+                serde_json::json!([{}, "latest"])
+            ),
+            CachePolicy::Never
+        );
+    }
+
+    #[test]
+    fn test_transaction_receipt_cache_policy() {
+        let chain_params = DEFAULT_CHAIN_CACHE_PARAMS;
+        let params = serde_json::json!(["0x00"]);
+        let policy = |result: Value| {
+            cache_policy(
+                PolicyCachedMethods::EthGetTransactionReceipt,
+                &params,
+                &result,
+                Some(1_000),
+                &chain_params,
+            )
+        };
+
+        assert_eq!(
+            policy(serde_json::json!({ "blockNumber": "0x1" })),
+            CachePolicy::Immutable
+        );
+        assert_eq!(
+            policy(serde_json::json!({ "blockNumber": "0x3e8" })),
+            CachePolicy::Ttl(chain_params.block_time)
+        );
+        // Pending transaction
+        assert_eq!(
+            policy(serde_json::json!({ "blockNumber": null })),
+            CachePolicy::Never
+        );
+    }
+
+    #[test]
+    fn test_response_chain_head() {
+        assert_eq!(
+            response_chain_head(
+                PolicyCachedMethods::EthBlockNumber,
+                &Value::Null,
+                &"0x10".into()
+            ),
+            Some(16)
+        );
+        assert_eq!(
+            response_chain_head(
+                PolicyCachedMethods::EthGetBlockByNumber,
+                &serde_json::json!(["latest", false]),
+                &serde_json::json!({ "number": "0x20" })
+            ),
+            Some(32)
+        );
+        assert_eq!(
+            response_chain_head(
+                PolicyCachedMethods::EthGetBlockByNumber,
+                &serde_json::json!(["0x20", false]),
+                &serde_json::json!({ "number": "0x20" })
+            ),
+            None
+        );
+    }
 }
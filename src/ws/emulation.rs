@@ -0,0 +1,524 @@
+use {
+    super::metering::SessionMeter,
+    crate::{
+        handlers::{
+            proxy::{access::check_method_access, rpc_call, PROVIDER_RESPONSE_MAX_BYTES},
+            RpcQueryParams,
+        },
+        json_rpc::{
+            ErrorResponse, JsonRpcError, JsonRpcResponse, INTERNAL_ERROR_CODE, INVALID_PARAMS_CODE,
+            LIMIT_EXCEEDED_CODE, METHOD_NOT_FOUND_CODE,
+        },
+        state::AppState,
+    },
+    axum::{
+        body::{to_bytes, Bytes},
+        extract::ws::{Message as AxumWsMessage, WebSocket},
+    },
+    futures_util::{SinkExt, StreamExt},
+    hyper::HeaderMap,
+    serde_json::{json, Value},
+    std::{collections::HashMap, net::SocketAddr, sync::Arc, time::Duration},
+    tokio::{
+        sync::{mpsc, watch, Semaphore},
+        task::JoinHandle,
+        time::{interval, MissedTickBehavior},
+    },
+    tracing::log::debug,
+};
+
+/// Default interval of the chain head polling for the emulated subscriptions
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// Default maximum amount of the emulated subscriptions per session
+const DEFAULT_MAX_SUBSCRIPTIONS: usize = 10;
+/// Default maximum amount of the in-flight proxy calls per session
+const DEFAULT_MAX_IN_FLIGHT_CALLS: usize = 32;
+/// Maximum amount of the `newHeads` notifications per poll, the older
+/// blocks are skipped when the session falls behind the chain
+const MAX_HEADS_PER_POLL: u64 = 10;
+/// Maximum block range of the `eth_getLogs` request per poll
+const MAX_LOGS_BLOCKS_PER_POLL: u64 = 100;
+
+/// Context of the HTTP proxy calls made on behalf of the WebSocket client
+#[derive(Clone)]
+struct CallContext {
+    state: Arc<AppState>,
+    addr: SocketAddr,
+    query_params: RpcQueryParams,
+    headers: HeaderMap,
+}
+
+impl CallContext {
+    /// Send the client frame through the HTTP proxy path and return the
+    /// response frame. The method access rules are checked as for the HTTP
+    /// proxy calls, including the emulated subscriptions polling calls.
+    async fn call_frame(&self, text: String) -> String {
+        let id = request_id(&text);
+        let body = Bytes::from(text);
+        let response = match check_method_access(&self.state, &self.query_params, &body).await {
+            Ok(Some(denied)) => Ok(denied),
+            Ok(None) => {
+                rpc_call(
+                    self.state.clone(),
+                    self.addr,
+                    self.query_params.clone(),
+                    self.headers.clone(),
+                    body,
+                )
+                .await
+            }
+            Err(e) => Err(e),
+        };
+        let response = match response {
+            Ok(response) => response,
+            Err(e) => return error_response(id, INTERNAL_ERROR_CODE, &e.to_string()),
+        };
+        match to_bytes(response.into_body(), PROVIDER_RESPONSE_MAX_BYTES).await {
+            Ok(body) => String::from_utf8_lossy(&body).into_owned(),
+            Err(e) => error_response(id, INTERNAL_ERROR_CODE, &e.to_string()),
+        }
+    }
+
+    /// Internal JSON-RPC call of the emulated subscription, `None` on failure
+    async fn call(&self, method: &str, params: Value) -> Option<Value> {
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let response = self.call_frame(request.to_string()).await;
+        let mut response = serde_json::from_str::<Value>(&response).ok()?;
+        if let Some(error) = response.get("error") {
+            debug!("Emulated subscription {method} call failed: {error}");
+            return None;
+        }
+        Some(response.get_mut("result")?.take())
+    }
+
+    async fn block_number(&self) -> Option<u64> {
+        let result = self.call("eth_blockNumber", json!([])).await?;
+        u64::from_str_radix(result.as_str()?.strip_prefix("0x")?, 16).ok()
+    }
+}
+
+/// Request id of the client frame, `null` if the frame is not a request
+fn request_id(text: &str) -> Value {
+    serde_json::from_str::<Value>(text)
+        .ok()
+        .and_then(|request| request.get("id").cloned())
+        .unwrap_or_default()
+}
+
+fn error_response(id: Value, code: i32, message: &str) -> String {
+    serde_json::to_string(&JsonRpcResponse::Error(JsonRpcError::new(
+        id,
+        ErrorResponse {
+            code,
+            message: message.to_string().into(),
+            data: None,
+        },
+    )))
+    .unwrap_or_default()
+}
+
+fn subscription_notification(subscription_id: &Value, result: Value) -> String {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "eth_subscription",
+        "params": { "subscription": subscription_id, "result": result },
+    })
+    .to_string()
+}
+
+/// Emulated subscription kind
+#[derive(Debug, Clone, PartialEq)]
+enum EmulatedSubscription {
+    NewHeads,
+    Logs { filter: Value },
+}
+
+impl EmulatedSubscription {
+    /// Parse the `eth_subscribe` params, returns the error message
+    /// for the not supported subscriptions
+    fn from_params(params: &Value) -> Result<Self, (i32, String)> {
+        match params.get(0).and_then(Value::as_str) {
+            Some("newHeads") => Ok(Self::NewHeads),
+            Some("logs") => match params.get(1).cloned().unwrap_or_else(|| json!({})) {
+                filter @ Value::Object(_) => Ok(Self::Logs { filter }),
+                _ => Err((INVALID_PARAMS_CODE, "Invalid logs filter".to_string())),
+            },
+            Some(kind) => Err((
+                METHOD_NOT_FOUND_CODE,
+                format!("Subscription {kind} is not supported for the chain"),
+            )),
+            None => Err((INVALID_PARAMS_CODE, "Missing subscription kind".to_string())),
+        }
+    }
+}
+
+/// Active emulated subscriptions of the session. The chain head is polled
+/// once for all the session subscriptions while there are any.
+struct Subscriptions {
+    context: CallContext,
+    outgoing: mpsc::UnboundedSender<String>,
+    poll_interval: Duration,
+    max_subscriptions: usize,
+    /// Notification tasks by the subscription id
+    tasks: HashMap<String, JoinHandle<()>>,
+    /// Latest polled chain head
+    head: watch::Sender<Option<u64>>,
+    head_poll: Option<JoinHandle<()>>,
+}
+
+impl Subscriptions {
+    /// Handle the subscription requests, returns `None` for the requests
+    /// which are sent through the HTTP proxy path
+    fn handle_request(&mut self, text: &str) -> Option<String> {
+        let request = serde_json::from_str::<Value>(text).ok()?;
+        let id = request.get("id")?.clone();
+        let method = request.get("method")?.as_str()?;
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+        let is_evm = self.context.query_params.chain_id.starts_with("eip155:");
+
+        match method {
+            "eth_subscribe" if is_evm => {
+                let subscription = match EmulatedSubscription::from_params(&params) {
+                    Ok(subscription) => subscription,
+                    Err((code, message)) => return Some(error_response(id, code, &message)),
+                };
+                if self.tasks.len() >= self.max_subscriptions {
+                    return Some(error_response(
+                        id,
+                        LIMIT_EXCEEDED_CODE,
+                        &format!(
+                            "Maximum of {} subscriptions per connection is reached",
+                            self.max_subscriptions
+                        ),
+                    ));
+                }
+                if self.head_poll.is_none() {
+                    self.head_poll = Some(tokio::spawn(poll_head(
+                        self.context.clone(),
+                        self.head.clone(),
+                        self.poll_interval,
+                    )));
+                }
+                let subscription_id = Value::String(format!("0x{:032x}", rand::random::<u128>()));
+                let task = tokio::spawn(notify_subscription(
+                    self.context.clone(),
+                    subscription,
+                    subscription_id.clone(),
+                    self.outgoing.clone(),
+                    self.head.subscribe(),
+                ));
+                self.tasks.insert(subscription_id.to_string(), task);
+                Some(json!({ "jsonrpc": "2.0", "id": id, "result": subscription_id }).to_string())
+            }
+            "eth_unsubscribe" if is_evm => {
+                let task = params
+                    .get(0)
+                    .and_then(|subscription_id| self.tasks.remove(&subscription_id.to_string()));
+                let is_unsubscribed = task.inspect(JoinHandle::abort).is_some();
+                if self.tasks.is_empty() {
+                    self.stop_head_poll();
+                }
+                Some(json!({ "jsonrpc": "2.0", "id": id, "result": is_unsubscribed }).to_string())
+            }
+            method if method.ends_with("ubscribe") => Some(error_response(
+                id,
+                METHOD_NOT_FOUND_CODE,
+                "Subscriptions are not supported for the chain",
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl Subscriptions {
+    /// Stop polling the chain head without the subscriptions, the next
+    /// subscriptions start from the newly polled head
+    fn stop_head_poll(&mut self) {
+        if let Some(head_poll) = self.head_poll.take() {
+            head_poll.abort();
+        }
+        self.head.send_replace(None);
+    }
+}
+
+impl Drop for Subscriptions {
+    fn drop(&mut self) {
+        for task in self.tasks.values() {
+            task.abort();
+        }
+        self.stop_head_poll();
+    }
+}
+
+/// Poll the chain head for the session subscriptions, the subscriptions are
+/// notified only when the head advances
+async fn poll_head(
+    context: CallContext,
+    head: watch::Sender<Option<u64>>,
+    poll_interval: Duration,
+) {
+    let mut ticker = interval(poll_interval);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    loop {
+        ticker.tick().await;
+        let Some(block) = context.block_number().await else {
+            continue;
+        };
+        head.send_if_modified(|current| {
+            let is_advanced = *current < Some(block);
+            if is_advanced {
+                *current = Some(block);
+            }
+            is_advanced
+        });
+    }
+}
+
+/// Send the emulated subscription notifications on the chain head updates
+/// until the session is closed
+async fn notify_subscription(
+    context: CallContext,
+    subscription: EmulatedSubscription,
+    subscription_id: Value,
+    outgoing: mpsc::UnboundedSender<String>,
+    mut heads: watch::Receiver<Option<u64>>,
+) {
+    // Notifications start from the next block after the subscription
+    let mut last_block = *heads.borrow_and_update();
+
+    while heads.changed().await.is_ok() {
+        let Some(head) = *heads.borrow_and_update() else {
+            continue;
+        };
+        let Some(last) = last_block else {
+            last_block = Some(head);
+            continue;
+        };
+        if head <= last {
+            continue;
+        }
+
+        let notifications = match &subscription {
+            EmulatedSubscription::NewHeads => {
+                let from_block = (last + 1).max(head.saturating_sub(MAX_HEADS_PER_POLL - 1));
+                let mut heads = Vec::new();
+                for number in from_block..=head {
+                    let Some(mut block) = context
+                        .call(
+                            "eth_getBlockByNumber",
+                            json!([format!("0x{number:x}"), false]),
+                        )
+                        .await
+                        .filter(Value::is_object)
+                    else {
+                        break;
+                    };
+                    // Headers are sent without the transactions list
+                    if let Some(block) = block.as_object_mut() {
+                        block.remove("transactions");
+                    }
+                    heads.push(block);
+                    last_block = Some(number);
+                }
+                heads
+            }
+            EmulatedSubscription::Logs { filter } => {
+                let from_block = (last + 1).max(head.saturating_sub(MAX_LOGS_BLOCKS_PER_POLL - 1));
+                let Some(logs) = context
+                    .call(
+                        "eth_getLogs",
+                        json!([logs_filter(filter, from_block, head)]),
+                    )
+                    .await
+                    .and_then(|logs| logs.as_array().cloned())
+                else {
+                    continue;
+                };
+                last_block = Some(head);
+                logs
+            }
+        };
+
+        for notification in notifications {
+            if outgoing
+                .send(subscription_notification(&subscription_id, notification))
+                .is_err()
+            {
+                return;
+            }
+        }
+    }
+}
+
+/// Subscription logs filter for the polled block range
+fn logs_filter(filter: &Value, from_block: u64, to_block: u64) -> Value {
+    let mut filter = filter.clone();
+    if let Some(filter) = filter.as_object_mut() {
+        filter.remove("blockHash");
+        filter.insert("fromBlock".into(), format!("0x{from_block:x}").into());
+        filter.insert("toBlock".into(), format!("0x{to_block:x}").into());
+    }
+    filter
+}
+
+/// Serve the WebSocket client of the chain without WebSocket providers.
+/// Requests are sent through the HTTP proxy path and the `newHeads` and
+/// `logs` subscriptions are emulated by polling the chain head.
+#[tracing::instrument(skip_all, fields(chain_id = %query_params.chain_id), level = "debug")]
+pub async fn run(
+    state: Arc<AppState>,
+    addr: SocketAddr,
+    query_params: RpcQueryParams,
+    headers: HeaderMap,
+    meter: SessionMeter,
+    client_ws: WebSocket,
+) {
+    let project_id = query_params.project_id.clone();
+    let (mut client_sender, mut client_receiver) = client_ws.split();
+    let (outgoing, mut outgoing_receiver) = mpsc::unbounded_channel::<String>();
+    let context = CallContext {
+        state: state.clone(),
+        addr,
+        query_params,
+        headers,
+    };
+    let mut subscriptions = Subscriptions {
+        context: context.clone(),
+        outgoing: outgoing.clone(),
+        poll_interval: state
+            .config
+            .providers
+            .ws_emulation_poll_interval_ms
+            .filter(|ms| *ms > 0)
+            .map(Duration::from_millis)
+            .unwrap_or(DEFAULT_POLL_INTERVAL),
+        max_subscriptions: state
+            .config
+            .providers
+            .ws_emulation_max_subscriptions
+            .unwrap_or(DEFAULT_MAX_SUBSCRIPTIONS),
+        tasks: HashMap::new(),
+        head: watch::Sender::new(None),
+        head_poll: None,
+    };
+    let max_in_flight_calls = state
+        .config
+        .providers
+        .ws_emulation_max_in_flight_calls
+        .unwrap_or(DEFAULT_MAX_IN_FLIGHT_CALLS)
+        .max(1);
+    let in_flight_calls = Arc::new(Semaphore::new(max_in_flight_calls));
+    let mut quota_check = meter.quota_check_timer();
+
+    loop {
+        tokio::select! {
+            message = client_receiver.next() => {
+                let text = match message {
+                    Some(Ok(AxumWsMessage::Close(_))) | Some(Err(_)) | None => {
+                        debug!("WebSocket client {project_id} disconnected");
+                        break;
+                    }
+                    Some(Ok(AxumWsMessage::Text(text))) => text,
+                    // Pings are answered by the WebSocket implementation
+                    Some(Ok(_)) => continue,
+                };
+                let frame = meter.on_client_frame(&state, None, text.as_str());
+                if let Some(rejected) = frame.rejected {
+                    let _ = outgoing.send(rejected);
+                }
+                let Some(text) = frame.forward else {
+                    continue;
+                };
+                if let Some(response) = subscriptions.handle_request(&text) {
+                    let _ = outgoing.send(response);
+                    continue;
+                }
+                // Calls are made concurrently up to the in-flight calls limit,
+                // the responses are matched by the client with the request ids
+                let Ok(permit) = in_flight_calls.clone().try_acquire_owned() else {
+                    let _ = outgoing.send(error_response(
+                        request_id(&text),
+                        LIMIT_EXCEEDED_CODE,
+                        &format!(
+                            "Maximum of {max_in_flight_calls} in-flight requests per connection is reached"
+                        ),
+                    ));
+                    continue;
+                };
+                let context = context.clone();
+                let outgoing = outgoing.clone();
+                tokio::spawn(async move {
+                    let _ = outgoing.send(context.call_frame(text).await);
+                    drop(permit);
+                });
+            }
+            Some(text) = outgoing_receiver.recv() => {
+                if client_sender.send(AxumWsMessage::Text(text.into())).await.is_err() {
+                    debug!("WebSocket client {project_id} disconnected");
+                    break;
+                }
+            }
+            _ = quota_check.tick() => {
+                if let Some(close) = meter.check_quota(&state).await {
+                    let _ = client_sender.send(close).await;
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emulated_subscription_from_params() {
+        assert_eq!(
+            EmulatedSubscription::from_params(&json!(["newHeads"])),
+            Ok(EmulatedSubscription::NewHeads)
+        );
+        assert_eq!(
+            EmulatedSubscription::from_params(&json!(["logs"])),
+            Ok(EmulatedSubscription::Logs { filter: json!({}) })
+        );
+        assert_eq!(
+            EmulatedSubscription::from_params(&json!(["logs", "0x1"])).map_err(|(code, _)| code),
+            Err(INVALID_PARAMS_CODE)
+        );
+        assert_eq!(
+            EmulatedSubscription::from_params(&json!(["newPendingTransactions"]))
+                .map_err(|(code, _)| code),
+            Err(METHOD_NOT_FOUND_CODE)
+        );
+    }
+
+    #[test]
+    fn test_request_id() {
+        assert_eq!(
+            request_id(r#"{"jsonrpc":"2.0","id":7,"method":"eth_blockNumber"}"#),
+            json!(7)
+        );
+        assert_eq!(request_id("[]"), Value::Null);
+        assert_eq!(request_id("invalid"), Value::Null);
+    }
+
+    #[test]
+    fn test_logs_filter() {
+        let filter = json!({
+            "address": "0x1",
+            "topics": ["0x2"],
+            "fromBlock": "earliest",
+            "blockHash": "0x3",
+        });
+        assert_eq!(
+            logs_filter(&filter, 16, 31),
+            json!({
+                "address": "0x1",
+                "topics": ["0x2"],
+                "fromBlock": "0x10",
+                "toBlock": "0x1f",
+            })
+        );
+    }
+}
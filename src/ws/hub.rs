@@ -0,0 +1,832 @@
+use {
+    super::session::reconnect_backoff,
+    crate::{
+        json_rpc::INTERNAL_ERROR_CODE,
+        providers::{ProviderKind, ProvidersConfig},
+        state::AppState,
+    },
+    async_tungstenite::{tokio::ConnectStream, tungstenite, WebSocketStream},
+    futures_util::{SinkExt, StreamExt},
+    serde_json::{json, Value},
+    std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+        time::Duration,
+    },
+    tokio::{
+        sync::{broadcast, mpsc, oneshot},
+        task::JoinHandle,
+    },
+    tracing::log::{debug, warn},
+};
+
+/// Amount of the notifications buffered for the slow client sessions
+const NOTIFICATIONS_CAPACITY: usize = 1024;
+const MAX_CONNECT_ATTEMPTS: u32 = 3;
+/// Maximum time the session waits for the hub subscription confirmation
+const SUBSCRIBE_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Subscription topic that is shared between the client sessions.
+/// Only the topics without the client-specific state are shared:
+/// `newHeads`, `logs` with identical filters and Solana `slotSubscribe`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedTopic {
+    key: String,
+    method: String,
+    params: Value,
+}
+
+impl SharedTopic {
+    pub fn from_request(method: &str, params: &Value) -> Option<Self> {
+        let key = match method {
+            "eth_subscribe" => match params.as_array()?.as_slice() {
+                [topic] if topic == "newHeads" => "newHeads".to_string(),
+                [topic] if topic == "logs" => "logs".to_string(),
+                [topic, filter] if topic == "logs" => format!("logs:{}", canonical_json(filter)),
+                _ => return None,
+            },
+            "slotSubscribe" if params.as_array().is_none_or(Vec::is_empty) => {
+                "slotSubscribe".to_string()
+            }
+            _ => return None,
+        };
+        Some(Self {
+            key,
+            method: method.to_string(),
+            params: params.clone(),
+        })
+    }
+
+    fn unsubscribe_method(&self) -> &'static str {
+        if self.method == "eth_subscribe" {
+            "eth_unsubscribe"
+        } else {
+            "slotUnsubscribe"
+        }
+    }
+
+    /// Client subscription id in the namespace format
+    fn new_client_id(&self) -> Value {
+        if self.method == "eth_subscribe" {
+            Value::String(format!("0x{:032x}", rand::random::<u128>()))
+        } else {
+            Value::from(rand::random::<u32>())
+        }
+    }
+}
+
+/// Serialize the JSON value with the sorted object keys,
+/// so identical `logs` filters share the topic
+fn canonical_json(value: &Value) -> String {
+    match value {
+        Value::Object(map) => {
+            let mut entries = map.iter().collect::<Vec<_>>();
+            entries.sort_by_key(|(key, _)| *key);
+            let entries = entries
+                .into_iter()
+                .map(|(key, value)| {
+                    format!("{}:{}", Value::from(key.as_str()), canonical_json(value))
+                })
+                .collect::<Vec<_>>();
+            format!("{{{}}}", entries.join(","))
+        }
+        Value::Array(values) => {
+            let values = values.iter().map(canonical_json).collect::<Vec<_>>();
+            format!("[{}]", values.join(","))
+        }
+        _ => value.to_string(),
+    }
+}
+
+/// Subscription notification fanned out to the client sessions
+#[derive(Debug)]
+pub struct HubNotification {
+    method: String,
+    result: Value,
+}
+
+/// Message of the hub to the client session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HubEvent {
+    /// Notification to send to the client
+    Notification(String),
+    /// Response to the client shared topic request
+    Response(String),
+    /// The shared topic subscription failed, the client subscription is
+    /// forgotten by the session
+    SubscribeFailed { client_id: String, response: String },
+    /// The shared topic is dropped by the hub, e.g. when the upstream is
+    /// unavailable, so the client subscription is not served anymore
+    Closed { client_id: String },
+}
+
+/// Upstream connection established by the chain hub connect task
+type ConnectResult = Option<(WebSocketStream<ConnectStream>, ProviderKind)>;
+
+/// Topic generation and the notifications receiver, or the JSON-RPC error object
+type SubscribeResult = Result<(u64, broadcast::Receiver<Arc<HubNotification>>), Value>;
+
+enum HubCommand {
+    Subscribe {
+        topic: SharedTopic,
+        reply: oneshot::Sender<SubscribeResult>,
+    },
+    Unsubscribe {
+        key: String,
+        generation: u64,
+    },
+}
+
+/// Hub of the upstream subscriptions shared between the WebSocket client
+/// sessions. A single upstream connection per chain keeps one subscription
+/// per topic and fans the notifications out to all the subscribed sessions.
+#[derive(Debug)]
+pub struct SubscriptionHub {
+    enabled: bool,
+    chains: Mutex<HashMap<String, mpsc::UnboundedSender<HubCommand>>>,
+}
+
+impl SubscriptionHub {
+    pub fn new(config: &ProvidersConfig) -> Self {
+        Self {
+            enabled: config.ws_subscription_hub_enabled.unwrap_or(false),
+            chains: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Chain hub commands channel, the chain hub task is started on the
+    /// first subscription for the chain
+    fn chain_commands(
+        &self,
+        state: &Arc<AppState>,
+        chain_id: &str,
+    ) -> Option<mpsc::UnboundedSender<HubCommand>> {
+        let mut chains = self.chains.lock().ok()?;
+        if let Some(commands) = chains.get(chain_id).filter(|c| !c.is_closed()) {
+            return Some(commands.clone());
+        }
+        let (commands, receiver) = mpsc::unbounded_channel();
+        let chain_hub = ChainHub::new(state.clone(), chain_id.to_string());
+        tokio::spawn(chain_hub.run(receiver));
+        chains.insert(chain_id.to_string(), commands.clone());
+        Some(commands)
+    }
+
+    /// Subscribe to the shared topic, returns the JSON-RPC error object if the
+    /// upstream subscription failed or wasn't confirmed in time
+    pub async fn subscribe(
+        &self,
+        state: &Arc<AppState>,
+        chain_id: &str,
+        topic: SharedTopic,
+    ) -> Result<HubSubscription, Value> {
+        let key = topic.key.clone();
+        let commands = self
+            .chain_commands(state, chain_id)
+            .ok_or_else(|| hub_error("Subscription hub is unavailable"))?;
+        let (reply, response) = oneshot::channel();
+        commands
+            .send(HubCommand::Subscribe { topic, reply })
+            .map_err(|_| hub_error("Subscription hub is unavailable"))?;
+        // The hub releases the subscriber if the reply receiver is dropped
+        let (generation, receiver) = tokio::time::timeout(SUBSCRIBE_TIMEOUT, response)
+            .await
+            .map_err(|_| hub_error("Upstream subscription timed out"))?
+            .map_err(|_| hub_error("Subscription hub is unavailable"))??;
+        Ok(HubSubscription {
+            key,
+            generation,
+            commands,
+            receiver,
+        })
+    }
+}
+
+fn hub_error(message: &str) -> Value {
+    json!({ "code": INTERNAL_ERROR_CODE, "message": message })
+}
+
+/// Session subscription to the shared topic, the session is unsubscribed
+/// from the topic when dropped
+pub struct HubSubscription {
+    key: String,
+    generation: u64,
+    commands: mpsc::UnboundedSender<HubCommand>,
+    receiver: broadcast::Receiver<Arc<HubNotification>>,
+}
+
+impl Drop for HubSubscription {
+    fn drop(&mut self) {
+        let _ = self.commands.send(HubCommand::Unsubscribe {
+            key: self.key.clone(),
+            generation: self.generation,
+        });
+    }
+}
+
+/// Shared topic subscription of the chain hub
+struct Topic {
+    topic: SharedTopic,
+    /// Distinguishes the topic from the dropped topics with the same key
+    generation: u64,
+    sender: broadcast::Sender<Arc<HubNotification>>,
+    subscribers: usize,
+    upstream_id: Option<Value>,
+    /// Sessions waiting for the upstream subscription confirmation
+    waiters: Vec<oneshot::Sender<SubscribeResult>>,
+}
+
+/// Upstream connection and the shared subscriptions of the chain
+struct ChainHub {
+    state: Arc<AppState>,
+    chain_id: String,
+    topics: HashMap<String, Topic>,
+    upstream: Option<WebSocketStream<ConnectStream>>,
+    provider_kind: Option<ProviderKind>,
+    /// Connect task running the attempts with the backoff outside of the hub
+    /// loop, so the commands are handled while connecting
+    connecting: Option<JoinHandle<ConnectResult>>,
+    /// Topic keys by the pending upstream subscribe request id
+    requests: HashMap<u64, String>,
+    /// Topic keys by the upstream subscription id
+    topic_keys: HashMap<String, String>,
+    next_request_id: u64,
+    next_generation: u64,
+}
+
+impl ChainHub {
+    fn new(state: Arc<AppState>, chain_id: String) -> Self {
+        Self {
+            state,
+            chain_id,
+            topics: HashMap::new(),
+            upstream: None,
+            provider_kind: None,
+            connecting: None,
+            requests: HashMap::new(),
+            topic_keys: HashMap::new(),
+            next_request_id: 0,
+            next_generation: 0,
+        }
+    }
+
+    async fn run(mut self, mut commands: mpsc::UnboundedReceiver<HubCommand>) {
+        loop {
+            if let Some(upstream) = self.upstream.as_mut() {
+                tokio::select! {
+                    command = commands.recv() => match command {
+                        Some(command) => self.handle_command(command).await,
+                        None => return,
+                    },
+                    message = upstream.next() => match message {
+                        Some(Ok(tungstenite::Message::Text(text))) => self.handle_upstream_message(&text).await,
+                        Some(Ok(message)) if !message.is_close() => {}
+                        _ => {
+                            warn!(
+                                "Subscription hub upstream {:?} connection lost for {}",
+                                self.provider_kind, self.chain_id
+                            );
+                            self.upstream = None;
+                            self.start_connect(reconnect_backoff(0));
+                        }
+                    },
+                }
+            } else if let Some(connecting) = self.connecting.as_mut() {
+                tokio::select! {
+                    command = commands.recv() => match command {
+                        Some(command) => self.handle_command(command).await,
+                        None => return,
+                    },
+                    connected = connecting => {
+                        self.connecting = None;
+                        self.on_connected(connected.ok().flatten()).await;
+                    }
+                }
+            } else {
+                // No upstream connection without the topics
+                match commands.recv().await {
+                    Some(command) => self.handle_command(command).await,
+                    None => return,
+                }
+            }
+        }
+    }
+
+    async fn handle_command(&mut self, command: HubCommand) {
+        match command {
+            HubCommand::Subscribe { topic, reply } => {
+                if let Some(active) = self.topics.get_mut(&topic.key) {
+                    active.subscribers += 1;
+                    if active.upstream_id.is_some() {
+                        // The session gave up waiting for the reply
+                        if reply
+                            .send(Ok((active.generation, active.sender.subscribe())))
+                            .is_err()
+                        {
+                            active.subscribers -= 1;
+                        }
+                    } else {
+                        active.waiters.push(reply);
+                    }
+                    return;
+                }
+
+                let key = topic.key.clone();
+                let (sender, _) = broadcast::channel(NOTIFICATIONS_CAPACITY);
+                self.next_generation += 1;
+                self.topics.insert(
+                    key.clone(),
+                    Topic {
+                        topic,
+                        generation: self.next_generation,
+                        sender,
+                        subscribers: 1,
+                        upstream_id: None,
+                        waiters: vec![reply],
+                    },
+                );
+                self.state
+                    .metrics
+                    .record_websocket_hub_topics(self.chain_id.clone(), self.topics.len());
+                // The topic is subscribed by the connect task when connected
+                if self.upstream.is_none() {
+                    self.start_connect(Duration::ZERO);
+                } else if !self.send_subscribe(&key).await {
+                    self.upstream = None;
+                    self.start_connect(reconnect_backoff(0));
+                }
+            }
+            HubCommand::Unsubscribe { key, generation } => {
+                self.release(&key, generation, 1).await;
+            }
+        }
+    }
+
+    /// Release the topic subscribers. The topic is unsubscribed upstream
+    /// without the subscribers and the upstream connection is closed without
+    /// the topics.
+    async fn release(&mut self, key: &str, generation: u64, released: usize) {
+        let Some(active) = self
+            .topics
+            .get_mut(key)
+            .filter(|active| active.generation == generation)
+        else {
+            return;
+        };
+        active.subscribers = active.subscribers.saturating_sub(released);
+        if active.subscribers > 0 {
+            return;
+        }
+        if let Some(active) = self.topics.remove(key) {
+            self.unsubscribe_upstream(active).await;
+        }
+        self.state
+            .metrics
+            .record_websocket_hub_topics(self.chain_id.clone(), self.topics.len());
+        if self.topics.is_empty() {
+            if let Some(mut upstream) = self.upstream.take() {
+                let _ = upstream.close(None).await;
+            }
+            self.requests.clear();
+            self.topic_keys.clear();
+        }
+    }
+
+    async fn unsubscribe_upstream(&mut self, active: Topic) {
+        let Some(upstream_id) = active.upstream_id else {
+            return;
+        };
+        self.topic_keys.remove(&upstream_id.to_string());
+        let Some(upstream) = self.upstream.as_mut() else {
+            return;
+        };
+        self.next_request_id += 1;
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": self.next_request_id,
+            "method": active.topic.unsubscribe_method(),
+            "params": [upstream_id],
+        });
+        if let Err(e) = upstream
+            .send(tungstenite::Message::Text(request.to_string()))
+            .await
+        {
+            debug!("Failed to unsubscribe the hub upstream subscription: {e}");
+        }
+    }
+
+    /// Send the upstream subscribe request for the topic,
+    /// returns `false` if the upstream connection failed
+    async fn send_subscribe(&mut self, key: &str) -> bool {
+        let (Some(upstream), Some(active)) = (self.upstream.as_mut(), self.topics.get(key)) else {
+            return false;
+        };
+        self.next_request_id += 1;
+        self.requests.insert(self.next_request_id, key.to_string());
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": self.next_request_id,
+            "method": active.topic.method,
+            "params": active.topic.params,
+        });
+        upstream
+            .send(tungstenite::Message::Text(request.to_string()))
+            .await
+            .is_ok()
+    }
+
+    /// Start the connect task unless it's already running, the task starts
+    /// after the `delay`. The topics are resubscribed when connected.
+    fn start_connect(&mut self, delay: Duration) {
+        self.requests.clear();
+        self.topic_keys.clear();
+        for active in self.topics.values_mut() {
+            active.upstream_id = None;
+        }
+        if self.connecting.is_some() {
+            return;
+        }
+        let excluded = self.provider_kind.take().into_iter().collect();
+        self.connecting = Some(tokio::spawn(connect_upstream(
+            self.state.clone(),
+            self.chain_id.clone(),
+            excluded,
+            delay,
+        )));
+    }
+
+    /// Subscribe to all the topics on the new upstream connection. The topics
+    /// are dropped if no provider is available.
+    async fn on_connected(&mut self, connected: ConnectResult) {
+        let Some((upstream, provider_kind)) = connected else {
+            warn!("Subscription hub failed to connect for {}", self.chain_id);
+            self.drop_topics();
+            return;
+        };
+        self.upstream = Some(upstream);
+        self.provider_kind = Some(provider_kind);
+        let keys = self.topics.keys().cloned().collect::<Vec<_>>();
+        for key in keys {
+            if !self.send_subscribe(&key).await {
+                self.upstream = None;
+                self.start_connect(reconnect_backoff(0));
+                return;
+            }
+        }
+        // All topics are unsubscribed while connecting
+        if self.topics.is_empty() {
+            if let Some(mut upstream) = self.upstream.take() {
+                let _ = upstream.close(None).await;
+            }
+        }
+    }
+
+    /// Drop all topics answering the waiting sessions with the error. Dropping
+    /// the topic closes its notifications channel, so the subscribed sessions
+    /// are notified by their forwarding tasks.
+    fn drop_topics(&mut self) {
+        for (_, active) in self.topics.drain() {
+            for waiter in active.waiters {
+                let _ = waiter.send(Err(hub_error("Upstream provider is unavailable")));
+            }
+        }
+        self.requests.clear();
+        self.topic_keys.clear();
+        self.state
+            .metrics
+            .record_websocket_hub_topics(self.chain_id.clone(), 0);
+    }
+
+    async fn handle_upstream_message(&mut self, text: &str) {
+        let Ok(message) = serde_json::from_str::<Value>(text) else {
+            return;
+        };
+
+        // Subscription notification
+        if let (Some(method), Some(params)) = (
+            message.get("method").and_then(Value::as_str),
+            message.get("params"),
+        ) {
+            let active = params
+                .get("subscription")
+                .and_then(|upstream_id| self.topic_keys.get(&upstream_id.to_string()))
+                .and_then(|key| self.topics.get(key));
+            if let Some(active) = active {
+                // No receivers error is expected when all sessions lag behind
+                let _ = active.sender.send(Arc::new(HubNotification {
+                    method: method.to_string(),
+                    result: params.get("result").cloned().unwrap_or(Value::Null),
+                }));
+            }
+            return;
+        }
+
+        // Upstream subscribe response
+        let Some(key) = message
+            .get("id")
+            .and_then(Value::as_u64)
+            .and_then(|id| self.requests.remove(&id))
+        else {
+            return;
+        };
+        let Some(active) = self.topics.get_mut(&key) else {
+            return;
+        };
+        match (message.get("result"), message.get("error")) {
+            (Some(upstream_id), None) => {
+                active.upstream_id = Some(upstream_id.clone());
+                self.topic_keys.insert(upstream_id.to_string(), key.clone());
+                // Sessions which gave up waiting for the confirmation are released
+                let generation = active.generation;
+                let timed_out = active
+                    .waiters
+                    .drain(..)
+                    .map(|waiter| waiter.send(Ok((generation, active.sender.subscribe()))))
+                    .filter(Result::is_err)
+                    .count();
+                if timed_out > 0 {
+                    self.release(&key, generation, timed_out).await;
+                }
+            }
+            (_, error) => {
+                let error = error
+                    .cloned()
+                    .unwrap_or_else(|| hub_error("Invalid subscription response"));
+                if let Some(active) = self.topics.remove(&key) {
+                    for waiter in active.waiters {
+                        let _ = waiter.send(Err(error.clone()));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Connect to the weighted WebSocket provider for the chain after the
+/// `delay`. The failed providers are excluded unless there are no other
+/// providers for the chain.
+async fn connect_upstream(
+    state: Arc<AppState>,
+    chain_id: String,
+    mut excluded: Vec<ProviderKind>,
+    delay: Duration,
+) -> ConnectResult {
+    tokio::time::sleep(delay).await;
+    for attempt in 0..MAX_CONNECT_ATTEMPTS {
+        let provider = state
+            .providers
+            .get_ws_provider_for_chain_id_excluding(&chain_id, &excluded)
+            .or_else(|| state.providers.get_ws_provider_for_chain_id(&chain_id))?;
+        let provider_kind = provider.provider_kind();
+        match provider.connect(&chain_id).await {
+            Ok(upstream) => return Some((upstream, provider_kind)),
+            Err(e) => warn!("Subscription hub failed to connect to {provider_kind}: {e}"),
+        }
+        excluded.push(provider_kind);
+        tokio::time::sleep(reconnect_backoff(attempt)).await;
+    }
+    None
+}
+
+/// Shared topics subscriptions of the client session. The notifications are
+/// forwarded to the session with the session's own subscription ids.
+pub struct HubSession {
+    state: Arc<AppState>,
+    chain_id: String,
+    notifications: mpsc::UnboundedSender<HubEvent>,
+    /// Notifications forwarding tasks by the client subscription id
+    subscriptions: HashMap<String, JoinHandle<()>>,
+}
+
+impl HubSession {
+    pub fn new(
+        state: Arc<AppState>,
+        chain_id: String,
+    ) -> (Self, mpsc::UnboundedReceiver<HubEvent>) {
+        let (notifications, receiver) = mpsc::unbounded_channel();
+        (
+            Self {
+                state,
+                chain_id,
+                notifications,
+                subscriptions: HashMap::new(),
+            },
+            receiver,
+        )
+    }
+
+    /// Handle the client request if it's a shared topic subscription or
+    /// unsubscription. The response is sent to the session as the hub event,
+    /// the subscription is confirmed without blocking the session. Returns
+    /// `false` if the request should be sent upstream.
+    pub fn handle_request(&mut self, text: &str) -> bool {
+        if !self.state.subscription_hub.is_enabled() {
+            return false;
+        }
+        let Ok(request) = serde_json::from_str::<Value>(text) else {
+            return false;
+        };
+        let (Some(id), Some(method)) = (
+            request.get("id").cloned(),
+            request.get("method").and_then(Value::as_str),
+        ) else {
+            return false;
+        };
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+        if method == "eth_unsubscribe" || method == "slotUnsubscribe" {
+            let Some(forwarding) = params
+                .get(0)
+                .and_then(|client_id| self.subscriptions.remove(&client_id.to_string()))
+            else {
+                return false;
+            };
+            forwarding.abort();
+            let response = json!({ "jsonrpc": "2.0", "id": id, "result": true });
+            let _ = self
+                .notifications
+                .send(HubEvent::Response(response.to_string()));
+            return true;
+        }
+
+        let Some(topic) = SharedTopic::from_request(method, &params) else {
+            return false;
+        };
+        let client_id = topic.new_client_id();
+        let forwarding = tokio::spawn(subscribe(
+            self.state.clone(),
+            self.chain_id.clone(),
+            topic,
+            id,
+            client_id.clone(),
+            self.notifications.clone(),
+        ));
+        self.subscriptions.insert(client_id.to_string(), forwarding);
+        true
+    }
+}
+
+impl HubSession {
+    /// Forget the subscription closed by the hub
+    pub fn on_closed(&mut self, client_id: &str) {
+        self.subscriptions.remove(client_id);
+    }
+}
+
+impl Drop for HubSession {
+    fn drop(&mut self) {
+        for forwarding in self.subscriptions.values() {
+            forwarding.abort();
+        }
+    }
+}
+
+/// Subscribe the session to the shared topic and forward the notifications
+/// after the subscription response. Dropping the task before the upstream
+/// subscription is confirmed releases the session subscriber.
+async fn subscribe(
+    state: Arc<AppState>,
+    chain_id: String,
+    topic: SharedTopic,
+    id: Value,
+    client_id: Value,
+    notifications: mpsc::UnboundedSender<HubEvent>,
+) {
+    let hub = &state.subscription_hub;
+    match hub.subscribe(&state, &chain_id, topic).await {
+        Ok(subscription) => {
+            state.metrics.add_websocket_hub_subscription(chain_id);
+            let response = json!({ "jsonrpc": "2.0", "id": id, "result": client_id });
+            if notifications
+                .send(HubEvent::Response(response.to_string()))
+                .is_ok()
+            {
+                forward_notifications(subscription, client_id, notifications).await;
+            }
+        }
+        Err(error) => {
+            let _ = notifications.send(HubEvent::SubscribeFailed {
+                client_id: client_id.to_string(),
+                response: json!({ "jsonrpc": "2.0", "id": id, "error": error }).to_string(),
+            });
+        }
+    }
+}
+
+/// Forward the shared topic notifications to the session with the session's
+/// subscription id. Dropping the task unsubscribes the session from the topic.
+/// The session is notified when the hub drops the topic.
+async fn forward_notifications(
+    mut subscription: HubSubscription,
+    client_id: Value,
+    notifications: mpsc::UnboundedSender<HubEvent>,
+) {
+    loop {
+        match subscription.receiver.recv().await {
+            Ok(notification) => {
+                let message = json!({
+                    "jsonrpc": "2.0",
+                    "method": notification.method,
+                    "params": { "subscription": client_id, "result": notification.result },
+                });
+                if notifications
+                    .send(HubEvent::Notification(message.to_string()))
+                    .is_err()
+                {
+                    return;
+                }
+            }
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!("WebSocket session lagged behind the subscription hub by {skipped} notifications");
+            }
+            Err(broadcast::error::RecvError::Closed) => {
+                let _ = notifications.send(HubEvent::Closed {
+                    client_id: client_id.to_string(),
+                });
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_topic_from_request() {
+        let new_heads = SharedTopic::from_request("eth_subscribe", &json!(["newHeads"])).unwrap();
+        assert_eq!(new_heads.key, "newHeads");
+        assert_eq!(new_heads.unsubscribe_method(), "eth_unsubscribe");
+        assert!(new_heads
+            .new_client_id()
+            .as_str()
+            .unwrap()
+            .starts_with("0x"));
+
+        // Identical logs filters share the topic regardless of the keys order
+        let logs = SharedTopic::from_request(
+            "eth_subscribe",
+            &serde_json::from_str(r#"["logs", {"address": "0x1", "topics": ["0x2"]}]"#).unwrap(),
+        )
+        .unwrap();
+        let same_logs = SharedTopic::from_request(
+            "eth_subscribe",
+            &serde_json::from_str(r#"["logs", {"topics": ["0x2"], "address": "0x1"}]"#).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(logs.key, same_logs.key);
+
+        let slot = SharedTopic::from_request("slotSubscribe", &json!([])).unwrap();
+        assert_eq!(slot.unsubscribe_method(), "slotUnsubscribe");
+        assert!(slot.new_client_id().is_u64());
+
+        // Client-specific subscriptions are not shared
+        assert!(
+            SharedTopic::from_request("eth_subscribe", &json!(["newPendingTransactions"]))
+                .is_none()
+        );
+        assert!(SharedTopic::from_request("accountSubscribe", &json!(["address"])).is_none());
+        assert!(SharedTopic::from_request("eth_blockNumber", &json!([])).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_dropped_topic_closes_session_subscription() {
+        let (sender, receiver) = broadcast::channel(NOTIFICATIONS_CAPACITY);
+        let (commands, _commands_receiver) = mpsc::unbounded_channel();
+        let (notifications, mut events) = mpsc::unbounded_channel();
+        let forwarding = tokio::spawn(forward_notifications(
+            HubSubscription {
+                key: "newHeads".to_string(),
+                generation: 1,
+                commands,
+                receiver,
+            },
+            json!("0xabc"),
+            notifications,
+        ));
+        sender
+            .send(Arc::new(HubNotification {
+                method: "eth_subscription".to_string(),
+                result: json!({ "number": "0x1" }),
+            }))
+            .unwrap();
+        drop(sender);
+        forwarding.await.unwrap();
+
+        assert!(matches!(
+            events.recv().await,
+            Some(HubEvent::Notification(_))
+        ));
+        assert_eq!(
+            events.recv().await,
+            Some(HubEvent::Closed {
+                client_id: json!("0xabc").to_string()
+            })
+        );
+    }
+}
@@ -0,0 +1,311 @@
+use {
+    crate::{
+        analytics::MessageInfo,
+        error::RpcError,
+        handlers::{proxy::access::AccessScope, RpcQueryParams},
+        json_rpc::{ErrorResponse, JsonRpcError, JsonRpcResponse, METHOD_NOT_FOUND_CODE},
+        providers::{ProviderKind, ProvidersConfig},
+        state::AppState,
+        utils::network,
+    },
+    axum::extract::ws::{close_code, CloseFrame, Message as AxumWsMessage},
+    hyper::HeaderMap,
+    serde_json::Value,
+    std::{collections::HashSet, net::SocketAddr, sync::Arc, time::Duration},
+    tokio::time::{interval_at, Instant, Interval},
+    tracing::log::warn,
+};
+
+/// Default interval of the project quota checks during the WebSocket session
+const DEFAULT_QUOTA_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Client frame after the methods filtering
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct FilteredFrame {
+    /// Frame with the allowed requests to send upstream
+    pub forward: Option<String>,
+    /// Error responses for the not allowed requests to send to the client
+    pub rejected: Option<String>,
+}
+
+/// Metering of the WebSocket session JSON-RPC requests with the same
+/// analytics and metrics as the HTTP proxy calls
+#[derive(Debug)]
+pub struct SessionMeter {
+    query_params: RpcQueryParams,
+    headers: HeaderMap,
+    origin: Option<Arc<str>>,
+    region: Option<Vec<String>>,
+    country: Option<Arc<str>>,
+    continent: Option<Arc<str>>,
+    /// Allowed methods, all methods are allowed if not set
+    allowed_methods: Option<HashSet<String>>,
+    /// Project tier for the method access tier rules
+    tier: Option<String>,
+    quota_check_interval: Duration,
+}
+
+impl SessionMeter {
+    pub fn new(
+        state: &AppState,
+        query_params: RpcQueryParams,
+        headers: HeaderMap,
+        addr: SocketAddr,
+        tier: Option<String>,
+    ) -> Self {
+        let origin = headers
+            .get("origin")
+            .map(|v| Arc::from(v.to_str().unwrap_or("invalid_header").to_string()));
+        let (country, continent, region) = state
+            .analytics
+            .lookup_geo_data(network::get_forwarded_ip(&headers).unwrap_or_else(|| addr.ip()))
+            .map(|geo| (geo.country, geo.continent, geo.region))
+            .unwrap_or((None, None, None));
+        Self {
+            query_params,
+            headers,
+            origin,
+            region,
+            country,
+            continent,
+            allowed_methods: allowed_methods(&state.config.providers),
+            tier,
+            quota_check_interval: state
+                .config
+                .providers
+                .ws_quota_check_interval_secs
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_QUOTA_CHECK_INTERVAL),
+        }
+    }
+
+    /// Timer of the periodic project quota checks, the quota is checked
+    /// before the upgrade so the first tick is delayed
+    pub fn quota_check_timer(&self) -> Interval {
+        interval_at(
+            Instant::now() + self.quota_check_interval,
+            self.quota_check_interval,
+        )
+    }
+
+    /// Check the project quota during the session. Returns the close message
+    /// for the client if the project is not allowed to continue.
+    pub async fn check_quota(&self, state: &AppState) -> Option<AxumWsMessage> {
+        let e = self.validate_quota(state).await.err()?;
+        warn!(
+            "Closing WebSocket client {} session: {e}",
+            self.query_params.project_id
+        );
+        state
+            .metrics
+            .add_websocket_quota_exceeded(self.query_params.chain_id.clone());
+        Some(AxumWsMessage::Close(Some(CloseFrame {
+            code: close_code::POLICY,
+            reason: e.to_string().into(),
+        })))
+    }
+
+    /// Validate the project quota the same way as for the HTTP proxy calls
+    async fn validate_quota(&self, state: &AppState) -> Result<(), RpcError> {
+        let project_id = &self.query_params.project_id;
+        if state
+            .config
+            .server
+            .skip_quota_chains
+            .contains(&self.query_params.chain_id)
+        {
+            state.validate_project_access(project_id).await
+        } else {
+            state.validate_project_access_and_quota(project_id).await
+        }
+    }
+
+    /// Filter out the methods of the client frame not allowed for the
+    /// WebSocket sessions or by the method access rules and record the
+    /// analytics for the allowed requests. Frames which are not JSON-RPC
+    /// requests are forwarded as is. The provider is `None` when the requests
+    /// are sent through the HTTP proxy calls which record the analytics.
+    pub fn on_client_frame(
+        &self,
+        state: &AppState,
+        provider_kind: Option<&ProviderKind>,
+        text: &str,
+    ) -> FilteredFrame {
+        let chain_id = self.query_params.chain_id.clone();
+        let requests = match serde_json::from_str::<Value>(text) {
+            Ok(Value::Array(requests)) => requests,
+            Ok(request @ Value::Object(_)) => vec![request],
+            _ => {
+                return FilteredFrame {
+                    forward: Some(text.to_string()),
+                    rejected: None,
+                }
+            }
+        };
+        let is_batch = text.trim_start().starts_with('[');
+
+        let (allowed, rejected): (Vec<_>, Vec<_>) = requests
+            .into_iter()
+            .partition(|request| self.is_allowed(request) && self.is_permitted(state, request));
+        let rejected = rejected
+            .into_iter()
+            .map(|request| {
+                let method = request_method(&request).unwrap_or_default().to_string();
+                state
+                    .metrics
+                    .add_websocket_method_rejected(chain_id.clone(), method.clone());
+                method_not_allowed_error(request.get("id").cloned().unwrap_or_default(), &method)
+            })
+            .collect::<Vec<_>>();
+
+        state.metrics.add_websocket_message(chain_id.clone());
+        if let Some(provider_kind) = provider_kind.filter(|_| !allowed.is_empty()) {
+            state.metrics.add_rpc_call(chain_id, provider_kind);
+            self.record_messages(state, provider_kind, &allowed);
+        }
+
+        if rejected.is_empty() {
+            return FilteredFrame {
+                forward: Some(text.to_string()),
+                rejected: None,
+            };
+        }
+        // Allowed requests of the batch are forwarded as a separate batch,
+        // so the client receives the rejected ones in their own response
+        let forward = (!allowed.is_empty()).then(|| Value::Array(allowed).to_string());
+        let rejected = if is_batch {
+            Value::Array(rejected).to_string()
+        } else {
+            rejected.into_iter().next().unwrap_or_default().to_string()
+        };
+        FilteredFrame {
+            forward,
+            rejected: Some(rejected),
+        }
+    }
+
+    fn record_messages(&self, state: &AppState, provider_kind: &ProviderKind, requests: &[Value]) {
+        for request in requests {
+            let (Some(id), Some(method)) = (request.get("id"), request_method(request)) else {
+                continue;
+            };
+            state.analytics.message(MessageInfo::new(
+                &self.query_params,
+                &self.headers,
+                self.query_params.session_id.clone(),
+                id.to_string(),
+                method.to_string(),
+                self.region.clone(),
+                self.country.clone(),
+                self.continent.clone(),
+                provider_kind,
+                self.origin.clone(),
+                self.query_params.sdk_info.sv.clone(),
+                self.query_params.sdk_info.st.clone(),
+            ));
+        }
+    }
+
+    /// Check the request against the method access rules the same way as
+    /// for the HTTP proxy calls
+    fn is_permitted(&self, state: &AppState, request: &Value) -> bool {
+        let Some(method) = request_method(request) else {
+            return true;
+        };
+        let scope = AccessScope {
+            project_id: &self.query_params.project_id,
+            chain_id: &self.query_params.chain_id,
+            tier: self.tier.as_deref(),
+        };
+        match state.method_access.check(method, &scope) {
+            Ok(()) => true,
+            Err(list) => {
+                state.metrics.add_rpc_method_denied(
+                    self.query_params.project_id.clone(),
+                    self.query_params.chain_id.clone(),
+                    list.as_str(),
+                );
+                false
+            }
+        }
+    }
+
+    fn is_allowed(&self, request: &Value) -> bool {
+        match (&self.allowed_methods, request_method(request)) {
+            (Some(allowed_methods), Some(method)) => allowed_methods.contains(method),
+            // Requests without the method are left for the upstream to reject
+            _ => true,
+        }
+    }
+}
+
+fn allowed_methods(config: &ProvidersConfig) -> Option<HashSet<String>> {
+    config
+        .ws_allowed_methods
+        .as_ref()
+        .map(|methods| methods.iter().cloned().collect())
+}
+
+fn request_method(request: &Value) -> Option<&str> {
+    request.get("method").and_then(Value::as_str)
+}
+
+fn method_not_allowed_error(id: Value, method: &str) -> Value {
+    serde_json::to_value(JsonRpcResponse::Error(JsonRpcError::new(
+        id,
+        ErrorResponse {
+            code: METHOD_NOT_FOUND_CODE,
+            message: format!("The method {method} is not allowed").into(),
+            data: None,
+        },
+    )))
+    .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, serde_json::json};
+
+    fn meter(allowed: &[&str]) -> SessionMeter {
+        SessionMeter {
+            query_params: serde_json::from_value(json!({
+                "chainId": "eip155:1",
+                "projectId": "test",
+            }))
+            .unwrap(),
+            headers: HeaderMap::new(),
+            origin: None,
+            region: None,
+            country: None,
+            continent: None,
+            allowed_methods: Some(allowed.iter().map(|m| m.to_string()).collect()),
+            tier: None,
+            quota_check_interval: DEFAULT_QUOTA_CHECK_INTERVAL,
+        }
+    }
+
+    #[test]
+    fn test_allowed_methods() {
+        let meter = meter(&["eth_blockNumber", "eth_subscribe"]);
+        assert!(meter.is_allowed(&json!({ "id": 1, "method": "eth_blockNumber" })));
+        assert!(!meter.is_allowed(&json!({ "id": 1, "method": "debug_traceTransaction" })));
+        // Requests without the method are not filtered
+        assert!(meter.is_allowed(&json!({ "id": 1 })));
+
+        let mut meter = meter;
+        meter.allowed_methods = None;
+        assert!(meter.is_allowed(&json!({ "id": 1, "method": "debug_traceTransaction" })));
+    }
+
+    #[test]
+    fn test_method_not_allowed_error() {
+        let error = method_not_allowed_error(json!(7), "debug_traceTransaction");
+        assert_eq!(error["id"], 7);
+        assert_eq!(error["error"]["code"], METHOD_NOT_FOUND_CODE);
+        assert_eq!(
+            error["error"]["message"],
+            "The method debug_traceTransaction is not allowed"
+        );
+    }
+}
@@ -0,0 +1,49 @@
+use {
+    async_tungstenite::tungstenite,
+    axum::extract::ws::Message as AxumWsMessage,
+    bytes::Bytes,
+    std::borrow::Cow,
+    tracing::log::debug,
+};
+
+pub mod emulation;
+pub mod hub;
+pub mod metering;
+pub mod session;
+
+/// Convert the client message to the upstream provider message
+pub fn to_upstream_message(msg: AxumWsMessage) -> tungstenite::Message {
+    match msg {
+        AxumWsMessage::Text(s) => tungstenite::Message::Text(s.to_string()),
+        AxumWsMessage::Binary(b) => tungstenite::Message::Binary(b.to_vec()),
+        AxumWsMessage::Ping(b) => tungstenite::Message::Ping(b.to_vec()),
+        AxumWsMessage::Pong(b) => tungstenite::Message::Pong(b.to_vec()),
+        AxumWsMessage::Close(frame) => {
+            tungstenite::Message::Close(frame.map(|f| tungstenite::protocol::CloseFrame {
+                code: f.code.into(),
+                reason: Cow::Owned(f.reason.to_string()),
+            }))
+        }
+    }
+}
+
+/// Convert the upstream provider message to the client message,
+/// `None` for the raw frames which are not relayed
+pub fn from_upstream_message(msg: tungstenite::Message) -> Option<AxumWsMessage> {
+    Some(match msg {
+        tungstenite::Message::Text(s) => AxumWsMessage::Text(s.into()),
+        tungstenite::Message::Binary(b) => AxumWsMessage::Binary(Bytes::from(b)),
+        tungstenite::Message::Ping(b) => AxumWsMessage::Ping(Bytes::from(b)),
+        tungstenite::Message::Pong(b) => AxumWsMessage::Pong(Bytes::from(b)),
+        tungstenite::Message::Close(frame) => {
+            AxumWsMessage::Close(frame.map(|f| axum::extract::ws::CloseFrame {
+                code: f.code.into(),
+                reason: f.reason.as_ref().into(),
+            }))
+        }
+        tungstenite::Message::Frame(_) => {
+            debug!("Received unhandled WebSocket raw frame type. Skipping.");
+            return None;
+        }
+    })
+}
@@ -0,0 +1,763 @@
+use {
+    super::{
+        from_upstream_message,
+        hub::{HubEvent, HubSession},
+        metering::SessionMeter,
+        to_upstream_message,
+    },
+    crate::{
+        handlers::RpcQueryParams,
+        json_rpc::{
+            ErrorResponse, JsonRpcError, JsonRpcResponse, INTERNAL_ERROR_CODE, PARSE_ERROR_CODE,
+        },
+        providers::{ProviderKind, RpcWsProvider},
+        state::AppState,
+    },
+    async_tungstenite::{tokio::ConnectStream, tungstenite, WebSocketStream},
+    axum::extract::ws::{close_code, CloseFrame, Message as AxumWsMessage, WebSocket},
+    futures_util::{SinkExt, StreamExt},
+    serde_json::{json, Value},
+    std::{collections::HashMap, sync::Arc, time::Duration},
+    tracing::log::{debug, warn},
+};
+
+const DEFAULT_MAX_RECONNECTS: u32 = 3;
+const RECONNECT_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_RECONNECT_BACKOFF: Duration = Duration::from_secs(10);
+/// Prefix of the internal request ids used to replay the subscriptions
+const REPLAY_ID_PREFIX: &str = "rpc-proxy-replay-";
+
+fn is_subscribe_method(method: &str) -> bool {
+    // `eth_subscribe` and Solana `*Subscribe` methods
+    method == "eth_subscribe" || method.ends_with("Subscribe")
+}
+
+fn is_unsubscribe_method(method: &str) -> bool {
+    method == "eth_unsubscribe" || method.ends_with("Unsubscribe")
+}
+
+/// Active subscription of the client
+#[derive(Debug)]
+struct Subscription {
+    /// Subscription id the client holds
+    client_id: Value,
+    /// Subscription id of the current upstream connection
+    upstream_id: Value,
+    method: String,
+    params: Value,
+}
+
+/// Client request awaiting the upstream response
+#[derive(Debug)]
+enum PendingRequest {
+    Call {
+        id: Value,
+    },
+    Subscribe {
+        id: Value,
+        method: String,
+        params: Value,
+    },
+    Unsubscribe {
+        id: Value,
+        subscription: String,
+    },
+    /// Internal request re-issuing the subscription after the failover
+    Replay {
+        subscription: String,
+    },
+}
+
+/// JSON-RPC state of the WebSocket session, which tracks the active
+/// subscriptions to replay them on the new upstream connection. Clients hold
+/// the subscription ids generated by the proxy, so the ids of different
+/// upstream connections never collide, and the upstream ids are remapped.
+#[derive(Debug, Default)]
+pub struct SubscriptionTracker {
+    pending: HashMap<String, PendingRequest>,
+    /// Active subscriptions by the client subscription id
+    subscriptions: HashMap<String, Subscription>,
+    /// Client subscription ids by the upstream subscription id
+    client_ids: HashMap<String, Value>,
+    next_replay_id: u64,
+    /// Client subscription id which replay was rejected by the new upstream
+    failed_replay: Option<String>,
+}
+
+impl SubscriptionTracker {
+    /// Track the client request and rewrite the unsubscribed subscription id
+    /// to the upstream one. Returns the message to send upstream.
+    pub fn on_client_request(&mut self, text: &str) -> String {
+        // Batch and invalid requests are passed as is without tracking
+        let Ok(mut request) = serde_json::from_str::<Value>(text) else {
+            return text.to_string();
+        };
+        let (Some(id), Some(method)) = (
+            request.get("id").cloned(),
+            request
+                .get("method")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+        ) else {
+            return text.to_string();
+        };
+        let key = id.to_string();
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+        if is_unsubscribe_method(&method) {
+            if let Some(client_id) = params.get(0) {
+                let subscription = client_id.to_string();
+                self.pending.insert(
+                    key,
+                    PendingRequest::Unsubscribe {
+                        id,
+                        subscription: subscription.clone(),
+                    },
+                );
+                if let Some(active) = self.subscriptions.get(&subscription) {
+                    if active.upstream_id != active.client_id {
+                        request["params"][0] = active.upstream_id.clone();
+                        return request.to_string();
+                    }
+                }
+                return text.to_string();
+            }
+        } else if is_subscribe_method(&method) {
+            self.pending
+                .insert(key, PendingRequest::Subscribe { id, method, params });
+            return text.to_string();
+        }
+
+        self.pending.insert(key, PendingRequest::Call { id });
+        text.to_string()
+    }
+
+    /// Track the upstream message and rewrite the subscription id in the
+    /// notifications. Returns the message to send to the client, `None` for
+    /// the internal replay responses. The rejected replay is reported by
+    /// `take_failed_replay`.
+    pub fn on_upstream_message(&mut self, text: &str) -> Option<String> {
+        let Ok(mut message) = serde_json::from_str::<Value>(text) else {
+            return Some(text.to_string());
+        };
+
+        // Subscription notification
+        if message.get("method").is_some() {
+            let client_id = message
+                .get("params")
+                .and_then(|params| params.get("subscription"))
+                .and_then(|upstream_id| {
+                    self.client_ids
+                        .get(&upstream_id.to_string())
+                        .filter(|client_id| *client_id != upstream_id)
+                })
+                .cloned();
+            if let Some(client_id) = client_id {
+                message["params"]["subscription"] = client_id;
+                return Some(message.to_string());
+            }
+            return Some(text.to_string());
+        }
+
+        let Some(id) = message.get("id") else {
+            return Some(text.to_string());
+        };
+        match self.pending.remove(&id.to_string()) {
+            Some(PendingRequest::Subscribe { method, params, .. }) => {
+                let Some(upstream_id) = message.get("result").filter(|result| !result.is_null())
+                else {
+                    return Some(text.to_string());
+                };
+                let upstream_id = upstream_id.clone();
+                let client_id = self.new_client_id(&upstream_id);
+                self.client_ids
+                    .insert(upstream_id.to_string(), client_id.clone());
+                self.subscriptions.insert(
+                    client_id.to_string(),
+                    Subscription {
+                        client_id: client_id.clone(),
+                        upstream_id,
+                        method,
+                        params,
+                    },
+                );
+                message["result"] = client_id;
+                Some(message.to_string())
+            }
+            Some(PendingRequest::Unsubscribe { subscription, .. }) => {
+                self.remove_subscription(&subscription);
+                Some(text.to_string())
+            }
+            Some(PendingRequest::Replay { subscription }) => {
+                let is_replayed = match (
+                    message.get("result"),
+                    self.subscriptions.get_mut(&subscription),
+                ) {
+                    (Some(upstream_id), Some(active)) => {
+                        active.upstream_id = upstream_id.clone();
+                        self.client_ids
+                            .insert(upstream_id.to_string(), active.client_id.clone());
+                        true
+                    }
+                    _ => false,
+                };
+                if !is_replayed {
+                    warn!("Failed to replay the subscription {subscription}: {text}");
+                    self.remove_subscription(&subscription);
+                    self.failed_replay = Some(subscription);
+                }
+                None
+            }
+            Some(PendingRequest::Call { .. }) | None => Some(text.to_string()),
+        }
+    }
+
+    /// Forget the pending requests of the lost upstream connection.
+    /// Returns the responses to the client for the requests left unanswered.
+    pub fn on_upstream_lost(&mut self) -> Vec<String> {
+        self.client_ids.clear();
+        let pending = std::mem::take(&mut self.pending);
+        pending
+            .into_values()
+            .filter_map(|request| match request {
+                PendingRequest::Call { id } | PendingRequest::Subscribe { id, .. } => {
+                    upstream_lost_error(id)
+                }
+                // The subscription is not replayed, so it's unsubscribed
+                PendingRequest::Unsubscribe { id, subscription } => {
+                    self.remove_subscription(&subscription);
+                    Some(json!({ "jsonrpc": "2.0", "id": id, "result": true }).to_string())
+                }
+                PendingRequest::Replay { .. } => None,
+            })
+            .collect()
+    }
+
+    /// Requests re-issuing the active subscriptions on the new upstream connection
+    pub fn replay_requests(&mut self) -> Vec<String> {
+        let mut requests = Vec::with_capacity(self.subscriptions.len());
+        for (subscription, active) in &self.subscriptions {
+            let id = format!("{REPLAY_ID_PREFIX}{}", self.next_replay_id);
+            self.next_replay_id += 1;
+            self.pending.insert(
+                Value::String(id.clone()).to_string(),
+                PendingRequest::Replay {
+                    subscription: subscription.clone(),
+                },
+            );
+            requests.push(
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "method": active.method,
+                    "params": active.params,
+                })
+                .to_string(),
+            );
+        }
+        requests
+    }
+
+    /// Unique client subscription id in the upstream id format, the numeric
+    /// ids for Solana and the hex string ids otherwise
+    fn new_client_id(&self, upstream_id: &Value) -> Value {
+        loop {
+            let client_id = if upstream_id.is_number() {
+                Value::from(rand::random::<u32>())
+            } else {
+                Value::String(format!("0x{:032x}", rand::random::<u128>()))
+            };
+            if !self.subscriptions.contains_key(&client_id.to_string()) {
+                return client_id;
+            }
+        }
+    }
+
+    /// Client subscription id which replay failed, the client is not
+    /// notified by this subscription anymore
+    pub fn take_failed_replay(&mut self) -> Option<String> {
+        self.failed_replay.take()
+    }
+
+    fn remove_subscription(&mut self, subscription: &str) {
+        if let Some(active) = self.subscriptions.remove(subscription) {
+            self.client_ids.remove(&active.upstream_id.to_string());
+        }
+    }
+}
+
+/// Text of the client Binary frame, or the error response if the frame is
+/// not valid UTF-8
+fn binary_frame_text(data: &[u8]) -> Result<String, String> {
+    String::from_utf8(data.to_vec()).map_err(|_| {
+        json!({
+            "jsonrpc": "2.0",
+            "id": null,
+            "error": { "code": PARSE_ERROR_CODE, "message": "Binary frame is not valid UTF-8" },
+        })
+        .to_string()
+    })
+}
+
+fn upstream_lost_error(id: Value) -> Option<String> {
+    serde_json::to_string(&JsonRpcResponse::Error(JsonRpcError::new(
+        id,
+        ErrorResponse {
+            code: INTERNAL_ERROR_CODE,
+            message: "Upstream provider connection lost".into(),
+            data: None,
+        },
+    )))
+    .ok()
+}
+
+/// Relay the messages between the client and the upstream provider.
+/// Without the `upstream` connection the session connects to the `provider`
+/// on the first client message not served by the subscription hub, so the
+/// sessions using only the shared topics don't hold their own upstream
+/// connections. On the upstream failure the session reconnects to another weighted
+/// WebSocket provider for the chain and replays the active subscriptions.
+/// Shareable subscriptions are served by the chain subscription hub when enabled.
+/// Client requests are metered and filtered by the allowed methods, and the
+/// project quota is re-checked periodically during the session.
+#[tracing::instrument(skip_all, fields(chain_id = %query_params.chain_id), level = "debug")]
+pub async fn run(
+    state: Arc<AppState>,
+    query_params: RpcQueryParams,
+    meter: SessionMeter,
+    client_ws: WebSocket,
+    provider: Arc<dyn RpcWsProvider>,
+    upstream: Option<WebSocketStream<ConnectStream>>,
+) {
+    let chain_id = query_params.chain_id.clone();
+    let project_id = query_params.project_id.clone();
+    let (mut client_sender, mut client_receiver) = client_ws.split();
+    let mut upstream = upstream;
+    let mut provider_kind = provider.provider_kind();
+    let mut tracker = SubscriptionTracker::default();
+    let (mut hub_session, mut hub_notifications) = HubSession::new(state.clone(), chain_id.clone());
+    let mut quota_check = meter.quota_check_timer();
+
+    loop {
+        tokio::select! {
+            message = client_receiver.next() => {
+                // Upstream nodes parse the Binary frames as JSON-RPC requests,
+                // so they are metered and filtered as the Text frames
+                let message = match message {
+                    Some(Ok(AxumWsMessage::Binary(data))) => match binary_frame_text(&data) {
+                        Ok(text) => Some(Ok(AxumWsMessage::Text(text.into()))),
+                        Err(rejected) => {
+                            if client_sender.send(AxumWsMessage::Text(rejected.into())).await.is_err() {
+                                debug!("WebSocket client {project_id} disconnected");
+                                break;
+                            }
+                            continue;
+                        }
+                    },
+                    message => message,
+                };
+                let message = match message {
+                    Some(Ok(AxumWsMessage::Close(_))) | Some(Err(_)) | None => {
+                        debug!("WebSocket client {project_id} disconnected");
+                        break;
+                    }
+                    Some(Ok(AxumWsMessage::Text(text))) => {
+                        let frame = meter.on_client_frame(&state, Some(&provider_kind), text.as_str());
+                        if let Some(rejected) = frame.rejected {
+                            if client_sender.send(AxumWsMessage::Text(rejected.into())).await.is_err() {
+                                debug!("WebSocket client {project_id} disconnected");
+                                break;
+                            }
+                        }
+                        let Some(text) = frame.forward else {
+                            continue;
+                        };
+                        if hub_session.handle_request(&text) {
+                            continue;
+                        }
+                        AxumWsMessage::Text(tracker.on_client_request(&text).into())
+                    }
+                    // Ping and Pong frames are relayed without the checks, but
+                    // not without the upstream connection
+                    Some(Ok(_)) if upstream.is_none() => continue,
+                    Some(Ok(message)) => message,
+                };
+                if upstream.is_none() {
+                    match connect(&state, &chain_id, provider.as_ref(), &mut tracker).await {
+                        Some((new_upstream, new_provider_kind)) => {
+                            upstream = Some(new_upstream);
+                            provider_kind = new_provider_kind;
+                        }
+                        None => {
+                            warn!("Failed to connect the WebSocket upstream for {chain_id}");
+                            let _ = client_sender.send(AxumWsMessage::Close(None)).await;
+                            return;
+                        }
+                    }
+                }
+                if let Some(upstream) = upstream.as_mut() {
+                    if upstream.send(to_upstream_message(message)).await.is_ok() {
+                        continue;
+                    }
+                }
+            }
+            message = next_upstream_message(&mut upstream) => {
+                match message {
+                    Some(Ok(tungstenite::Message::Text(text))) => {
+                        if let Some(text) = tracker.on_upstream_message(&text) {
+                            if client_sender.send(AxumWsMessage::Text(text.into())).await.is_err() {
+                                debug!("WebSocket client {project_id} disconnected");
+                                break;
+                            }
+                        }
+                        // The client resubscribes on the new session
+                        if let Some(subscription) = tracker.take_failed_replay() {
+                            warn!("Closing WebSocket client {project_id} session: subscription {subscription} replay failed");
+                            let _ = client_sender
+                                .send(AxumWsMessage::Close(Some(CloseFrame {
+                                    code: close_code::AGAIN,
+                                    reason: "Subscription replay failed".into(),
+                                })))
+                                .await;
+                            break;
+                        }
+                        continue;
+                    }
+                    Some(Ok(message)) if !message.is_close() => {
+                        if let Some(message) = from_upstream_message(message) {
+                            if client_sender.send(message).await.is_err() {
+                                debug!("WebSocket client {project_id} disconnected");
+                                break;
+                            }
+                        }
+                        continue;
+                    }
+                    // Closed or failed upstream connection
+                    _ => {}
+                }
+            }
+            _ = quota_check.tick() => {
+                if let Some(close) = meter.check_quota(&state).await {
+                    let _ = client_sender.send(close).await;
+                    break;
+                }
+                continue;
+            }
+            Some(event) = hub_notifications.recv() => match event {
+                HubEvent::Notification(message) | HubEvent::Response(message) => {
+                    if client_sender.send(AxumWsMessage::Text(message.into())).await.is_err() {
+                        debug!("WebSocket client {project_id} disconnected");
+                        break;
+                    }
+                    continue;
+                }
+                HubEvent::SubscribeFailed { client_id, response } => {
+                    hub_session.on_closed(&client_id);
+                    if client_sender.send(AxumWsMessage::Text(response.into())).await.is_err() {
+                        debug!("WebSocket client {project_id} disconnected");
+                        break;
+                    }
+                    continue;
+                }
+                HubEvent::Closed { client_id } => {
+                    // The client resubscribes on the new session
+                    hub_session.on_closed(&client_id);
+                    warn!("Closing WebSocket client {project_id} session: subscription {client_id} upstream is unavailable");
+                    let _ = client_sender
+                        .send(AxumWsMessage::Close(Some(CloseFrame {
+                            code: close_code::AGAIN,
+                            reason: "Subscription upstream is unavailable".into(),
+                        })))
+                        .await;
+                    break;
+                }
+            },
+        }
+
+        // The upstream connection is lost, so answer the pending requests
+        // and fail over to another provider
+        warn!("WebSocket upstream provider {provider_kind} connection lost for {chain_id}");
+        let mut client_gone = false;
+        for response in tracker.on_upstream_lost() {
+            if client_sender
+                .send(AxumWsMessage::Text(response.into()))
+                .await
+                .is_err()
+            {
+                client_gone = true;
+                break;
+            }
+        }
+        if client_gone {
+            break;
+        }
+        match reconnect(&state, &chain_id, &provider_kind, &mut tracker).await {
+            Some((new_upstream, new_provider_kind)) => {
+                upstream = Some(new_upstream);
+                provider_kind = new_provider_kind;
+            }
+            None => {
+                warn!("Failed to reconnect the WebSocket upstream for {chain_id}");
+                let _ = client_sender.send(AxumWsMessage::Close(None)).await;
+                return;
+            }
+        }
+    }
+    if let Some(mut upstream) = upstream {
+        let _ = upstream.close(None).await;
+    }
+}
+
+/// Next message of the upstream connection, never resolves without the
+/// connection
+async fn next_upstream_message(
+    upstream: &mut Option<WebSocketStream<ConnectStream>>,
+) -> Option<Result<tungstenite::Message, tungstenite::Error>> {
+    match upstream {
+        Some(upstream) => upstream.next().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Open the session upstream connection to the selected provider, another
+/// provider for the chain is used if the connection failed
+async fn connect(
+    state: &AppState,
+    chain_id: &str,
+    provider: &dyn RpcWsProvider,
+    tracker: &mut SubscriptionTracker,
+) -> Option<(WebSocketStream<ConnectStream>, ProviderKind)> {
+    let provider_kind = provider.provider_kind();
+    match provider.connect(chain_id).await {
+        Ok(upstream) => Some((upstream, provider_kind)),
+        Err(e) => {
+            warn!("Failed to connect to the WebSocket provider {provider_kind}: {e}");
+            reconnect(state, chain_id, &provider_kind, tracker).await
+        }
+    }
+}
+
+/// Connect to another weighted WebSocket provider for the chain and replay
+/// the active subscriptions. The failed providers are excluded unless there
+/// are no other providers for the chain.
+async fn reconnect(
+    state: &AppState,
+    chain_id: &str,
+    failed_provider: &ProviderKind,
+    tracker: &mut SubscriptionTracker,
+) -> Option<(WebSocketStream<ConnectStream>, ProviderKind)> {
+    let max_reconnects = state
+        .config
+        .providers
+        .ws_max_reconnects
+        .unwrap_or(DEFAULT_MAX_RECONNECTS);
+    let mut excluded = vec![failed_provider.clone()];
+
+    for attempt in 0..max_reconnects {
+        let provider = state
+            .providers
+            .get_ws_provider_for_chain_id_excluding(chain_id, &excluded)
+            .or_else(|| state.providers.get_ws_provider_for_chain_id(chain_id))?;
+        let provider_kind = provider.provider_kind();
+
+        match provider.connect(chain_id).await {
+            Ok(mut upstream) => {
+                let replay_requests = tracker.replay_requests();
+                let replayed = replay_requests.len();
+                let mut replay_failed = false;
+                for request in replay_requests {
+                    if upstream
+                        .send(tungstenite::Message::Text(request.into()))
+                        .await
+                        .is_err()
+                    {
+                        replay_failed = true;
+                        break;
+                    }
+                }
+                if !replay_failed {
+                    debug!(
+                        "WebSocket upstream for {chain_id} failed over to {provider_kind} \
+                         with {replayed} subscriptions replayed"
+                    );
+                    state
+                        .metrics
+                        .add_websocket_upstream_failover(chain_id.to_string(), &provider_kind);
+                    state.metrics.add_websocket_subscriptions_replayed(
+                        chain_id.to_string(),
+                        replayed as u64,
+                    );
+                    return Some((upstream, provider_kind));
+                }
+                // Replay requests are dropped with the failed connection
+                tracker.on_upstream_lost();
+                warn!("Failed to replay subscriptions to {provider_kind} for {chain_id}");
+            }
+            Err(e) => {
+                warn!("Failed to connect to the WebSocket provider {provider_kind}: {e}");
+            }
+        }
+        excluded.push(provider_kind);
+        tokio::time::sleep(reconnect_backoff(attempt)).await;
+    }
+    None
+}
+
+/// Exponential backoff of the reconnection attempt capped by the maximum
+pub(super) fn reconnect_backoff(attempt: u32) -> Duration {
+    RECONNECT_BACKOFF
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(MAX_RECONNECT_BACKOFF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Subscribe with the upstream subscription id, returns the client one
+    fn subscribe(tracker: &mut SubscriptionTracker, id: u64, subscription_id: &str) -> Value {
+        tracker.on_client_request(
+            &json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "method": "eth_subscribe",
+                "params": ["newHeads"],
+            })
+            .to_string(),
+        );
+        let response = tracker
+            .on_upstream_message(
+                &json!({ "jsonrpc": "2.0", "id": id, "result": subscription_id }).to_string(),
+            )
+            .unwrap();
+        parse(&response)["result"].clone()
+    }
+
+    fn notification(subscription_id: &str) -> String {
+        json!({
+            "jsonrpc": "2.0",
+            "method": "eth_subscription",
+            "params": { "subscription": subscription_id, "result": { "number": "0x1" } },
+        })
+        .to_string()
+    }
+
+    fn parse(text: &str) -> Value {
+        serde_json::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn test_binary_frame_text() {
+        let request = r#"{"jsonrpc":"2.0","id":1,"method":"eth_blockNumber"}"#;
+        assert_eq!(binary_frame_text(request.as_bytes()).unwrap(), request);
+
+        let error = binary_frame_text(&[0xff, 0xfe]).unwrap_err();
+        assert_eq!(parse(&error)["error"]["code"], PARSE_ERROR_CODE);
+        assert_eq!(parse(&error)["id"], Value::Null);
+    }
+
+    #[test]
+    fn test_subscriptions_replay_and_remap() {
+        let mut tracker = SubscriptionTracker::default();
+        let client_id = subscribe(&mut tracker, 1, "0xaaa");
+        assert_eq!(tracker.subscriptions.len(), 1);
+        assert_ne!(client_id, "0xaaa");
+        assert!(client_id.as_str().unwrap().starts_with("0x"));
+
+        // Upstream subscription id is remapped to the client one
+        let message = tracker.on_upstream_message(&notification("0xaaa")).unwrap();
+        assert_eq!(parse(&message)["params"]["subscription"], client_id);
+
+        // Pending requests are answered with an error on the upstream failure
+        tracker.on_client_request(r#"{"jsonrpc":"2.0","id":2,"method":"eth_blockNumber"}"#);
+        let responses = tracker.on_upstream_lost();
+        assert_eq!(responses.len(), 1);
+        assert_eq!(parse(&responses[0])["id"], 2);
+        assert_eq!(parse(&responses[0])["error"]["code"], INTERNAL_ERROR_CODE);
+
+        // Subscriptions are replayed and the replay responses are not relayed
+        let replay_requests = tracker.replay_requests();
+        assert_eq!(replay_requests.len(), 1);
+        let replay_request = parse(&replay_requests[0]);
+        assert_eq!(replay_request["method"], "eth_subscribe");
+        assert_eq!(replay_request["params"], json!(["newHeads"]));
+        let replay_response =
+            json!({ "jsonrpc": "2.0", "id": replay_request["id"], "result": "0xbbb" });
+        assert!(tracker
+            .on_upstream_message(&replay_response.to_string())
+            .is_none());
+
+        // New upstream subscription id is remapped to the client one
+        let message = tracker.on_upstream_message(&notification("0xbbb")).unwrap();
+        assert_eq!(parse(&message)["params"]["subscription"], client_id);
+
+        // Unsubscribe is sent with the upstream subscription id
+        let request = tracker.on_client_request(
+            &json!({
+                "jsonrpc": "2.0",
+                "id": 3,
+                "method": "eth_unsubscribe",
+                "params": [client_id],
+            })
+            .to_string(),
+        );
+        assert_eq!(parse(&request)["params"], json!(["0xbbb"]));
+        tracker.on_upstream_message(r#"{"jsonrpc":"2.0","id":3,"result":true}"#);
+        assert_eq!(tracker.subscriptions.len(), 0);
+    }
+
+    #[test]
+    fn test_new_subscription_after_failover_keeps_existing() {
+        let mut tracker = SubscriptionTracker::default();
+        let replayed_id = subscribe(&mut tracker, 1, "0x1");
+        tracker.on_upstream_lost();
+        let replay_request = parse(&tracker.replay_requests()[0]);
+        tracker.on_upstream_message(
+            &json!({ "jsonrpc": "2.0", "id": replay_request["id"], "result": "0x2" }).to_string(),
+        );
+
+        // The new upstream reuses the id the old upstream had assigned
+        let new_id = subscribe(&mut tracker, 2, "0x1");
+        assert_ne!(new_id, replayed_id);
+        assert_eq!(tracker.subscriptions.len(), 2);
+        let message = tracker.on_upstream_message(&notification("0x1")).unwrap();
+        assert_eq!(parse(&message)["params"]["subscription"], new_id);
+        let message = tracker.on_upstream_message(&notification("0x2")).unwrap();
+        assert_eq!(parse(&message)["params"]["subscription"], replayed_id);
+    }
+
+    #[test]
+    fn test_reconnect_backoff() {
+        assert_eq!(reconnect_backoff(0), RECONNECT_BACKOFF);
+        assert_eq!(reconnect_backoff(1), RECONNECT_BACKOFF * 2);
+        assert_eq!(reconnect_backoff(40), MAX_RECONNECT_BACKOFF);
+        assert_eq!(reconnect_backoff(u32::MAX), MAX_RECONNECT_BACKOFF);
+    }
+
+    #[test]
+    fn test_failed_replay_drops_subscription() {
+        let mut tracker = SubscriptionTracker::default();
+        let client_id = subscribe(&mut tracker, 1, "0xaaa");
+        tracker.on_upstream_lost();
+        let replay_request = parse(&tracker.replay_requests()[0]);
+        let replay_response = json!({
+            "jsonrpc": "2.0",
+            "id": replay_request["id"],
+            "error": { "code": -32000, "message": "subscriptions are not supported" },
+        });
+        assert!(tracker
+            .on_upstream_message(&replay_response.to_string())
+            .is_none());
+        assert_eq!(tracker.subscriptions.len(), 0);
+        assert_eq!(tracker.take_failed_replay(), Some(client_id.to_string()));
+        assert_eq!(tracker.take_failed_replay(), None);
+    }
+
+    #[test]
+    fn test_solana_subscription_methods() {
+        assert!(is_subscribe_method("accountSubscribe"));
+        assert!(!is_subscribe_method("accountUnsubscribe"));
+        assert!(is_unsubscribe_method("accountUnsubscribe"));
+        assert!(!is_subscribe_method("eth_blockNumber"));
+    }
+}